@@ -1,12 +1,14 @@
 //! Logic for plugins downloaded from Paper's hangar using the Hangar API.
 
-#[derive(serde::Deserialize, Clone, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
 pub struct ManifestHangarPlugin {
     slug: HangarSlug,
 }
 
 /// Describes a project on Hangar.
-#[derive(Clone, Debug, PartialEq, Eq, Hash, serde::Deserialize, dm::Into, dm::From)]
+#[derive(
+    Clone, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize, dm::Into, dm::From,
+)]
 pub struct HangarSlug(String);
 
 pub struct HangarResourceDetails {}
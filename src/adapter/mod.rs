@@ -1,18 +1,23 @@
 //! This module contains types that generalize plugins between different APIs.
 //! See submodules for API-specific types and logic.
 
-use std::borrow::Cow;
+use std::{borrow::Cow, cmp::Ordering, str::FromStr};
 
 use chrono::{DateTime, Utc};
 use ref_cast::RefCast;
 use rq::Url;
 use serde::ser::{SerializeMap, SerializeSeq};
 
+use crate::version::{
+    compare_version_names, parse_cutoff, ReleaseChannel, ReleaseChannelParseError, VersionPattern,
+    VersionReq, VersionReqParseError,
+};
+
 pub mod hangar;
 pub mod spiget;
 
 /// The number of fields in a serialized [`PluginVersion`].
-const PLUGIN_VERSION_SERIALIZED_FIELDS: usize = 4;
+const PLUGIN_VERSION_SERIALIZED_FIELDS: usize = 5;
 
 /// Represents a plugin version.
 ///
@@ -48,6 +53,25 @@ pub trait PluginVersion {
     /// May be [`None`] if no publishing datetime could be found.
     fn publish_date(&self) -> Option<DateTime<Utc>>;
 
+    /// The release channel of this version, like beta or snapshot.
+    ///
+    /// By default the channel is guessed from the version name with [`ReleaseChannel::from_version_name`].
+    /// Implementors for APIs that have explicit channels should return those instead.
+    #[inline]
+    fn channel(&self) -> ReleaseChannel {
+        ReleaseChannel::from_version_name(&self.version_name())
+    }
+
+    /// Compare this version with another version of the same plugin by version precedence (see [`compare_version_names`]),
+    /// instead of by publish date.
+    #[inline]
+    fn cmp_version(&self, other: &Self) -> Ordering
+    where
+        Self: Sized,
+    {
+        compare_version_names(&self.version_name(), &other.version_name())
+    }
+
     /// Generalized serialization for all [`PluginVersion`].
     ///
     /// Implementors of this trait should use the default implementation of this method,
@@ -65,6 +89,7 @@ pub trait PluginVersion {
         map.serialize_entry("version_identifier", self.version_identifier().as_ref())?;
         map.serialize_entry("version_name", self.version_name().as_ref())?;
         map.serialize_entry("download_url", self.download_url())?;
+        map.serialize_entry("channel", &self.channel())?;
 
         publish_date.map(|datetime| map.serialize_entry("publish_date", &datetime));
 
@@ -145,7 +170,16 @@ pub trait PluginDetails {
 
 /// The type of API that a plugin is sourced from.
 #[derive(
-    Copy, Clone, PartialEq, Eq, Debug, Hash, dm::Display, serde::Serialize, serde::Deserialize,
+    Copy,
+    Clone,
+    PartialEq,
+    Eq,
+    Debug,
+    Hash,
+    dm::Display,
+    serde::Serialize,
+    serde::Deserialize,
+    clap::ValueEnum,
 )]
 pub enum PluginApiType {
     #[display("Hangar")]
@@ -154,9 +188,19 @@ pub enum PluginApiType {
     Spiget,
 }
 
-/// A plugin version specification. Either a version name, a version identifier, or "latest" can be used to specify a version.
-/// This enum unifies all three ways into one type.
-#[derive(Debug, Clone, PartialEq, Eq, Hash, dm::Display)]
+/// Error for plugins from an API that isn't supported yet.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("Plugins from {0} are not supported yet.")]
+pub struct UnsupportedApiError(pub PluginApiType);
+
+/// A plugin version specification. A version can be specified by its name, its identifier, a requirement or regex that
+/// its name must match, or a date that it must be published before. "latest" specifies the most recent version.
+/// This enum unifies all of these ways into one type.
+///
+/// Version specs are written as strings like `latest`, `latest:beta`, `^2.3`, `before:2024-06-01` or `regex:^5\.`
+/// (see [`VersionSpec::from_str`]). Anything else is a version name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, dm::Display, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
 pub enum VersionSpec {
     /// A version name. The exact format of the name depends on the plugin and the plugin's API.
     #[display("{}", _0)]
@@ -164,9 +208,43 @@ pub enum VersionSpec {
     /// A version identifier. The exact format of the identifier depends on the plugin and the plugin's API.
     #[display("{}", _0)]
     Identifier(String),
-    /// The most recent version. Only get the most recent version, do not consider anything else.
-    #[display("latest")]
-    Latest,
+    /// The most recent version in the given release channel, or in a more stable channel.
+    /// For example, with [`ReleaseChannel::Beta`] the most recent beta or release is specified, but not snapshots.
+    #[display("{}", latest_display(*_0))]
+    Latest(ReleaseChannel),
+    /// The most recent version with a name that matches a semver-style requirement, like `^2.3`.
+    #[display("{}", _0)]
+    Requirement(VersionReq),
+    /// The most recent version that was published before a date.
+    #[display("before:{}", _0.format("%Y-%m-%dT%H:%M:%SZ"))]
+    Before(DateTime<Utc>),
+    /// The most recent version with a name that matches a regex.
+    #[display("regex:{}", _0)]
+    Pattern(VersionPattern),
+}
+
+/// The error returned by [`<VersionSpec as FromStr>::from_str`].
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+pub enum VersionSpecParseError {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Requirement(#[from] VersionReqParseError),
+    #[error("'{0}' is not a date like '2024-06-01' or '2024-06-01T12:00:00Z'")]
+    Date(String),
+    #[error("Invalid version name regex")]
+    Pattern(#[from] regex::Error),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Channel(#[from] ReleaseChannelParseError),
+}
+
+/// The string that [`VersionSpec::Latest`] is written as: `latest` for releases, and `latest:<channel>` otherwise.
+#[inline]
+fn latest_display(channel: ReleaseChannel) -> String {
+    match channel {
+        ReleaseChannel::Release => "latest".to_string(),
+        channel => format!("latest:{channel}"),
+    }
 }
 
 impl VersionSpec {
@@ -174,6 +252,81 @@ impl VersionSpec {
     /// (i.e., [`VersionSpec::Latest`])
     #[inline]
     pub fn is_latest(&self) -> bool {
-        matches!(self, Self::Latest)
+        matches!(self, Self::Latest(_))
+    }
+
+    /// Check if the given version is specified by this spec. Every version in the channel (or a more stable channel)
+    /// matches [`VersionSpec::Latest`].
+    #[inline]
+    pub fn matches<V: PluginVersion>(&self, version: &V) -> bool {
+        match self {
+            Self::Name(name) => version.version_name() == name.as_str(),
+            Self::Identifier(ident) => version.version_identifier() == ident.as_str(),
+            Self::Latest(channel) => version.channel() >= *channel,
+            Self::Requirement(req) => req.matches(&version.version_name()),
+            Self::Before(cutoff) => version
+                .publish_date()
+                .is_some_and(|publish_date| publish_date < *cutoff),
+            Self::Pattern(pattern) => pattern.matches(&version.version_name()),
+        }
+    }
+
+    /// Find the version that this spec specifies in the versions of a plugin, which must be ordered with the latest version first
+    /// (like by [`crate::version::VersionOrder`]).
+    /// This is the first version that matches the spec.
+    ///
+    /// Returns [`None`] if no version matches.
+    #[inline]
+    pub fn resolve<V: PluginVersion>(&self, versions: impl IntoIterator<Item = V>) -> Option<V> {
+        versions.into_iter().find(|version| self.matches(version))
+    }
+}
+
+impl FromStr for VersionSpec {
+    type Err = VersionSpecParseError;
+
+    /// Parse a version spec: `latest` (or `latest:<channel>`), a requirement starting with an operator (like `^2.3` or `>=1.2, <2`),
+    /// `before:<date>`, or `regex:<regex>`. Anything else is parsed as a version name.
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "latest" {
+            return Ok(Self::Latest(ReleaseChannel::Release));
+        }
+
+        if let Some(channel) = s.strip_prefix("latest:") {
+            return Ok(Self::Latest(ReleaseChannel::from_str(channel)?));
+        }
+
+        if let Some(date) = s.strip_prefix("before:") {
+            return parse_cutoff(date)
+                .map(Self::Before)
+                .ok_or_else(|| VersionSpecParseError::Date(date.to_string()));
+        }
+
+        if let Some(pattern) = s.strip_prefix("regex:") {
+            return Ok(Self::Pattern(VersionPattern::from_str(pattern)?));
+        }
+
+        if VersionReq::is_requirement(s) {
+            return Ok(Self::Requirement(VersionReq::from_str(s)?));
+        }
+
+        Ok(Self::Name(s.to_string()))
+    }
+}
+
+impl TryFrom<String> for VersionSpec {
+    type Error = VersionSpecParseError;
+
+    #[inline]
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value)
+    }
+}
+
+impl From<VersionSpec> for String {
+    #[inline]
+    fn from(value: VersionSpec) -> Self {
+        value.to_string()
     }
 }
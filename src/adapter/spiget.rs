@@ -22,16 +22,138 @@ use tokio::sync::{RwLock, RwLockReadGuard};
 use uuid::Uuid;
 
 use crate::{
-    error::{NotFoundError, ParseError, UnexpectedHttpStatus},
-    session::IoSession,
+    compat::{Incompatibility, ServerTarget},
+    error::{
+        diagnostics, NotCachedError, NotFoundError, ParseError, RequestError, UnexpectedHttpStatus,
+    },
+    jar::JarMetadata,
+    manifest::{suggest_plugin_name, Manifest, ManifestEntryError},
+    session::{DownloadError, DownloadSpec, IoSession},
+    util::closest_matches,
+    version::{ReleaseChannel, UpdatePolicy, VersionOrder},
 };
 
 use super::{PluginApiType, PluginDetails, PluginVersion, VersionSpec};
 
-/// A Spiget plugin entry in the manifest.
-#[derive(serde::Deserialize, Clone, Debug)]
+/// A Spiget plugin entry in the manifest. Created with only the resource ID by [`ManifestSpigetPlugin::new`].
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug, new)]
 pub struct ManifestSpigetPlugin {
     pub resource_id: ResourceId,
+    /// The name of the version to pin the plugin to, instead of using the latest version.
+    #[new(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The ID of the version to pin the plugin to. Takes precedence over a pinned version name.
+    #[new(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_id: Option<VersionId>,
+    /// A version spec (like `^2.3` or `before:2024-06-01`) that the plugin resolves to the most recent matching version of.
+    /// Only used if the plugin isn't pinned to a version name or ID.
+    #[new(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_spec: Option<VersionSpec>,
+    /// The least stable release channel (like `beta`) that the latest version of the plugin can be from.
+    /// Only used if the plugin isn't pinned. Defaults to releases only.
+    #[new(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_channel: Option<ReleaseChannel>,
+    /// How the versions of the plugin are ordered when finding the latest (matching) version.
+    /// Defaults to ordering by publish date.
+    #[new(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version_order: Option<VersionOrder>,
+    /// Which updates the 'update' subcommand makes to the version that the plugin is pinned to, without asking.
+    /// Defaults to any update.
+    #[new(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_policy: Option<UpdatePolicy>,
+}
+
+impl ManifestSpigetPlugin {
+    /// The version that the plugin is pinned to in the manifest, or [`VersionSpec::Latest`] if it isn't pinned.
+    #[inline]
+    pub fn pinned_version(&self) -> VersionSpec {
+        match (self.version_id, &self.version, &self.version_spec) {
+            (Some(version_id), _, _) => VersionSpec::Identifier(version_id.to_string()),
+            (None, Some(version_name), _) => VersionSpec::Name(version_name.clone()),
+            (None, None, Some(version_spec)) => version_spec.clone(),
+            (None, None, None) => VersionSpec::Latest(self.min_channel.unwrap_or_default()),
+        }
+    }
+
+    /// The manifest key that the plugin is pinned with (`version_id`, `version` or `version_spec`), if it is pinned.
+    #[inline]
+    pub fn pin_key(&self) -> Option<&'static str> {
+        match (self.version_id, &self.version, &self.version_spec) {
+            (Some(_), _, _) => Some("version_id"),
+            (None, Some(_), _) => Some("version"),
+            (None, None, Some(_)) => Some("version_spec"),
+            (None, None, None) => None,
+        }
+    }
+
+    /// Check if the given URL is a Spiget API endpoint for this plugin's resource
+    /// (like `/resources/{resource_id}` or `/resources/{resource_id}/versions`).
+    /// Only the path is checked, so that endpoints of Spiget mirrors are matched too.
+    #[inline]
+    pub fn is_resource_api_url(&self, url: &Url) -> bool {
+        let resource_id = self.resource_id.to_string();
+        let Some(segments) = url.path_segments() else {
+            return false;
+        };
+
+        segments
+            .collect::<Vec<_>>()
+            .windows(2)
+            .any(|window| window == ["resources", resource_id.as_str()])
+    }
+}
+
+/// Error for resources that can't be downloaded with the Spiget API.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+pub enum UndownloadableResourceError {
+    /// Premium resources can only be downloaded from SpigotMC by users who bought them.
+    #[error("Premium resources cannot be downloaded with the Spiget API.")]
+    #[diagnostic(help(
+        "Premium resources have to be downloaded manually from their resource page on SpigotMC."
+    ))]
+    Premium,
+    /// External resources are hosted on another site that the Spiget API can't download from.
+    #[error("External resources cannot be downloaded with the Spiget API. The resource is hosted at '{url}'.")]
+    #[diagnostic(help(
+        "External resources have to be downloaded manually from the site that hosts them."
+    ))]
+    External { url: String },
+}
+
+impl UndownloadableResourceError {
+    /// A short label for the resource, like "this is a premium resource".
+    #[inline]
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Premium => "this is a premium resource",
+            Self::External { .. } => "this is an external resource",
+        }
+    }
+}
+
+/// Error for when none of the most recent versions of a plugin are compatible with the server.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error(
+    "None of the {checked} latest {} of plugin '{plugin_name}' that were checked are compatible with the server. The latest version is '{latest}'.",
+    if *.checked == 1 { "version" } else { "versions" }
+)]
+#[diagnostic(help(
+    "Pin the plugin to a version that is compatible with the server with the 'pin' subcommand."
+))]
+pub struct IncompatibleVersionsError {
+    pub plugin_name: String,
+    /// The number of versions that were checked, which are all incompatible.
+    pub checked: usize,
+    pub latest: String,
+    /// Why the latest version is incompatible.
+    #[source]
+    pub incompatibility: Incompatibility,
 }
 
 /// A resource ID for a Spigot resource.
@@ -45,6 +167,7 @@ pub struct ManifestSpigetPlugin {
     dm::Into,
     dm::From,
     serde::Deserialize,
+    serde::Serialize,
     dm::Display,
     dm::Constructor,
 )]
@@ -57,6 +180,26 @@ impl ResourceId {
     pub fn plugin_page(&self) -> Url {
         Url::parse(&format!("https://www.spigotmc.org/resources/{}", self.0)).unwrap()
     }
+
+    /// Get the resource ID from the URL of a resource page on the Spigot website,
+    /// like `https://www.spigotmc.org/resources/gsit.62325/` or `https://spigotmc.org/resources/62325`.
+    ///
+    /// Returns [`None`] if the URL is not a resource page.
+    #[inline]
+    pub fn from_plugin_page(url: &Url) -> Option<Self> {
+        let host = url.host_str()?;
+        if host != "spigotmc.org" && !host.ends_with(".spigotmc.org") {
+            return None;
+        }
+
+        let mut segments = url.path_segments()?;
+        segments.find(|&segment| segment == "resources")?;
+
+        // the slug is optional, the ID is always after the last dot
+        let resource = segments.next()?;
+        let id = resource.rsplit('.').next()?;
+        Self::from_str(id).ok()
+    }
 }
 
 impl FromStr for ResourceId {
@@ -79,6 +222,7 @@ impl FromStr for ResourceId {
     dm::Into,
     dm::From,
     serde::Deserialize,
+    serde::Serialize,
     dm::Display,
     dm::Constructor,
 )]
@@ -121,7 +265,8 @@ pub struct SpigetResourceJson {
     pub external: bool,
     pub versions: Vec<TinyVersionJson>,
     // we don't have a resource icon field, since this is a CLI app
-    // we don't include information regarding the premium status of a resource because im lazy
+    #[serde(default)]
+    pub premium: bool,
     pub source_code_link: Option<String>,
     pub donation_link: Option<String>,
 }
@@ -175,11 +320,43 @@ pub struct SpigetRatingJson {
     pub average: f64,
 }
 
+/// Model for a resource in the search results of the Spiget API.
+/// Only has the fields that are requested by [`SpigetApiClient::search_resources`].
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SpigetSearchResultJson {
+    pub id: ResourceId,
+    pub name: String,
+    #[serde(default)]
+    pub tag: String,
+    pub author: SpigetAuthorJson,
+    pub downloads: u64,
+    pub rating: SpigetRatingJson,
+    #[serde(deserialize_with = "chrono::serde::ts_seconds::deserialize")]
+    pub update_date: chrono::DateTime<Utc>,
+    #[serde(default)]
+    pub tested_versions: Vec<String>,
+    #[serde(default)]
+    pub premium: bool,
+    #[serde(default)]
+    pub external: bool,
+}
+
+/// Model for the author of a Spigot resource.
+/// Authors in resource objects only have an ID, the name is only included by the `/authors/{author_id}` endpoint.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct SpigetAuthorJson {
+    pub id: u64,
+    pub name: Option<String>,
+}
+
 /// A client for communicating with the Spiget API.
 #[derive(Clone, Debug)]
 pub struct SpigetApiClient {
     client: ClientWithMiddleware,
     spiget_base_url: Url,
+    /// Whether the client is only allowed to read responses from the HTTP cache.
+    offline: bool,
 }
 
 /// Essentially a more verbose variant of [`SpigetVersionJson`]. Implements [`crate::adapter::PluginVersion`], so this type can be used in more general contexts.
@@ -259,13 +436,17 @@ pub type SpigetApiResult<T> = miette::Result<T>;
 //  this will be more cache-friendly and much, much, much simpler
 #[allow(dead_code)]
 impl SpigetApiClient {
-    /// Create a new API client, wrapping the given [`reqwest::Client`].
+    /// Create a new API client, wrapping the given [`reqwest::Client`] and making requests to the Spiget API at `base_url`.
+    ///
+    /// If `offline` is true, the wrapped client is expected to only read responses from the HTTP cache,
+    /// and cache misses will be reported as [`NotCachedError::Response`].
     #[inline]
     #[must_use]
-    pub fn new(client: &ClientWithMiddleware) -> Self {
+    pub fn new(client: &ClientWithMiddleware, base_url: Url, offline: bool) -> Self {
         Self {
             client: client.clone(),
-            spiget_base_url: Url::parse(BASE_URL).unwrap(),
+            spiget_base_url: base_url,
+            offline,
         }
     }
 
@@ -287,11 +468,23 @@ impl SpigetApiClient {
             .wrap_err("Error building request for Spiget API")?;
         let url = request.url().clone();
 
-        self.client
+        let response = self
+            .client
             .execute(request)
             .await
-            .into_diagnostic()
-            .wrap_err_with(|| format!("Spiget API error with URL '{url}'"))
+            .map_err(|source| RequestError {
+                url: url.clone(),
+                source,
+            })
+            .wrap_err("Spiget API error")?;
+
+        // the HTTP cache responds with a gateway timeout if it's only allowed to use cached responses
+        // and there was no cached response for this request
+        if self.offline && response.status() == StatusCode::GATEWAY_TIMEOUT {
+            return Err(NotCachedError::Response(url).into());
+        }
+
+        Ok(response)
     }
 
     /// Compute the download URL for a given version of a given resource.
@@ -432,6 +625,53 @@ impl SpigetApiClient {
         .wrap_err_with(|| format!("Error getting latest version of resource '{resource_id}'"))
     }
 
+    /// Search for resources with names matching `query` with the `/search/resources/{query}` endpoint,
+    /// starting with the most downloaded resource.
+    /// The parameter `size` determines the maximum length of the returned list.
+    ///
+    /// The authors of the returned resources only have an ID. The returned vector is empty if no resources matched the query.
+    #[inline]
+    pub async fn search_resources(
+        &self,
+        query: &str,
+        size: u64,
+    ) -> SpigetApiResult<Vec<SpigetSearchResultJson>> {
+        let mut url = self.endpoint_url("search/resources/").unwrap();
+        url.path_segments_mut()
+            .expect("the Spiget API URL is a base URL")
+            .pop_if_empty()
+            .push(query);
+        url.set_query(Some(&format!(
+            "field=name&size={size}&sort=-downloads&fields=id,name,tag,author,downloads,rating,updateDate,testedVersions,premium,external"
+        )));
+
+        let req = self.client.get(url);
+        let response = self.send_request(req).await?;
+
+        match response.status() {
+            StatusCode::OK => Self::parse_response(response).await,
+            // the API responds with 404 if nothing matched the query
+            StatusCode::NOT_FOUND => Ok(Vec::new()),
+            status => Err(UnexpectedHttpStatus(status).into()),
+        }
+        .wrap_err_with(|| format!("Error searching for resources matching '{query}'"))
+    }
+
+    /// Get an author from the `/authors/{author_id}` endpoint.
+    #[inline]
+    pub async fn author(&self, author_id: u64) -> SpigetApiResult<SpigetAuthorJson> {
+        let url = self.endpoint_url(&format!("authors/{author_id}")).unwrap();
+
+        let req = self.client.get(url);
+        let response = self.send_request(req).await?;
+
+        match response.status() {
+            StatusCode::OK => Self::parse_response(response).await,
+            status => Err(UnexpectedHttpStatus(status).into()),
+        }
+        .wrap_err_with(|| format!("Error getting author '{author_id}'"))
+    }
+
     /// Get the URL to download the provided version of the provided resource.
     /// This method performs validation to ensure that the requested version is actually valid for this resource, and that the requested resource exists in the first place.
     ///
@@ -486,6 +726,105 @@ async fn versions_map(
     Ok(IndexMap::from_iter(versions.into_iter().map(|v| (v.id, v))))
 }
 
+/// Search for resources with names matching `query`, starting with the most downloaded resource.
+/// At most `limit` resources are returned.
+///
+/// The authors of the resources only have an ID, their names can be fetched with [`fetch_author_names`].
+#[inline]
+pub async fn search_resources(
+    session: &IoSession,
+    query: &str,
+    limit: u64,
+) -> SpigetApiResult<Vec<SpigetSearchResultJson>> {
+    session
+        .spiget_api()
+        .search_resources(query, limit)
+        .await
+        .wrap_err("Error with Spiget API")
+}
+
+/// Fetch the names of the authors of the given search results, with one request per author.
+/// If the name of an author couldn't be fetched, it's left out.
+#[inline]
+pub async fn fetch_author_names(session: &IoSession, results: &mut [SpigetSearchResultJson]) {
+    let mut author_ids = results
+        .iter()
+        .map(|result| result.author.id)
+        .collect::<Vec<_>>();
+    author_ids.sort_unstable();
+    author_ids.dedup();
+
+    let authors = futures::future::join_all(
+        author_ids
+            .iter()
+            .map(|&author_id| session.spiget_api().author(author_id)),
+    )
+    .await;
+
+    let mut names = HashMap::new();
+    for (author_id, author) in author_ids.into_iter().zip(authors) {
+        match author {
+            Ok(author) => {
+                names.insert(author_id, author.name);
+            }
+            Err(error) => log::debug!("Could not get author {author_id}: {error:?}"),
+        }
+    }
+
+    for result in results {
+        if let Some(name) = names.get(&result.author.id) {
+            result.author.name.clone_from(name);
+        }
+    }
+}
+
+/// The number of the most recent versions of a plugin that [`SpigetPlugin::resolve_version`] checks for compatibility with the server.
+const COMPATIBLE_VERSION_CANDIDATES: usize = 5;
+
+/// The maximum number of search results that are considered by [`find_resource`].
+const FIND_RESOURCE_SEARCH_LIMIT: u64 = 10;
+
+/// Find the resource of a plugin by the name of the plugin (like the name in its plugin descriptor).
+///
+/// Resources are searched for by the name, and the most downloaded resource with the same name is returned, preferring downloadable resources
+/// and resources by one of the given authors. Names are compared without tags, taglines, casing or separators, so that "WorldEdit" matches "World Edit".
+///
+/// Returns [`None`] if no resource matched.
+#[inline]
+pub async fn find_resource(
+    session: &IoSession,
+    name: &str,
+    authors: &[String],
+) -> SpigetApiResult<Option<SpigetSearchResultJson>> {
+    let comparable_name = |name: &str| suggest_plugin_name(name).replace('-', "");
+
+    let plugin_name = comparable_name(name);
+    let mut candidates = search_resources(session, name, FIND_RESOURCE_SEARCH_LIMIT)
+        .await?
+        .into_iter()
+        .filter(|resource| comparable_name(&resource.name) == plugin_name)
+        .collect::<Vec<_>>();
+
+    // the authors only matter for choosing between several resources with the same name
+    if !authors.is_empty() && candidates.len() > 1 {
+        fetch_author_names(session, &mut candidates).await;
+    }
+
+    // the sort is stable, so the most downloaded resources stay first
+    candidates.sort_by_key(|resource| {
+        let downloadable = !resource.premium && !resource.external;
+        let by_author = resource.author.name.as_ref().is_some_and(|author| {
+            authors
+                .iter()
+                .any(|plugin_author| plugin_author.eq_ignore_ascii_case(author))
+        });
+
+        (!downloadable, !by_author)
+    });
+
+    Ok(candidates.into_iter().next())
+}
+
 /// Map of version IDs and the JSON for those versions.
 pub type SpigetVersionMap = IndexMap<VersionId, SpigetVersionJson>;
 
@@ -496,6 +835,8 @@ pub struct SpigetPlugin {
     resource_details: SpigetResourceJson,
     /// Cached version details. Ordered by release date, with the latest version first.
     cached_versions: Arc<IndexMap<VersionId, SpigetVersionJson>>,
+    /// How versions are ordered when resolving version specs.
+    version_order: VersionOrder,
 }
 
 impl SpigetPlugin {
@@ -519,14 +860,127 @@ impl SpigetPlugin {
             io: session.clone(),
             cached_versions: Arc::new(versions),
             resource_details,
+            version_order: VersionOrder::default(),
         })
     }
 
+    /// Like [`SpigetPlugin::new`], for the plugin with the given name in the manifest.
+    ///
+    /// The versions of the plugin are ordered like the plugin's `version_order` in the manifest.
+    ///
+    /// If the resource doesn't exist, the error points at the resource ID of the plugin in the manifest source.
+    #[inline]
+    pub async fn from_manifest(
+        session: &IoSession,
+        manifest: &Manifest,
+        plugin_name: &str,
+        plugin: &ManifestSpigetPlugin,
+    ) -> SpigetApiResult<SpigetPlugin> {
+        let error = match Self::new(session, plugin.resource_id).await {
+            Ok(spiget_plugin) => {
+                let version_order = plugin.version_order.unwrap_or_default();
+                return Ok(spiget_plugin.with_version_order(version_order));
+            }
+            Err(error) => error,
+        };
+
+        let not_found = error.chain().any(|cause| {
+            matches!(
+                cause.downcast_ref::<NotFoundError>(),
+                Some(NotFoundError::PluginInApi)
+            )
+        });
+
+        if !not_found {
+            return Err(error);
+        }
+
+        Err(manifest
+            .entry_error(
+                plugin_name,
+                Some("resource_id"),
+                "no Spigot resource has this ID",
+                NotFoundError::PluginInApi,
+            )
+            .with_help(
+                "The resource ID is the number at the end of the URL of the resource page, like 'https://www.spigotmc.org/resources/<name>.<ID>/'.",
+            )
+            .into())
+    }
+
+    /// Set how versions are ordered when resolving version specs.
+    #[inline]
+    pub fn with_version_order(mut self, version_order: VersionOrder) -> Self {
+        self.version_order = version_order;
+        self
+    }
+
     #[inline]
     pub fn resource_id(&self) -> ResourceId {
         self.resource_details.id
     }
 
+    /// Check if the resource is a premium resource. Premium resources cannot be downloaded with the Spiget API.
+    #[inline]
+    pub fn is_premium(&self) -> bool {
+        self.resource_details.premium
+    }
+
+    /// Check that the resource can be downloaded with the Spiget API (i.e., it's not a premium or external resource).
+    #[inline]
+    pub fn downloadable(&self) -> Result<(), UndownloadableResourceError> {
+        if self.is_premium() {
+            return Err(UndownloadableResourceError::Premium);
+        }
+
+        if self.resource_details.external {
+            let file = &self.resource_details.file;
+            let url = file
+                .external_url
+                .clone()
+                .unwrap_or_else(|| file.url.clone());
+
+            return Err(UndownloadableResourceError::External { url });
+        }
+
+        Ok(())
+    }
+
+    /// Like [`SpigetPlugin::downloadable`], for the plugin with the given name in the manifest.
+    /// The error points at the resource ID of the plugin in the manifest source.
+    #[inline]
+    pub fn check_downloadable(
+        &self,
+        manifest: &Manifest,
+        plugin_name: &str,
+    ) -> Result<(), ManifestEntryError> {
+        let Err(error) = self.downloadable() else {
+            return Ok(());
+        };
+
+        let help = miette::Diagnostic::help(&error).map(|help| help.to_string());
+        let mut error =
+            manifest.entry_error(plugin_name, Some("resource_id"), error.label(), error);
+        if let Some(help) = help {
+            error = error.with_help(help);
+        }
+
+        Err(error)
+    }
+
+    /// The name of the resource.
+    #[inline]
+    pub fn resource_name(&self) -> &str {
+        &self.resource_details.name
+    }
+
+    /// The Minecraft versions that the author tested the resource with (like `1.21`).
+    /// These apply to the resource as a whole, not to a specific version.
+    #[inline]
+    pub fn tested_versions(&self) -> &[String] {
+        &self.resource_details.tested_versions
+    }
+
     /// Try getting a version from the version cache.
     #[inline]
     fn get_cached_version(&self, version_id: VersionId) -> Option<&SpigetVersionJson> {
@@ -542,13 +996,30 @@ impl SpigetPlugin {
         }
     }
 
-    /// Get the latest version of this plugin.
+    /// The versions of this plugin in the given order, with the latest version first.
+    /// Versions with the same precedence are ordered by release date.
+    #[inline]
+    pub fn sorted_versions(&self, order: VersionOrder) -> Vec<SpigetResourceVersion> {
+        let mut versions = self.iter_versions().collect::<Vec<_>>();
+        if order == VersionOrder::Version {
+            // the sort is stable, so the release date order is kept for equal versions
+            versions.sort_by(|a, b| b.cmp_version(a));
+        }
+
+        versions
+    }
+
+    /// Get the latest version of this plugin, in the version order of this plugin.
     ///
     /// Returns [`None`] if there is no latest version (i.e., no version has been published).
     #[inline]
     pub fn latest_version(&self) -> Option<SpigetResourceVersion> {
         log::debug!("finding latest version");
 
+        if self.version_order != VersionOrder::Date {
+            return self.sorted_versions(self.version_order).into_iter().next();
+        }
+
         let latest_version = self.cached_versions.first().map(|e| e.1).cloned()?;
 
         let resource_id = self.resource_id();
@@ -590,6 +1061,56 @@ impl SpigetPlugin {
             .find(|v| v.version.name == version_name)
     }
 
+    /// The names of the versions that are similar to the version name in the given [`VersionSpec`], closest first.
+    /// Used to suggest versions when [`SpigetPlugin::version_from_spec`] didn't find a version with the name.
+    ///
+    /// Returns no names for specs that aren't version names.
+    #[inline]
+    pub fn similar_versions(&self, version_spec: &VersionSpec) -> Vec<String> {
+        let VersionSpec::Name(version_name) = version_spec else {
+            return Vec::new();
+        };
+
+        let names = self
+            .cached_versions
+            .values()
+            .map(|version| version.name.as_str());
+
+        closest_matches(version_name, names)
+            .into_iter()
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    /// The error for when [`SpigetPlugin::version_from_spec`] didn't find a version of the plugin with the given name in the manifest.
+    ///
+    /// If no versions have been published for the resource at all, the error points at the plugin's entry in the manifest source.
+    /// Otherwise similar versions are suggested.
+    #[inline]
+    pub fn version_not_found(
+        &self,
+        manifest: &Manifest,
+        plugin_name: &str,
+        version_spec: &VersionSpec,
+    ) -> miette::Report {
+        if self.cached_versions.is_empty() {
+            return manifest
+                .entry_error(
+                    plugin_name,
+                    None,
+                    "no versions have been published for this resource",
+                    NotFoundError::Version,
+                )
+                .into();
+        }
+
+        diagnostics::version_not_found(
+            plugin_name,
+            version_spec,
+            &self.similar_versions(version_spec),
+        )
+    }
+
     /// Get a version from the given [`VersionSpec`].
     /// Returns [`None`] if no version could be found for the given spec.
     #[inline]
@@ -604,9 +1125,160 @@ impl SpigetPlugin {
                 self.version(id)
             }
             VersionSpec::Name(name) => self.search_version(name),
-            VersionSpec::Latest => self.latest_version(),
+            // every version is at least a snapshot
+            VersionSpec::Latest(ReleaseChannel::Snapshot) => self.latest_version(),
+            version_spec => version_spec.resolve(self.sorted_versions(self.version_order)),
         })
     }
+
+    /// Like [`SpigetPlugin::version_from_spec`], but [`VersionSpec::Latest`] resolves to the latest version that is compatible
+    /// with the given server (if the manifest specifies one).
+    ///
+    /// Spiget doesn't know which Minecraft versions a version supports, so compatibility is checked with the plugin descriptors
+    /// in the jars of the most recent versions that are in the download cache (or downloaded, see [`IoSession::check_jars`]).
+    /// A version whose jar isn't cached is used if the resource was tested with the Minecraft version of the server (or doesn't say).
+    /// Other versions that can't be checked are only used if no checked version is compatible.
+    /// If every checked version is incompatible, an [`IncompatibleVersionsError`] is returned.
+    #[inline]
+    pub async fn resolve_version(
+        &self,
+        plugin_name: &str,
+        version_spec: &VersionSpec,
+        target: &ServerTarget,
+    ) -> SpigetApiResult<Option<SpigetResourceVersion>> {
+        if !version_spec.is_latest() || target.is_empty() {
+            return self.version_from_spec(version_spec);
+        }
+
+        let candidates = self.candidate_versions(version_spec, |_| true);
+        self.resolve_candidates(plugin_name, version_spec, target, &candidates)
+            .await
+    }
+
+    /// The versions that match the version spec and the filter (like the versions that an update policy allows),
+    /// from the latest to the oldest. [`SpigetPlugin::resolve_candidates`] picks the version that they resolve to.
+    #[inline]
+    pub fn candidate_versions(
+        &self,
+        version_spec: &VersionSpec,
+        filter: impl Fn(&SpigetResourceVersion) -> bool,
+    ) -> Vec<SpigetResourceVersion> {
+        self.sorted_versions(self.version_order)
+            .into_iter()
+            .filter(|version| version_spec.matches(version) && filter(version))
+            .collect()
+    }
+
+    /// Like [`SpigetPlugin::resolve_version`], but the version is picked from the given candidates
+    /// (see [`SpigetPlugin::candidate_versions`]), so that they can be filtered in more than one way after they're listed once.
+    #[inline]
+    pub async fn resolve_candidates<'c>(
+        &self,
+        plugin_name: &str,
+        version_spec: &VersionSpec,
+        target: &ServerTarget,
+        candidates: impl IntoIterator<Item = &'c SpigetResourceVersion>,
+    ) -> SpigetApiResult<Option<SpigetResourceVersion>> {
+        let mut candidates = candidates.into_iter();
+        if !version_spec.is_latest() || target.is_empty() {
+            return Ok(candidates.next().cloned());
+        }
+
+        if target.tested_with(self.tested_versions()) == Some(false) {
+            log::warn!(
+                "Plugin '{plugin_name}' has not been tested with the Minecraft version of the server (tested with {})",
+                self.tested_versions().join(", ")
+            );
+        }
+
+        let mut unchecked = None;
+        let mut latest_incompatibility = None;
+        let mut checked = 0;
+        for version in candidates.take(COMPATIBLE_VERSION_CANDIDATES) {
+            let metadata = match self.cached_metadata(plugin_name, version).await {
+                Ok(Some(metadata)) => metadata,
+                // the resource being tested with the server's Minecraft version is the best that's known without the jar
+                Ok(None) if target.tested_with(self.tested_versions()) != Some(false) => {
+                    log::info!(
+                        "Using version '{0}' of plugin '{plugin_name}' without checking its jar for compatibility with the server, \
+                        since it's not in the download cache (use '--check-jars' to download it)",
+                        version.version.name
+                    );
+                    return Ok(Some(version.clone()));
+                }
+                Ok(None) => {
+                    unchecked.get_or_insert(version);
+                    continue;
+                }
+                Err(error) => {
+                    log::warn!(
+                        "Could not check if version '{0}' of plugin '{plugin_name}' is compatible with the server: {error}",
+                        version.version.name
+                    );
+                    unchecked.get_or_insert(version);
+                    continue;
+                }
+            };
+
+            checked += 1;
+            match target.check(&metadata.descriptor) {
+                Ok(()) => return Ok(Some(version.clone())),
+                Err(incompatibility) => {
+                    log::info!(
+                        "Skipping version '{0}' of plugin '{plugin_name}': {incompatibility}",
+                        version.version.name
+                    );
+                    latest_incompatibility.get_or_insert((version, incompatibility));
+                }
+            }
+        }
+
+        if let Some(version) = unchecked {
+            log::warn!(
+                "Using version '{0}' of plugin '{plugin_name}', which could not be checked for compatibility with the server",
+                version.version.name
+            );
+            return Ok(Some(version.clone()));
+        }
+
+        match latest_incompatibility {
+            Some((latest, incompatibility)) => Err(IncompatibleVersionsError {
+                plugin_name: plugin_name.to_string(),
+                checked,
+                latest: latest.version.name.clone(),
+                incompatibility,
+            }
+            .into()),
+            None => Ok(None),
+        }
+    }
+
+    /// The metadata of the jar of a version, for checking if it's compatible with the server.
+    ///
+    /// Only the download cache is read, unless the session should download jars to check them ([`IoSession::check_jars`]).
+    /// Returns [`None`] if the jar isn't cached and shouldn't be downloaded.
+    #[inline]
+    async fn cached_metadata(
+        &self,
+        plugin_name: &str,
+        version: &SpigetResourceVersion,
+    ) -> Result<Option<JarMetadata>, DownloadError> {
+        if self.io.check_jars() {
+            let spec = DownloadSpec {
+                plugin_name,
+                version,
+                api_type: PluginApiType::Spiget,
+            };
+
+            return self.io.plugin_metadata(spec).await.map(Some);
+        }
+
+        Ok(self
+            .io
+            .download_cache()
+            .jar_metadata(plugin_name, &version.version_identifier())
+            .await?)
+    }
 }
 
 /// An iterator over the versions of a plugin.
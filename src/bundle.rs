@@ -0,0 +1,351 @@
+//! Cache bundles for moving cached data to machines without network access.
+//!
+//! A bundle is a tar archive with the following layout:
+//! - `bundle.json`: Describes the contents of the bundle (see [`BundleIndex`]). Always the first file in the archive.
+//! - `data/`: Cached plugin files, with the same names as in the download cache's data directory.
+//! - `http/`: Cached API responses from the HTTP cache, with names from [`BundleIndex::http_entries`].
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use rq::Url;
+use tokio::sync::mpsc;
+
+use crate::caching::{CacheError, DownloadCache, IndexFilePlugins};
+use crate::error::ParseError;
+use crate::manifest::Manifest;
+
+/// Name of the file describing the contents of a bundle.
+pub static BUNDLE_INDEX_FILE_NAME: &str = "bundle.json";
+
+/// Name of the directory in a bundle where cached plugin files are stored.
+pub static BUNDLE_DATA_DIRECTORY_NAME: &str = "data";
+
+/// Name of the directory in a bundle where cached API responses are stored.
+pub static BUNDLE_HTTP_DIRECTORY_NAME: &str = "http";
+
+/// The version of the bundle format written by this version of pluginstall.
+pub const BUNDLE_FORMAT_VERSION: u32 = 1;
+
+/// The contents of a bundle. Serialized to the [`BUNDLE_INDEX_FILE_NAME`] file in the bundle.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+pub struct BundleIndex {
+    /// The version of the bundle format.
+    pub format_version: u32,
+    /// The name of the manifest this bundle was exported for.
+    pub manifest_name: String,
+    /// Index entries of the cached plugins in this bundle, in the same format as the download cache index.
+    pub plugins: IndexFilePlugins,
+    /// Maps HTTP cache keys to the names of the files with their data in the bundle's HTTP directory.
+    pub http_entries: HashMap<String, String>,
+}
+
+/// Only the format version of a bundle index. Used to check the format version before parsing the rest of the index.
+#[derive(serde::Deserialize, Debug)]
+struct BundleFormatVersion {
+    format_version: u32,
+}
+
+/// Error with exporting or importing a bundle.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+pub enum BundleError {
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Cache(#[from] CacheError),
+    #[error("HTTP cache error")]
+    HttpCache(#[from] cacache::Error),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Parse(#[from] ParseError),
+    #[error("Unsupported bundle format version {0} (expected version {BUNDLE_FORMAT_VERSION})")]
+    #[diagnostic(help("The bundle was probably created by another version of pluginstall."))]
+    UnsupportedVersion(u32),
+    #[error("Bundle is missing the file '{0}'")]
+    MissingFile(String),
+}
+
+/// A summary of what was exported or imported in a bundle operation.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct BundleSummary {
+    /// The name of the manifest that the bundle was exported for.
+    pub manifest_name: String,
+    /// The number of cached plugin files that were exported or imported.
+    pub plugin_files: usize,
+    /// The number of cached API responses that were exported or imported.
+    pub http_entries: usize,
+}
+
+/// Get the URL of an HTTP cache key. Keys are in the format `{method}:{url}`.
+#[inline]
+fn http_cache_key_url(key: &str) -> Option<Url> {
+    let (_method, url) = key.split_once(':')?;
+    Url::parse(url).ok()
+}
+
+/// Append a file with the given size and data to a tar archive.
+#[inline]
+fn append_file(
+    builder: &mut tar::Builder<impl Write>,
+    path: &str,
+    size: u64,
+    data: impl Read,
+) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+
+    builder.append_data(&mut header, path, data)
+}
+
+/// Read the files in a tar archive one at a time, sending their paths and data to the receiver.
+/// Stops early (without an error) if the receiver is closed.
+#[inline]
+fn read_files(bundle: impl Read, files: mpsc::Sender<(String, Vec<u8>)>) -> io::Result<()> {
+    let mut archive = tar::Archive::new(bundle);
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+
+        let path = entry.path()?.to_string_lossy().into_owned();
+        let mut data = Vec::new();
+        entry.read_to_end(&mut data)?;
+
+        if files.blocking_send((path, data)).is_err() {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+/// Export all the cached data needed by the plugins in the manifest into a bundle at the given path.
+/// This includes every cached file of these plugins, and every cached API response with data about these plugins.
+///
+/// The bundle is written to the file as it's created, so it's never held in memory.
+/// Returns the size of the bundle in bytes and a summary of its contents.
+#[inline]
+pub async fn export_bundle(
+    cache: &DownloadCache,
+    manifest: &Manifest,
+    bundle_path: &Path,
+) -> Result<(u64, BundleSummary), BundleError> {
+    let mut index = BundleIndex {
+        format_version: BUNDLE_FORMAT_VERSION,
+        manifest_name: manifest.meta.manifest_name.clone(),
+        plugins: IndexFilePlugins::default(),
+        http_entries: HashMap::new(),
+    };
+
+    // maps paths in the bundle to the paths of the cached files
+    let mut plugin_files = Vec::new();
+    for plugin_name in manifest.plugin.keys() {
+        let Some(cached_plugin) = cache.cached_plugin(plugin_name).await else {
+            log::warn!("No cached files for plugin '{plugin_name}', it will not be in the bundle");
+            continue;
+        };
+
+        for meta in cached_plugin.versions.values() {
+            let path = format!("{BUNDLE_DATA_DIRECTORY_NAME}/{}", meta.cache_file_name);
+            plugin_files.push((path, cache.cached_file_path(meta)));
+        }
+
+        index.plugins.insert(plugin_name.clone(), cached_plugin);
+    }
+
+    let http_cache_path = cache.cacache_manager().path;
+    // the HTTP cache is created lazily, so it won't exist if no requests have been made yet
+    if http_cache_path.is_dir() {
+        let list_path = http_cache_path.clone();
+        // listing the HTTP cache reads its whole index synchronously
+        let keys = tokio::task::spawn_blocking(move || {
+            cacache::list_sync(list_path)
+                .map(|entry| entry.map(|entry| entry.key))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .await
+        .expect("listing the HTTP cache doesn't panic")?;
+
+        for key in keys {
+            if index.http_entries.contains_key(&key) {
+                continue;
+            }
+
+            let Some(url) = http_cache_key_url(&key) else {
+                continue;
+            };
+
+            if !manifest
+                .plugin
+                .values()
+                .any(|plugin| plugin.is_plugin_api_url(&url))
+            {
+                continue;
+            }
+
+            let file_name = index.http_entries.len().to_string();
+            index.http_entries.insert(key, file_name);
+        }
+    }
+
+    let index_json = serde_json::to_vec_pretty(&index)
+        .expect("the serialize implementation is derived and shouldn't fail");
+
+    let summary = BundleSummary {
+        manifest_name: index.manifest_name,
+        plugin_files: plugin_files.len(),
+        http_entries: index.http_entries.len(),
+    };
+
+    let bundle_path = bundle_path.to_path_buf();
+    let http_entries = index.http_entries;
+    // tar archives can only be written synchronously
+    let bundle_size = tokio::task::spawn_blocking(move || -> Result<u64, BundleError> {
+        let mut builder = tar::Builder::new(BufWriter::new(File::create(bundle_path)?));
+
+        // the index comes first, so that importing can check it before reading any other files
+        append_file(
+            &mut builder,
+            BUNDLE_INDEX_FILE_NAME,
+            index_json.len() as _,
+            index_json.as_slice(),
+        )?;
+
+        for (path, cache_file_path) in plugin_files {
+            let file = File::open(cache_file_path)?;
+            let size = file.metadata()?.len();
+            append_file(&mut builder, &path, size, file)?;
+        }
+
+        for (key, file_name) in http_entries {
+            let data = cacache::read_sync(&http_cache_path, &key)?;
+            let path = format!("{BUNDLE_HTTP_DIRECTORY_NAME}/{file_name}");
+            append_file(&mut builder, &path, data.len() as _, data.as_slice())?;
+        }
+
+        let file = builder
+            .into_inner()?
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        Ok(file.metadata()?.len())
+    })
+    .await
+    .expect("writing a bundle doesn't panic")?;
+
+    Ok((bundle_size, summary))
+}
+
+/// Import the bundle at the given path into the cache, merging its contents with the data that's already cached.
+///
+/// Cached plugin files already in the cache will only be replaced if the file in the bundle is more recent.
+/// Cached API responses will always be replaced.
+///
+/// The files in the bundle are read and imported one at a time, so the bundle is never held in memory.
+/// Returns a summary of what was imported.
+#[inline]
+pub async fn import_bundle(
+    cache: &DownloadCache,
+    bundle_path: &Path,
+) -> Result<BundleSummary, BundleError> {
+    let bundle = File::open(bundle_path)?;
+    let (sender, mut files) = mpsc::channel(1);
+
+    // tar archives can only be read synchronously
+    let reader = tokio::task::spawn_blocking(move || read_files(bundle, sender));
+
+    let result = import_files(cache, &mut files).await;
+
+    // stop reading if the import failed early
+    drop(files);
+    reader.await.expect("reading a bundle doesn't panic")?;
+
+    result
+}
+
+/// Import the files of a bundle, in the order they are in the bundle.
+#[inline]
+async fn import_files(
+    cache: &DownloadCache,
+    files: &mut mpsc::Receiver<(String, Vec<u8>)>,
+) -> Result<BundleSummary, BundleError> {
+    let index_json = match files.recv().await {
+        Some((path, data)) if path == BUNDLE_INDEX_FILE_NAME => data,
+        _ => return Err(BundleError::MissingFile(BUNDLE_INDEX_FILE_NAME.to_string())),
+    };
+    let index_json = String::from_utf8_lossy(&index_json);
+
+    let format_version = serde_json::from_str::<BundleFormatVersion>(&index_json)
+        .map_err(|err| ParseError::json(err, index_json.as_ref()))?
+        .format_version;
+
+    if format_version != BUNDLE_FORMAT_VERSION {
+        return Err(BundleError::UnsupportedVersion(format_version));
+    }
+
+    let index = serde_json::from_str::<BundleIndex>(&index_json)
+        .map_err(|err| ParseError::json(err, index_json.as_ref()))?;
+
+    // maps paths in the bundle to the cached plugin versions they contain
+    let mut plugin_files = HashMap::new();
+    for (plugin_name, cached_plugin) in index.plugins {
+        for (version_identifier, meta) in cached_plugin.versions {
+            let path = format!("{BUNDLE_DATA_DIRECTORY_NAME}/{}", meta.cache_file_name);
+            plugin_files.insert(
+                path,
+                (
+                    plugin_name.clone(),
+                    version_identifier,
+                    cached_plugin.source_api,
+                    meta,
+                ),
+            );
+        }
+    }
+
+    // maps paths in the bundle to HTTP cache keys
+    let http_entries = index.http_entries.len();
+    let mut http_files = index
+        .http_entries
+        .into_iter()
+        .map(|(key, file_name)| (format!("{BUNDLE_HTTP_DIRECTORY_NAME}/{file_name}"), key))
+        .collect::<HashMap<_, _>>();
+
+    let http_cache_path = cache.cacache_manager().path;
+    let mut imported_plugin_files = 0;
+    while let Some((path, data)) = files.recv().await {
+        if let Some((plugin_name, version_identifier, source_api, meta)) =
+            plugin_files.remove(&path)
+        {
+            let merged = cache
+                .merge_cached_file(&plugin_name, &version_identifier, source_api, meta, &data)
+                .await?;
+
+            if merged {
+                imported_plugin_files += 1;
+            }
+        } else if let Some(key) = http_files.remove(&path) {
+            cacache::write(&http_cache_path, key, data).await?;
+        }
+    }
+
+    if let Some(path) = plugin_files
+        .into_keys()
+        .chain(http_files.into_keys())
+        .next()
+    {
+        return Err(BundleError::MissingFile(path));
+    }
+
+    Ok(BundleSummary {
+        manifest_name: index.manifest_name,
+        plugin_files: imported_plugin_files,
+        http_entries,
+    })
+}
@@ -14,11 +14,14 @@ use tokio::io::{self, AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
 use tokio::sync::RwLock;
 
 use crate::adapter::PluginApiType;
+use crate::config::project_dirs;
 use crate::error::ParseError;
+use crate::jar::{self, JarError, JarMetadata};
 use crate::ok_none;
+use crate::util::validate_file_name;
 
 /// The name of the directory where cached data is stored.
-pub static DEFAULT_CACHE_DIRECTORY_NAME: &str = ".pluginstall_cache";
+pub static LEGACY_CACHE_DIRECTORY_NAME: &str = ".pluginstall_cache";
 
 /// Name of the cache index file in the cache directory. This file describes where versions of plugins are cached.
 pub static CACHE_INDEX_FILE_NAME: &str = "index.json";
@@ -35,20 +38,114 @@ pub enum CacheError {
     Io(#[from] io::Error),
     /// An error serializing/deserializing the cache index
     #[error(transparent)]
+    #[diagnostic(transparent)]
     IndexParse(ParseError),
     #[error("Error copying cached plugin file: {0}")]
     CopyFile(io::Error),
+    /// The cache index is from a newer version of pluginstall, and can't be modified.
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    UnsupportedIndex(IndexError),
+    /// The cached file is not a plugin jar, so it has no plugin metadata.
+    #[error("The cached file is not a plugin jar: {0}")]
+    NotAJar(String),
+    /// A cache entry had a file name that is not safe to use.
+    #[error("Invalid file name for cached plugin file: '{0}'")]
+    InvalidFileName(String),
+    /// A cached plugin file could not be read as a plugin jar.
+    #[error("Error reading cached plugin file")]
+    Jar(#[from] JarError),
 }
 
 pub type CacheResult<T> = Result<T, CacheError>;
 
-/// Get the default cache directory path, returning an error if it could not be found.
+impl From<IndexError> for CacheError {
+    #[inline]
+    fn from(err: IndexError) -> Self {
+        match err {
+            IndexError::Io(err) => Self::Io(err),
+            IndexError::Parse(err) => Self::IndexParse(err),
+            err @ IndexError::UnsupportedVersion(_) => Self::UnsupportedIndex(err),
+        }
+    }
+}
+
+/// Get the default cache directory path (the user's cache directory, like `$XDG_CACHE_HOME/pluginstall/`),
+/// returning an error if it could not be found.
 #[inline]
 pub fn default_cache_directory_path() -> io::Result<PathBuf> {
-    let dirs = UserDirs::new().ok_or(io::Error::other("could not get home directory"))?;
-    let home_dir = dirs.home_dir();
+    let dirs = project_dirs().ok_or(io::Error::other("could not get home directory"))?;
+
+    Ok(dirs.cache_dir().to_path_buf())
+}
+
+/// Get the path of the cache directory used by older versions of pluginstall (`$HOME/.pluginstall_cache`).
+/// Returns [`None`] if the home directory could not be found.
+#[inline]
+pub fn legacy_cache_directory_path() -> Option<PathBuf> {
+    let dirs = UserDirs::new()?;
+
+    Some(dirs.home_dir().join(LEGACY_CACHE_DIRECTORY_NAME))
+}
 
-    Ok(home_dir.join(DEFAULT_CACHE_DIRECTORY_NAME))
+/// Move the cache directory used by older versions of pluginstall to the default cache directory.
+///
+/// Nothing is moved if the default cache directory already exists, since the caches can't be merged safely.
+/// Failures are logged with instructions for moving the cache manually instead of being returned,
+/// because a missing cache only means that files are downloaded again.
+#[inline]
+pub async fn migrate_legacy_cache_directory(legacy_dir: &Path, cache_dir: &Path) {
+    if fs::try_exists(cache_dir).await.unwrap_or(true) {
+        log::warn!(
+            "Found a download cache at the old location '{}', which is no longer used. \
+            Move its contents to '{}' to keep using them, or remove it",
+            legacy_dir.display(),
+            cache_dir.display()
+        );
+        return;
+    }
+
+    let moved = async {
+        if let Some(parent) = cache_dir.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        fs::rename(legacy_dir, cache_dir).await
+    };
+
+    match moved.await {
+        Ok(()) => log::info!(
+            "Moved the download cache from the old location '{}' to '{}'",
+            legacy_dir.display(),
+            cache_dir.display()
+        ),
+        Err(error) => log::warn!(
+            "Could not move the download cache from the old location '{}' to '{}' ({error}). \
+            Move it there manually to keep using it, or remove it",
+            legacy_dir.display(),
+            cache_dir.display()
+        ),
+    }
+}
+
+/// Read the metadata of a plugin jar that is being cached, from its file in the cache data directory.
+/// Any file can be cached, so files that can't be read as a plugin jar are marked as such.
+/// Returns [`None`] if the file could not be read at all.
+#[inline]
+async fn cached_jar_metadata(path: &Path) -> Option<CachedJarMetadata> {
+    match jar::jar_metadata(path).await {
+        Ok(metadata) => Some(CachedJarMetadata::Jar(Box::new(metadata))),
+        Err(JarError::Io(error)) => {
+            log::debug!("Could not read the cached file: {error:?}");
+            None
+        }
+        Err(error) => {
+            log::debug!("The cached file is not a plugin jar: {error:?}");
+            Some(CachedJarMetadata::NotAJar {
+                not_a_jar: error.to_string(),
+            })
+        }
+    }
 }
 
 /// Compute the name of a file with cached data of a plugin.
@@ -77,7 +174,7 @@ impl DownloadCache {
     #[inline]
     pub async fn new(cache_path: &Path) -> CacheResult<Self> {
         if !cache_path.is_dir() {
-            fs::create_dir(&cache_path).await?;
+            fs::create_dir_all(&cache_path).await?;
         }
 
         let data_path = cache_path.join(CACHE_DATA_DIRECTORY_NAME);
@@ -87,12 +184,7 @@ impl DownloadCache {
         }
 
         let index_file_path = cache_path.join(CACHE_INDEX_FILE_NAME);
-        let cache_index = CacheIndex::new(&index_file_path)
-            .await
-            .map_err(|err| match err {
-                IndexError::Io(err) => CacheError::Io(err),
-                IndexError::Parse(err) => CacheError::IndexParse(err),
-            })?;
+        let cache_index = CacheIndex::new(&index_file_path).await?;
 
         Ok(Self {
             cache_path: cache_path.to_path_buf(),
@@ -139,6 +231,7 @@ impl DownloadCache {
         version_identifier: &str,
     ) -> CacheResult<Option<CachedPluginVersionFile>> {
         let mut cache_index = self.cache_index.write().await;
+        cache_index.check_writable()?;
 
         let Entry::Occupied(mut plugin_entry) = cache_index.plugins.entry(plugin_name.to_string())
         else {
@@ -175,15 +268,42 @@ impl DownloadCache {
         // if the retrieved file is outdated, then delete it and claim it never existed.
         // cached data is only valid as long as it's up to date
         if meta.is_outdated() {
-            self.delete_cached_file(plugin_name, version_identifier)
-                .await?;
+            // we can't delete anything from an index we can't write to, so just ignore the outdated file
+            if self.cache_index.read().await.check_writable().is_ok() {
+                self.delete_cached_file(plugin_name, version_identifier)
+                    .await?;
+            }
             return Ok(None);
         }
 
+        self.open_cached_file(meta).await.map(Some)
+    }
+
+    /// Get and open the cached plugin version if it exists, even if it has outlived its TTL.
+    /// Unlike [`DownloadCache::get_cached_file`], outdated files will not be deleted.
+    ///
+    /// Returns [`None`] if this version was not cached.
+    #[inline]
+    pub async fn get_cached_file_ignore_ttl(
+        &self,
+        plugin_name: &str,
+        version_identifier: &str,
+    ) -> CacheResult<Option<CachedFile>> {
+        let meta = ok_none!(
+            self.get_cached_plugin_metadata(plugin_name, version_identifier)
+                .await
+        );
+
+        self.open_cached_file(meta).await.map(Some)
+    }
+
+    /// Open the file in the data directory described by the given metadata.
+    #[inline]
+    async fn open_cached_file(&self, meta: CachedPluginVersionFile) -> CacheResult<CachedFile> {
         let file_path = self.cache_datadir_path.join(&meta.cache_file_name);
         let file = File::open(&file_path).await?;
 
-        Ok(Some(CachedFile { meta, file }))
+        Ok(CachedFile { meta, file })
     }
 
     /// Cache the data from the given reader.
@@ -200,6 +320,7 @@ impl DownloadCache {
         data: &[u8],
     ) -> CacheResult<()> {
         let mut index = self.cache_index.write().await;
+        index.check_writable()?;
 
         let plugins = index
             .plugins
@@ -219,6 +340,7 @@ impl DownloadCache {
             file_name: file_name.to_string(),
             cache_file_name,
             ttl,
+            metadata: cached_jar_metadata(&cache_file_path).await,
         };
 
         plugins
@@ -230,6 +352,136 @@ impl DownloadCache {
 
         Ok(())
     }
+
+    /// Get the index entry of a plugin, describing all of its cached versions.
+    /// Returns [`None`] if no versions of this plugin are cached.
+    #[inline]
+    pub async fn cached_plugin(&self, plugin_name: &str) -> Option<CachedPlugin> {
+        let cache_index = self.cache_index.read().await;
+
+        cache_index.plugins.get(plugin_name).cloned()
+    }
+
+    /// Get the metadata of the jar of a cached plugin version, even if it has outlived its TTL.
+    ///
+    /// Metadata is read when a file is cached, so the jar only has to be opened for files that were cached
+    /// by older versions of pluginstall. The metadata (or a mark that the file is not a plugin jar) is then added to the index,
+    /// if the index can be written to.
+    ///
+    /// Returns [`None`] if this version was not cached, and [`CacheError::NotAJar`] if the cached file is not a plugin jar.
+    #[inline]
+    pub async fn jar_metadata(
+        &self,
+        plugin_name: &str,
+        version_identifier: &str,
+    ) -> CacheResult<Option<JarMetadata>> {
+        let meta = ok_none!(
+            self.get_cached_plugin_metadata(plugin_name, version_identifier)
+                .await
+        );
+
+        match meta.metadata {
+            Some(CachedJarMetadata::Jar(metadata)) => return Ok(Some(*metadata)),
+            Some(CachedJarMetadata::NotAJar { not_a_jar }) => {
+                return Err(CacheError::NotAJar(not_a_jar))
+            }
+            None => (),
+        }
+
+        // files that can't be read as a plugin jar are marked, so that they aren't read again
+        let file_path = self.cache_datadir_path.join(&meta.cache_file_name);
+        let metadata = match jar::jar_metadata(&file_path).await {
+            Ok(metadata) => CachedJarMetadata::Jar(Box::new(metadata)),
+            Err(JarError::Io(error)) => return Err(JarError::Io(error).into()),
+            Err(error) => CachedJarMetadata::NotAJar {
+                not_a_jar: error.to_string(),
+            },
+        };
+
+        let mut index = self.cache_index.write().await;
+        if index.check_writable().is_ok() {
+            let cached = index
+                .plugins
+                .get_mut(plugin_name)
+                .and_then(|plugin| plugin.versions.get_mut(version_identifier));
+
+            if let Some(cached) = cached {
+                cached.metadata = Some(metadata.clone());
+                index.sync_to_disk().await?;
+            }
+        }
+
+        match metadata {
+            CachedJarMetadata::Jar(metadata) => Ok(Some(*metadata)),
+            CachedJarMetadata::NotAJar { not_a_jar } => Err(CacheError::NotAJar(not_a_jar)),
+        }
+    }
+
+    /// Get the path of the file with the data of a cached plugin version.
+    #[inline]
+    pub fn cached_file_path(&self, meta: &CachedPluginVersionFile) -> PathBuf {
+        self.cache_datadir_path.join(&meta.cache_file_name)
+    }
+
+    /// Merge a cached plugin version from another cache (like a cache bundle) into this cache.
+    /// The metadata is kept as-is (including the date it was added and its TTL), but the name of the file in the data directory is recomputed.
+    ///
+    /// If this version is already cached, it will only be replaced if the provided version was added to its cache more recently.
+    /// Returns `true` if the provided version was written to the cache, and `false` if the existing version was kept.
+    ///
+    /// Returns [`CacheError::InvalidFileName`] if the provided metadata would result in an unsafe file name.
+    #[inline]
+    pub async fn merge_cached_file(
+        &self,
+        plugin_name: &str,
+        version_identifier: &str,
+        plugin_type: PluginApiType,
+        mut meta: CachedPluginVersionFile,
+        data: &[u8],
+    ) -> CacheResult<bool> {
+        let cache_file_name = compute_cache_file_name(plugin_name, version_identifier, plugin_type);
+
+        // these names come from elsewhere, so make sure nobody is trying to make us write outside the cache (or download) directory
+        for name in [&cache_file_name, &meta.file_name] {
+            if !validate_file_name(Path::new(name)) {
+                return Err(CacheError::InvalidFileName(name.clone()));
+            }
+        }
+
+        let mut index = self.cache_index.write().await;
+        index.check_writable()?;
+
+        let plugins = index
+            .plugins
+            .entry(plugin_name.to_string())
+            .or_insert_with(|| CachedPlugin::new(plugin_type));
+
+        if let Some(existing) = plugins.versions.get(version_identifier) {
+            if existing.added >= meta.added {
+                return Ok(false);
+            }
+        }
+
+        let cache_file_path = self.cache_datadir_path.join(&cache_file_name);
+
+        let mut file = File::create(&cache_file_path).await?;
+        file.write_all(data).await?;
+        file.flush().await?;
+
+        meta.cache_file_name = cache_file_name;
+        // bundles from older versions of pluginstall don't have the metadata yet
+        if meta.metadata.is_none() {
+            meta.metadata = cached_jar_metadata(&cache_file_path).await;
+        }
+
+        plugins
+            .versions
+            .insert(version_identifier.to_string(), meta);
+
+        index.sync_to_disk().await?;
+
+        Ok(true)
+    }
 }
 
 /// A cached plugin file.
@@ -272,11 +524,109 @@ pub struct CacheIndex {
     /// Maps the manifest name of plugins to their cached files.
     /// Deserialized from (and serialized to) the cache index file ([`IndexFile::path`])
     pub plugins: IndexFilePlugins,
+    /// The version of the index file on disk, if it's newer than [`CACHE_INDEX_VERSION`].
+    /// Indices from newer versions of pluginstall may have data we don't know about, so they are never written to.
+    pub unsupported_version: Option<u32>,
 }
 
 /// The plugins in an index file.
 pub type IndexFilePlugins = HashMap<String, CachedPlugin>;
 
+/// The version of the cache index format written by this version of pluginstall.
+///
+/// Bump this whenever the format of the index changes, and add a migration from the previous version to [`INDEX_MIGRATIONS`].
+pub const CACHE_INDEX_VERSION: u32 = 4;
+
+/// A migration of the index JSON from one version to the next.
+type IndexMigration = fn(serde_json::Value) -> serde_json::Value;
+
+/// Migrations for upgrading older index formats. The migration at index `i` upgrades an index from version `i + 1` to version `i + 2`.
+static INDEX_MIGRATIONS: [IndexMigration; (CACHE_INDEX_VERSION - 1) as usize] =
+    [migrate_index_v1, migrate_index_v2, migrate_index_v3];
+
+/// Version 1 of the index was a bare map of plugins, without a version.
+/// Version 2 wraps the plugins in an [`IndexFile`] envelope.
+#[inline]
+fn migrate_index_v1(plugins: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "version": 2,
+        "plugins": plugins,
+    })
+}
+
+/// Version 3 adds the metadata of cached plugin jars (see [`CachedPluginVersionFile::metadata`]).
+/// The files cached by version 2 don't have it, so it's read from them when it's needed instead.
+#[inline]
+fn migrate_index_v2(mut index: serde_json::Value) -> serde_json::Value {
+    index["version"] = 3.into();
+    index
+}
+
+/// Version 4 adds the other kinds of plugin descriptors in a jar (see [`jar::PluginDescriptor::other_kinds`]).
+/// They can't be known without opening the jars, so the metadata of jars is removed to be read again when it's needed.
+/// Files that aren't plugin jars stay marked as such.
+#[inline]
+fn migrate_index_v3(mut index: serde_json::Value) -> serde_json::Value {
+    let plugins = index["plugins"]
+        .as_object_mut()
+        .into_iter()
+        .flat_map(|plugins| plugins.values_mut());
+    for plugin in plugins {
+        let versions = plugin["versions"]
+            .as_object_mut()
+            .into_iter()
+            .flat_map(|versions| versions.values_mut());
+        for version in versions {
+            let Some(version) = version.as_object_mut() else {
+                continue;
+            };
+
+            let is_jar = version
+                .get("metadata")
+                .is_some_and(|metadata| metadata.get("not_a_jar").is_none());
+            if is_jar {
+                version.remove("metadata");
+            }
+        }
+    }
+
+    index["version"] = 4.into();
+    index
+}
+
+/// Get the version of an index from its JSON.
+/// Indices without a version field are from version 1, before the index was versioned.
+/// Versions start at 1, so an index with version 0 is invalid.
+#[inline]
+fn index_version(index: &serde_json::Value) -> Result<u64, serde_json::Error> {
+    match index.get("version").and_then(serde_json::Value::as_u64) {
+        None => Ok(1),
+        Some(0) => Err(serde::de::Error::invalid_value(
+            serde::de::Unexpected::Unsigned(0),
+            &"a cache index version of at least 1",
+        )),
+        Some(version) => Ok(version),
+    }
+}
+
+/// Upgrade the JSON of an index in the given version (at least 1) to [`CACHE_INDEX_VERSION`].
+#[inline]
+fn migrate_index(mut index: serde_json::Value, version: u64) -> serde_json::Value {
+    for migration in &INDEX_MIGRATIONS[(version as usize - 1)..] {
+        index = migration(index);
+    }
+
+    index
+}
+
+/// The cache index file as it's stored on disk.
+#[derive(serde::Serialize, serde::Deserialize, Debug)]
+struct IndexFile<P> {
+    /// The version of the index format.
+    version: u32,
+    plugins: P,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, Clone, new)]
 pub struct CachedPlugin {
     /// Cached versions of this resource.
@@ -299,6 +649,21 @@ pub struct CachedPluginVersionFile {
     pub ttl: Option<chrono::Duration>,
     /// The date that this file was added to the cache.
     pub added: chrono::DateTime<Utc>,
+    /// The metadata of the plugin jar, so that the jar doesn't have to be opened to get it.
+    /// [`None`] if the file was cached by an older version of pluginstall, or could not be read when it was cached.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<CachedJarMetadata>,
+}
+
+/// The metadata of a cached file, which is read when the file is cached.
+/// Serialized as the metadata itself for plugin jars, and as `{"not_a_jar": reason}` for other files.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum CachedJarMetadata {
+    /// The file is not a plugin jar, with the reason that it couldn't be read as one.
+    NotAJar { not_a_jar: String },
+    /// The file is a plugin jar with this metadata (boxed, because it's much larger than the reason).
+    Jar(Box<JarMetadata>),
 }
 
 /// An error serializing/deserializing the cache index.
@@ -308,38 +673,106 @@ pub enum IndexError {
     Io(#[from] io::Error),
     #[error(transparent)]
     Parse(#[from] ParseError),
+    /// The index is from a newer version of pluginstall and cannot be written to.
+    #[error("The cache index has version {0}, but this version of pluginstall only supports versions up to {CACHE_INDEX_VERSION}. Refusing to modify it.")]
+    #[diagnostic(help("Upgrade pluginstall, or use another cache directory with '--cache'."))]
+    UnsupportedVersion(u32),
 }
 
 impl CacheIndex {
     /// Create a new cache index from the given path. If there's no cache index file at the given path,
     /// this function will return an empty cache index for this path, which will be created and initialized
     /// when [`CacheIndex::sync_to_disk`] is called for the first time.
+    ///
+    /// Index files from older versions of pluginstall will be migrated to the current format and rewritten in place.
+    /// Index files from newer versions will be read as well as possible, but never written to.
     #[inline]
     pub async fn new(path: impl AsRef<Path>) -> Result<Self, IndexError> {
         let path = path.as_ref();
 
-        let plugins = match File::open(path).await {
+        let contents = match File::open(path).await {
             Ok(mut file) => {
                 let mut contents = String::new();
                 file.read_to_string(&mut contents).await?;
-                serde_json::from_str::<IndexFilePlugins>(&contents)
-                    .map_err(|err| ParseError::json(err, contents))?
+                contents
             }
             // in this case the file will be created and written to lazily when needed
-            Err(err) if err.kind() == ErrorKind::NotFound => IndexFilePlugins::default(),
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                return Ok(Self {
+                    plugins: IndexFilePlugins::default(),
+                    path: path.to_path_buf(),
+                    unsupported_version: None,
+                })
+            }
             Err(err) => return Err(IndexError::Io(err)),
         };
 
-        Ok(Self {
+        let index = serde_json::from_str::<serde_json::Value>(&contents)
+            .map_err(|err| ParseError::json(err, &contents))?;
+        let version = index_version(&index).map_err(|err| ParseError::json(err, &contents))?;
+
+        if version > CACHE_INDEX_VERSION as u64 {
+            let version = u32::try_from(version).unwrap_or(u32::MAX);
+            log::warn!("Cache index at '{}' has unsupported version {version} (from a newer version of pluginstall). It will not be modified.", path.display());
+
+            // try our best to read the plugins, but there's no guarantee that the format is compatible
+            let plugins = serde_json::from_value::<IndexFile<IndexFilePlugins>>(index)
+                .map(|index| index.plugins)
+                .unwrap_or_default();
+
+            return Ok(Self {
+                plugins,
+                path: path.to_path_buf(),
+                unsupported_version: Some(version),
+            });
+        }
+
+        let outdated = version < CACHE_INDEX_VERSION as u64;
+        let index = migrate_index(index, version);
+        let plugins = serde_json::from_value::<IndexFile<IndexFilePlugins>>(index)
+            .map_err(|err| ParseError::json(err, &contents))?
+            .plugins;
+
+        let index = Self {
             plugins,
             path: path.to_path_buf(),
-        })
+            unsupported_version: None,
+        };
+
+        if outdated {
+            log::info!(
+                "Migrating cache index at '{}' from version {version} to version {CACHE_INDEX_VERSION}",
+                path.display()
+            );
+            index.sync_to_disk().await?;
+        }
+
+        Ok(index)
+    }
+
+    /// Check if this index can be written to.
+    /// Returns [`IndexError::UnsupportedVersion`] if the index is from a newer version of pluginstall.
+    #[inline]
+    pub fn check_writable(&self) -> Result<(), IndexError> {
+        match self.unsupported_version {
+            Some(version) => Err(IndexError::UnsupportedVersion(version)),
+            None => Ok(()),
+        }
     }
 
     /// Sync this cache index to disk.
+    ///
+    /// Returns [`IndexError::UnsupportedVersion`] if the index is from a newer version of pluginstall.
     #[inline]
-    pub async fn sync_to_disk(&self) -> io::Result<()> {
-        let json = serde_json::to_string_pretty(&self.plugins)
+    pub async fn sync_to_disk(&self) -> Result<(), IndexError> {
+        self.check_writable()?;
+
+        let index_file = IndexFile {
+            version: CACHE_INDEX_VERSION,
+            plugins: &self.plugins,
+        };
+
+        let json = serde_json::to_string_pretty(&index_file)
             .expect("the serialize implementation is derived and shouldn't fail");
 
         let mut file = File::create(&self.path).await?;
@@ -372,3 +805,106 @@ impl CachedPluginVersionFile {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_migrate_unversioned_index() {
+        let index = json!({
+            "gsit": {
+                "versions": {},
+                "source_api": "Spiget",
+            },
+            // a plugin with an unfortunate name shouldn't be mistaken for the version field
+            "version": {
+                "versions": {},
+                "source_api": "Spiget",
+            },
+        });
+
+        assert_eq!(index_version(&index).unwrap(), 1);
+
+        let index = migrate_index(index, 1);
+        assert_eq!(index_version(&index).unwrap(), CACHE_INDEX_VERSION as u64);
+
+        let index = serde_json::from_value::<IndexFile<IndexFilePlugins>>(index).unwrap();
+        assert!(index.plugins.contains_key("gsit"));
+        assert!(index.plugins.contains_key("version"));
+    }
+
+    #[test]
+    fn test_migrate_index_with_jar_metadata() {
+        let entry = |metadata: serde_json::Value| {
+            json!({
+                "file_name": "GSit.jar",
+                "cache_file_name": "gsit-1",
+                "ttl": null,
+                "added": "2026-01-01T00:00:00Z",
+                "metadata": metadata,
+            })
+        };
+        let index = json!({
+            "version": 3,
+            "plugins": {
+                "gsit": {
+                    "versions": {
+                        "1": entry(json!({
+                            "kind": "bukkit",
+                            "name": "GSit",
+                            "version": "1.10",
+                            "main": null,
+                            "api_version": null,
+                            "authors": [],
+                            "website": null,
+                            "depend": [],
+                            "softdepend": [],
+                            "loadbefore": [],
+                            "java_version": 17,
+                        })),
+                        "2": entry(json!({ "not_a_jar": "The jar has no plugin descriptor" })),
+                    },
+                    "source_api": "Spiget",
+                },
+            },
+        });
+
+        let index = migrate_index(index, 3);
+        assert_eq!(index_version(&index).unwrap(), CACHE_INDEX_VERSION as u64);
+
+        // the metadata of jars is read again, since it lacks the other descriptor kinds
+        let mut index = serde_json::from_value::<IndexFile<IndexFilePlugins>>(index).unwrap();
+        let mut versions = index.plugins.remove("gsit").unwrap().versions;
+        assert!(versions.remove("1").unwrap().metadata.is_none());
+        assert!(matches!(
+            versions.remove("2").unwrap().metadata,
+            Some(CachedJarMetadata::NotAJar { .. })
+        ));
+    }
+
+    #[test]
+    fn test_reject_index_version_zero() {
+        let index = json!({
+            "version": 0,
+            "plugins": {},
+        });
+
+        assert!(index_version(&index).is_err());
+    }
+
+    #[test]
+    fn test_migrate_current_index() {
+        let index = json!({
+            "version": CACHE_INDEX_VERSION,
+            "plugins": {},
+        });
+
+        assert_eq!(
+            migrate_index(index.clone(), CACHE_INDEX_VERSION as u64),
+            index
+        );
+    }
+}
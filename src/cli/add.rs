@@ -0,0 +1,136 @@
+//! The 'add' subcommand for adding a plugin to the manifest.
+
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::Args;
+use miette::{Context, IntoDiagnostic};
+use owo_colors::OwoColorize;
+use rq::Url;
+
+use crate::{
+    adapter::{
+        spiget::{ManifestSpigetPlugin, ResourceId, SpigetPlugin},
+        PluginApiType, UnsupportedApiError,
+    },
+    cli::Subcommand,
+    manifest::{suggest_plugin_name, Manifest, PluginDownloadSpec},
+    output::DataDisplay,
+    session::IoSession,
+};
+
+/// The 'add' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Add {
+    /// The API that the plugin is downloaded from.
+    #[arg(value_name = "SOURCE")]
+    pub source: PluginApiType,
+    /// The ID of the plugin in the API, like the resource ID of a Spigot resource.
+    #[arg(value_name = "ID")]
+    pub id: String,
+    /// The name of the plugin in the manifest. By default a name is suggested from the name of the plugin in the API.
+    #[arg(long, value_name = "PLUGIN_NAME")]
+    pub name: Option<String>,
+}
+
+/// The output of the 'add' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct AddOutput {
+    pub plugin_name: String,
+    pub plugin_type: PluginApiType,
+    /// The name of the plugin in the API.
+    pub title: String,
+    pub page_url: Url,
+    pub manifest_path: PathBuf,
+}
+
+/// Error for when the manifest already has a plugin with the name that a plugin was added with.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("The manifest already has a plugin with the name '{0}'.")]
+#[diagnostic(help("Use '--name' to add the plugin with another name."))]
+pub struct PluginExistsError(pub String);
+
+/// Error for when no name could be suggested for a plugin from the name of the plugin in the API.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("Could not suggest a name for the plugin '{0}'.")]
+#[diagnostic(help("Use '--name' to add the plugin with a name."))]
+pub struct NoPluginNameError(pub String);
+
+impl DataDisplay for AddOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        write!(
+            w,
+            "Added {0} plugin '{1}' ({2}, {3}) to '{4}'",
+            self.plugin_type,
+            self.plugin_name.bright_green(),
+            self.title,
+            self.page_url.bright_green(),
+            self.manifest_path.display().bright_green(),
+        )
+    }
+}
+
+impl Subcommand for Add {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let (title, plugin, page_url) = match self.source {
+            PluginApiType::Spiget => {
+                let resource_id = ResourceId::from_str(&self.id)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("'{}' is not a valid Spigot resource ID", self.id))?;
+
+                let existing = manifest.plugin.iter().find(|(_, spec)| {
+                    matches!(spec, PluginDownloadSpec::Spiget(spiget) if spiget.resource_id == resource_id)
+                });
+                if let Some((existing_name, _)) = existing {
+                    log::warn!(
+                        "Spigot resource {resource_id} is already in the manifest as '{existing_name}'"
+                    );
+                }
+
+                // the resource is validated before it's written to the manifest
+                let plugin = SpigetPlugin::new(session, resource_id)
+                    .await
+                    .wrap_err_with(|| format!("Error getting Spigot resource {resource_id}"))?;
+                plugin.downloadable()?;
+
+                let spec = PluginDownloadSpec::Spiget(ManifestSpigetPlugin::new(resource_id));
+
+                (
+                    plugin.resource_name().to_string(),
+                    spec,
+                    resource_id.plugin_page(),
+                )
+            }
+            source => return Err(UnsupportedApiError(source).into()),
+        };
+
+        let plugin_name = match &self.name {
+            Some(name) => name.clone(),
+            None => match suggest_plugin_name(&title) {
+                name if name.is_empty() => return Err(NoPluginNameError(title).into()),
+                name => name,
+            },
+        };
+
+        if manifest.plugin.contains_key(&plugin_name) {
+            return Err(PluginExistsError(plugin_name).into());
+        }
+
+        let mut document = manifest.edit()?;
+        document.add_plugin(&plugin_name, &plugin)?;
+        document
+            .save()
+            .await
+            .wrap_err_with(|| format!("Error writing manifest '{}'", document.path().display()))?;
+
+        let out = AddOutput {
+            plugin_name,
+            plugin_type: self.source,
+            title,
+            page_url,
+            manifest_path: document.path().to_path_buf(),
+        };
+
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
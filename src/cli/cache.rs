@@ -0,0 +1,153 @@
+//! The 'cache' subcommand for moving cached data between machines.
+
+use std::path::PathBuf;
+
+use clap::Args;
+use miette::{Context, IntoDiagnostic};
+use owo_colors::OwoColorize;
+
+use crate::{
+    bundle::{export_bundle, import_bundle, BundleSummary},
+    cli::Subcommand,
+    manifest::Manifest,
+    output::DataDisplay,
+    session::IoSession,
+};
+
+/// The 'cache' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Cache {
+    #[command(subcommand)]
+    pub command: CacheCommands,
+}
+
+#[derive(clap::Subcommand, Debug, Clone)]
+pub enum CacheCommands {
+    /// Pack all cached plugin files and API data needed by the manifest into a bundle.
+    Export(CacheExport),
+    /// Merge a bundle into the download cache.
+    Import(CacheImport),
+}
+
+/// The 'cache export' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct CacheExport {
+    /// The path to write the bundle (a tar archive) to.
+    #[arg(short = 'o', long, value_name = "PATH")]
+    pub out: PathBuf,
+}
+
+/// The 'cache import' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct CacheImport {
+    /// The path to the bundle to import.
+    #[arg(value_name = "BUNDLE")]
+    pub bundle: PathBuf,
+}
+
+/// The output of the 'cache export' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct CacheExportOutput {
+    pub summary: BundleSummary,
+    pub bundle_path: PathBuf,
+    /// The size of the bundle in bytes.
+    pub bundle_size: u64,
+}
+
+/// The output of the 'cache import' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct CacheImportOutput {
+    pub summary: BundleSummary,
+    pub bundle_path: PathBuf,
+}
+
+impl DataDisplay for CacheExportOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        writeln!(
+            w,
+            "Exported cache of manifest '{0}' to '{1}'",
+            self.summary.manifest_name.green(),
+            self.bundle_path.to_string_lossy().green()
+        )?;
+
+        let bundle_size = pretty_bytes::converter::convert(self.bundle_size as _);
+
+        write!(
+            w,
+            "{0} plugin files, {1} API responses ({2})",
+            self.summary.plugin_files.green(),
+            self.summary.http_entries.green(),
+            bundle_size.green()
+        )
+    }
+}
+
+impl DataDisplay for CacheImportOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        writeln!(
+            w,
+            "Imported bundle '{0}' of manifest '{1}'",
+            self.bundle_path.to_string_lossy().green(),
+            self.summary.manifest_name.green()
+        )?;
+
+        write!(
+            w,
+            "{0} plugin files, {1} API responses",
+            self.summary.plugin_files.green(),
+            self.summary.http_entries.green()
+        )
+    }
+}
+
+impl Subcommand for Cache {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        match &self.command {
+            CacheCommands::Export(export) => export.run(session, manifest).await,
+            CacheCommands::Import(import) => import.run(session, manifest).await,
+        }
+    }
+}
+
+impl Subcommand for CacheExport {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let (bundle_size, summary) = export_bundle(session.download_cache(), manifest, &self.out)
+            .await
+            .wrap_err_with(|| {
+                format!("Error exporting cache bundle to '{}'", self.out.display())
+            })?;
+
+        let out = CacheExportOutput {
+            summary,
+            bundle_path: self.out.clone(),
+            bundle_size,
+        };
+
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
+
+impl Subcommand for CacheImport {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let summary = import_bundle(session.download_cache(), &self.bundle)
+            .await
+            .wrap_err_with(|| {
+                format!("Error importing cache bundle '{}'", self.bundle.display())
+            })?;
+
+        if summary.manifest_name != manifest.meta.manifest_name {
+            log::warn!(
+                "Bundle was exported for the manifest '{}', but was imported into the cache of manifest '{}'",
+                summary.manifest_name,
+                manifest.meta.manifest_name
+            );
+        }
+
+        let out = CacheImportOutput {
+            summary,
+            bundle_path: self.bundle.clone(),
+        };
+
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
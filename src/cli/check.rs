@@ -0,0 +1,212 @@
+//! The 'check' subcommand for validating every plugin in the manifest against its API.
+
+use clap::Args;
+use miette::IntoDiagnostic;
+use owo_colors::OwoColorize;
+use serde_json::Value;
+
+use crate::{
+    adapter::{
+        spiget::{ManifestSpigetPlugin, SpigetPlugin},
+        PluginApiType, PluginVersion, UnsupportedApiError,
+    },
+    cli::Subcommand,
+    error::NotFoundError,
+    manifest::{Manifest, ManifestEntryError, PluginDownloadSpec},
+    output::DataDisplay,
+    session::IoSession,
+    util::did_you_mean,
+};
+
+/// The 'check' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Check {}
+
+/// A plugin in the manifest that passed the check.
+#[derive(Debug, serde::Serialize)]
+pub struct CheckedPlugin {
+    pub plugin_name: String,
+    pub api_type: PluginApiType,
+    /// The number of versions that have been published for the plugin.
+    pub version_count: usize,
+    /// Whether the plugin is pinned to a version in the manifest.
+    pub pinned: bool,
+    /// The name of the version that the plugin resolves to (the pinned version, or the latest version).
+    pub version_name: String,
+    /// The identifier of the version that the plugin resolves to.
+    pub version_identifier: String,
+}
+
+/// The output of the 'check' subcommand, when no problems were found.
+#[derive(Debug, serde::Serialize)]
+pub struct CheckOutput {
+    pub plugins: Vec<CheckedPlugin>,
+}
+
+/// Error with every problem that was found when checking the manifest.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("Found problems with {} of the {checked} plugins in the manifest", problems.len())]
+pub struct CheckError {
+    /// The number of plugins that were checked.
+    pub checked: usize,
+    #[related]
+    pub problems: Vec<ManifestEntryError>,
+}
+
+impl DataDisplay for CheckOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        for plugin in &self.plugins {
+            let resolves_to = if plugin.pinned {
+                "pinned version"
+            } else {
+                "latest version"
+            };
+
+            writeln!(
+                w,
+                "{0} plugin '{1}' has {2} versions, {3} '{4}' (ID {5})",
+                plugin.api_type,
+                plugin.plugin_name.bright_green(),
+                plugin.version_count,
+                resolves_to,
+                plugin.version_name.bright_green(),
+                plugin.version_identifier.bright_green(),
+            )?;
+        }
+
+        write!(
+            w,
+            "Checked {} plugins, no problems found",
+            self.plugins.len().green()
+        )
+    }
+
+    /// One record per plugin.
+    fn records(&self) -> Result<Vec<Value>, serde_json::Error> {
+        self.plugins.iter().map(serde_json::to_value).collect()
+    }
+}
+
+impl Subcommand for Check {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let mut plugin_names = manifest.plugin.keys().collect::<Vec<_>>();
+        plugin_names.sort();
+
+        let results = futures::future::join_all(
+            plugin_names
+                .iter()
+                .map(|plugin_name| check_plugin(session, manifest, plugin_name)),
+        )
+        .await;
+
+        let (plugins, problems): (Vec<_>, Vec<_>) = results.into_iter().partition(Result::is_ok);
+        let problems = problems
+            .into_iter()
+            .filter_map(Result::err)
+            .collect::<Vec<_>>();
+
+        if !problems.is_empty() {
+            return Err(CheckError {
+                checked: plugin_names.len(),
+                problems,
+            }
+            .into());
+        }
+
+        let out = CheckOutput {
+            plugins: plugins.into_iter().filter_map(Result::ok).collect(),
+        };
+
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
+
+/// Check a plugin in the manifest. Problems point at the plugin's entry in the manifest source.
+#[inline]
+async fn check_plugin(
+    session: &IoSession,
+    manifest: &Manifest,
+    plugin_name: &str,
+) -> Result<CheckedPlugin, ManifestEntryError> {
+    match &manifest.plugin[plugin_name] {
+        PluginDownloadSpec::Spiget(spiget) => {
+            check_spiget_plugin(session, manifest, plugin_name, spiget).await
+        }
+        PluginDownloadSpec::Hangar(_) => Err(manifest.entry_error(
+            plugin_name,
+            Some("type"),
+            "this source is not supported yet",
+            UnsupportedApiError(PluginApiType::Hangar),
+        )),
+    }
+}
+
+/// Check that a Spiget plugin exists, has versions, can be downloaded and that its pinned version (if any) resolves.
+#[inline]
+async fn check_spiget_plugin(
+    session: &IoSession,
+    manifest: &Manifest,
+    plugin_name: &str,
+    spiget: &ManifestSpigetPlugin,
+) -> Result<CheckedPlugin, ManifestEntryError> {
+    let plugin = SpigetPlugin::from_manifest(session, manifest, plugin_name, spiget)
+        .await
+        .map_err(|error| match error.downcast::<ManifestEntryError>() {
+            Ok(error) => error,
+            Err(error) => {
+                manifest.entry_error(plugin_name, None, "this plugin could not be checked", error)
+            }
+        })?;
+
+    plugin.check_downloadable(manifest, plugin_name)?;
+
+    let version_count = plugin.iter_versions().count();
+    if version_count == 0 {
+        return Err(manifest.entry_error(
+            plugin_name,
+            None,
+            "no versions have been published for this resource",
+            NotFoundError::Version,
+        ));
+    }
+
+    let pinned_version = spiget.pinned_version();
+    let version = match plugin
+        .resolve_version(plugin_name, &pinned_version, &manifest.server_target())
+        .await
+    {
+        Ok(Some(version)) => version,
+        Ok(None) => {
+            let error = manifest.entry_error(
+                plugin_name,
+                spiget.pin_key(),
+                "no version of the plugin matches this",
+                NotFoundError::Version,
+            );
+
+            return Err(
+                match did_you_mean(&plugin.similar_versions(&pinned_version)) {
+                    Some(help) => error.with_help(help),
+                    None => error,
+                },
+            );
+        }
+        Err(error) => {
+            return Err(manifest.entry_error(
+                plugin_name,
+                spiget.pin_key(),
+                "this version could not be resolved",
+                error,
+            ))
+        }
+    };
+
+    Ok(CheckedPlugin {
+        plugin_name: plugin_name.to_string(),
+        api_type: PluginApiType::Spiget,
+        version_count,
+        pinned: spiget.pin_key().is_some(),
+        version_name: version.version_name().into_owned(),
+        version_identifier: version.version_identifier().into_owned(),
+    })
+}
@@ -0,0 +1,232 @@
+//! The 'completions' subcommand for generating shell completion scripts, and the dynamic completers for arguments
+//! that complete with values from the manifest and from cached API data.
+
+use std::collections::HashSet;
+use std::ffi::{OsStr, OsString};
+use std::future::Future;
+use std::io::Write;
+
+use clap::{Args, CommandFactory, Parser};
+use clap_complete::env::Shells;
+use clap_complete::{CompletionCandidate, Shell};
+use miette::IntoDiagnostic;
+
+use crate::{
+    adapter::{
+        spiget::{SpigetPlugin, SpigetResourceVersion},
+        PluginVersion,
+    },
+    cli::{manifest_path, Cli},
+    config::Config,
+    manifest::{Manifest, PluginDownloadSpec},
+    session::{CachePolicy, CacheSettings, IoSession},
+};
+
+/// The environment variable that the shell sets when it asks pluginstall for completions.
+pub static COMPLETE_ENV_VAR: &str = "COMPLETE";
+
+/// The 'completions' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Completions {
+    /// The shell to generate the completion script for.
+    #[arg(value_name = "SHELL")]
+    pub shell: Shell,
+
+    /// Generate a static completion script.
+    ///
+    /// By default the script calls pluginstall to complete the command line, so that plugin names and versions can be
+    /// completed from the manifest and cached API data. Static scripts only complete subcommands and options.
+    #[arg(long = "static", action = clap::ArgAction::SetTrue)]
+    pub static_script: bool,
+}
+
+impl Completions {
+    /// Write the completion script to `stdout`. This does not need a manifest.
+    #[inline]
+    pub fn run(&self) -> miette::Result<()> {
+        let mut command = Cli::command();
+        let name = command.get_name().to_string();
+        let mut stdout = std::io::stdout().lock();
+
+        if self.static_script {
+            // the generator panics on write errors, so the script is written to stdout separately
+            let mut script = Vec::new();
+            clap_complete::generate(self.shell, &mut command, &name, &mut script);
+            return stdout.write_all(&script).into_diagnostic();
+        }
+
+        let shells = Shells::builtins();
+        let completer = shells
+            .completer(&self.shell.to_string())
+            .expect("all shells supported by clap_complete have a dynamic completer");
+
+        completer
+            .write_registration(COMPLETE_ENV_VAR, &name, &name, &name, &mut stdout)
+            .into_diagnostic()
+    }
+}
+
+/// Run a future to completion from a completer. Completers are called before the async runtime is started.
+#[inline]
+fn block_on<F: Future>(future: F) -> Option<F::Output> {
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .ok()?;
+
+    Some(runtime.block_on(future))
+}
+
+/// The words of the command line that is being completed, up to (but not including) the word under the cursor.
+#[inline]
+fn preceding_words() -> Vec<OsString> {
+    // the shell passes the command line after a '--', and (for some shells) the index of the word under the cursor
+    let words = std::env::args_os()
+        .skip_while(|arg| arg != "--")
+        .skip(1)
+        .collect::<Vec<_>>();
+
+    let index = std::env::var("_CLAP_COMPLETE_INDEX")
+        .ok()
+        .and_then(|index| index.parse().ok())
+        .unwrap_or(words.len().saturating_sub(1));
+
+    words.into_iter().take(index).collect()
+}
+
+/// Parse the manifest that the command line being completed refers to.
+#[inline]
+fn completion_manifest(config: &Config) -> Option<Manifest> {
+    // the command line is incomplete, so only the manifest option is read from it
+    let matches = Cli::command()
+        .ignore_errors(true)
+        .try_get_matches_from(preceding_words())
+        .ok()?;
+
+    let mut manifest = None;
+    let mut next = Some(&matches);
+    while let Some(matches) = next {
+        if let Ok(Some(path)) = matches.try_get_one::<std::path::PathBuf>("manifest") {
+            manifest = Some(path.clone());
+        }
+
+        next = matches.subcommand().map(|(_, matches)| matches);
+    }
+
+    block_on(Manifest::parse_from_file(manifest_path(
+        manifest.as_deref(),
+        config,
+    )))?
+    .ok()
+}
+
+/// Complete the name of a plugin from the keys of the manifest.
+#[inline]
+pub fn complete_plugin_name(current: &OsStr) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+
+    let config = block_on(Config::load())
+        .and_then(Result::ok)
+        .unwrap_or_default();
+    let Some(manifest) = completion_manifest(&config) else {
+        return Vec::new();
+    };
+
+    let mut names = manifest
+        .plugin
+        .into_keys()
+        .filter(|name| name.starts_with(current))
+        .collect::<Vec<_>>();
+    names.sort();
+
+    names.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Complete the name of a version of the plugin in the command line, from cached API data.
+#[inline]
+pub fn complete_version_name(current: &OsStr) -> Vec<CompletionCandidate> {
+    let mut seen = HashSet::new();
+    let mut names = completion_versions(current, |version| {
+        CompletionCandidate::new(version.version_name().into_owned())
+    });
+    // several versions can have the same name, and they are not necessarily next to each other
+    names.retain(|name| seen.insert(name.get_value().to_owned()));
+    names
+}
+
+/// Complete the identifier of a version of the plugin in the command line, from cached API data.
+/// The version names are shown as the help of the candidates.
+#[inline]
+pub fn complete_version_ident(current: &OsStr) -> Vec<CompletionCandidate> {
+    completion_versions(current, |version| {
+        CompletionCandidate::new(version.version_identifier().into_owned())
+            .help(Some(version.version_name().into_owned().into()))
+    })
+}
+
+/// Complete a version of the plugin in the command line from cached API data, with the candidates created by `candidate`.
+///
+/// Nothing is fetched from the network, so versions can only be completed after they have been fetched by an earlier invocation.
+#[inline]
+fn completion_versions(
+    current: &OsStr,
+    candidate: impl Fn(&SpigetResourceVersion) -> CompletionCandidate,
+) -> Vec<CompletionCandidate> {
+    let Some(current) = current.to_str() else {
+        return Vec::new();
+    };
+
+    // the command line up to the version option (without the option itself) is a complete command line
+    let mut words = preceding_words();
+    if words
+        .last()
+        .is_some_and(|word| word.to_string_lossy().starts_with('-'))
+    {
+        words.pop();
+    }
+
+    let Ok(cli) = Cli::try_parse_from(words) else {
+        return Vec::new();
+    };
+    let Some(plugin_spec) = cli.command.plugin_spec() else {
+        return Vec::new();
+    };
+
+    let versions = block_on(async {
+        let config = Config::load().await.unwrap_or_default();
+        let manifest = cli.manifest(&config).await.ok()?;
+
+        let PluginDownloadSpec::Spiget(spiget) = manifest.plugin(&plugin_spec.plugin_name).ok()?
+        else {
+            return None;
+        };
+
+        let download_cache = cli
+            .download_cache(&config, &manifest.meta.manifest_name)
+            .await
+            .ok()?;
+        let session = IoSession::new(
+            cli.cli_output(&config),
+            download_cache,
+            CacheSettings {
+                policy: CachePolicy::Offline,
+                ..cli.cache_settings()
+            },
+            cli.network_settings(&config),
+        );
+
+        let plugin = SpigetPlugin::new(&session, spiget.resource_id).await.ok()?;
+
+        let versions = plugin
+            .iter_versions()
+            .map(|version| candidate(&version))
+            .filter(|candidate| candidate.get_value().to_string_lossy().starts_with(current))
+            .collect::<Vec<_>>();
+
+        Some(versions)
+    });
+
+    versions.flatten().unwrap_or_default()
+}
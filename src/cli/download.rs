@@ -7,8 +7,9 @@ use miette::{bail, Context, IntoDiagnostic};
 use owo_colors::{AnsiColors, OwoColorize};
 
 use crate::{
-    adapter::{spiget::SpigetPlugin, PluginApiType, VersionSpec},
+    adapter::{spiget::SpigetPlugin, PluginApiType, PluginVersion, VersionSpec},
     cli::Subcommand,
+    dependencies::MissingDependency,
     error::diagnostics,
     manifest::{Manifest, PluginDownloadSpec},
     output::DataDisplay,
@@ -27,6 +28,10 @@ pub struct Download {
     /// The directory to download the file into. By default the file will be downloaded into the working directory.
     #[arg(short = 'o', long, value_name = "PATH")]
     pub out_dir: Option<PathBuf>,
+    /// Don't check the manifest for missing hard dependencies of the downloaded plugin.
+    /// The check only reads the download cache, so it never downloads the jars of other plugins.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub no_dependency_check: bool,
 }
 
 /// The output of the 'download' subcommand.
@@ -34,6 +39,9 @@ pub struct Download {
 pub struct DownloadOutput {
     pub report: DownloadReport,
     pub download_path: PathBuf,
+    /// The hard dependencies of the downloaded plugin that aren't in the manifest.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub missing_dependencies: Vec<MissingDependency>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -48,11 +56,6 @@ pub struct VersionNotFound {
 }
 
 impl DataDisplay for DownloadOutput {
-    fn write_json(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
-        let json_string = serde_json::to_string(self).unwrap();
-        write!(w, "{json_string}")
-    }
-
     fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
         writeln!(
             w,
@@ -70,6 +73,22 @@ impl DataDisplay for DownloadOutput {
 
         write!(w, "Download size: {0} ({1})", download_size.green(), cached)?;
 
+        for missing in &self.missing_dependencies {
+            write!(
+                w,
+                "\n{0} missing dependency '{1}', needed by {2}",
+                "Warning:".yellow(),
+                missing.name.red(),
+                missing.required_by.join(", "),
+            )?;
+        }
+        if !self.missing_dependencies.is_empty() {
+            write!(
+                w,
+                "\nAdd the missing dependencies with the 'add' subcommand, or with 'tree --add-missing'"
+            )?;
+        }
+
         Ok(())
     }
 }
@@ -81,8 +100,17 @@ impl Subcommand for Download {
         match plugin_manifest {
             PluginDownloadSpec::Hangar(_) => todo!(),
             PluginDownloadSpec::Spiget(spiget) => {
-                let plugin = SpigetPlugin::new(session, spiget.resource_id).await?;
-                let version_spec = self.version.get();
+                let plugin = SpigetPlugin::from_manifest(
+                    session,
+                    manifest,
+                    &self.plugin.plugin_name,
+                    spiget,
+                )
+                .await
+                .wrap_err_with(|| format!("Error getting plugin '{}'", self.plugin.plugin_name))?;
+                let version_spec = self.version.get_or(spiget.pinned_version());
+
+                plugin.check_downloadable(manifest, &self.plugin.plugin_name)?;
 
                 let out_dir = match &self.out_dir {
                     None => Path::new(".").to_path_buf(), // by default download to working directory
@@ -94,10 +122,18 @@ impl Subcommand for Download {
                     bail!(diagnostics::invalid_download_dir(&out_dir));
                 }
 
-                let Some(version) = plugin.version_from_spec(&version_spec)? else {
-                    bail!(diagnostics::version_not_found(
+                let Some(version) = plugin
+                    .resolve_version(
+                        &self.plugin.plugin_name,
+                        &version_spec,
+                        &manifest.server_target(),
+                    )
+                    .await?
+                else {
+                    return Err(plugin.version_not_found(
+                        manifest,
                         &self.plugin.plugin_name,
-                        &version_spec
+                        &version_spec,
                     ));
                 };
 
@@ -113,9 +149,22 @@ impl Subcommand for Download {
                     .await
                     .wrap_err("Error downloading Spiget plugin")?;
 
+                let missing_dependencies = if self.no_dependency_check {
+                    Vec::new()
+                } else {
+                    missing_dependencies(
+                        session,
+                        manifest,
+                        &self.plugin.plugin_name,
+                        &version.version_identifier(),
+                    )
+                    .await
+                };
+
                 let out = DownloadOutput {
                     report,
                     download_path: out_dir,
+                    missing_dependencies,
                 };
 
                 session.cli_output().display(&out).into_diagnostic()?;
@@ -125,3 +174,60 @@ impl Subcommand for Download {
         Ok(())
     }
 }
+
+/// The hard dependencies of the downloaded plugin that aren't in the manifest, so that it isn't silently installed
+/// without the plugins it needs. A dependency is in the manifest if it's the name of a plugin in the manifest,
+/// or the descriptor name of a cached version of one.
+///
+/// Only the download cache is read, so nothing is downloaded. The check is skipped (with a warning) if the jar
+/// of the downloaded version can't be read from the cache, since the plugin was already downloaded.
+#[inline]
+async fn missing_dependencies(
+    session: &IoSession,
+    manifest: &Manifest,
+    plugin_name: &str,
+    version_identifier: &str,
+) -> Vec<MissingDependency> {
+    let cache = session.download_cache();
+
+    let descriptor = match cache.jar_metadata(plugin_name, version_identifier).await {
+        Ok(Some(metadata)) => metadata.descriptor,
+        Ok(None) => {
+            log::debug!(
+                "The downloaded version is not cached, so its dependencies are not checked"
+            );
+            return Vec::new();
+        }
+        Err(error) => {
+            log::warn!("Could not check the manifest for missing dependencies: {error:?}");
+            return Vec::new();
+        }
+    };
+
+    let mut names = manifest.plugin.keys().cloned().collect::<Vec<_>>();
+    for manifest_name in manifest.plugin.keys() {
+        let Some(cached) = cache.cached_plugin(manifest_name).await else {
+            continue;
+        };
+
+        for cached_version in cached.versions.keys() {
+            if let Ok(Some(metadata)) = cache.jar_metadata(manifest_name, cached_version).await {
+                names.push(metadata.descriptor.name);
+            }
+        }
+    }
+
+    descriptor
+        .depend
+        .into_iter()
+        .filter(|dependency| {
+            !names
+                .iter()
+                .any(|name| name.eq_ignore_ascii_case(dependency))
+        })
+        .map(|name| MissingDependency {
+            name,
+            required_by: vec![plugin_name.to_string()],
+        })
+        .collect()
+}
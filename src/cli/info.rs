@@ -3,8 +3,9 @@
 // TODO: allow this command to display info about a specific version too
 
 use clap::Args;
-use miette::{bail, Context, IntoDiagnostic};
+use miette::{Context, IntoDiagnostic};
 use owo_colors::OwoColorize;
+use serde_json::Value;
 
 use crate::{
     adapter::{
@@ -12,9 +13,9 @@ use crate::{
         PluginDetails, PluginVersion,
     },
     cli::Subcommand,
-    error::{diagnostics, NotFoundError},
+    error::NotFoundError,
     manifest::{Manifest, PluginDownloadSpec},
-    output::DataDisplay,
+    output::{version_record, DataDisplay},
     session::IoSession,
 };
 
@@ -41,11 +42,6 @@ pub struct InfoOutput<P: PluginDetails, V: PluginVersion> {
 }
 
 impl<P: PluginDetails, V: PluginVersion> DataDisplay for InfoOutput<P, V> {
-    fn write_json(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
-        let json_string = serde_json::to_string(self).unwrap();
-        write!(w, "{json_string}")
-    }
-
     fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
         writeln!(
             w,
@@ -68,22 +64,45 @@ impl<P: PluginDetails, V: PluginVersion> DataDisplay for InfoOutput<P, V> {
                 .bright_green(),
         )
     }
+
+    /// A single record with the fields of the plugin and the version, and whether the version is the latest version.
+    fn records(&self) -> Result<Vec<Value>, serde_json::Error> {
+        let mut record = version_record(&self.details, &self.version)?;
+        record.insert("latest".to_string(), self.latest.into());
+
+        Ok(vec![Value::Object(record)])
+    }
 }
 
 impl Subcommand for Info {
     async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
         let plugin_manifest = manifest.plugin(&self.plugin.plugin_name)?;
-        let version_spec = self.version_spec.get();
 
         match plugin_manifest {
             PluginDownloadSpec::Spiget(spiget) => {
-                let plugin = SpigetPlugin::new(&session, spiget.resource_id).await?;
+                let version_spec = self.version_spec.get_or(spiget.pinned_version());
+                let plugin = SpigetPlugin::from_manifest(
+                    session,
+                    manifest,
+                    &self.plugin.plugin_name,
+                    spiget,
+                )
+                .await
+                .wrap_err_with(|| format!("Error getting plugin '{}'", self.plugin.plugin_name))?;
 
                 let latest = version_spec.is_latest();
-                let Some(version) = plugin.version_from_spec(&version_spec)? else {
-                    bail!(diagnostics::version_not_found(
+                let Some(version) = plugin
+                    .resolve_version(
+                        &self.plugin.plugin_name,
+                        &version_spec,
+                        &manifest.server_target(),
+                    )
+                    .await?
+                else {
+                    return Err(plugin.version_not_found(
+                        manifest,
                         &self.plugin.plugin_name,
-                        &version_spec
+                        &version_spec,
                     ));
                 };
 
@@ -0,0 +1,365 @@
+//! The 'init' subcommand for creating a new manifest, optionally from the plugins in an existing plugins directory.
+
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use miette::{Context, IntoDiagnostic};
+use owo_colors::OwoColorize;
+use rq::Url;
+use serde_json::Value;
+
+use crate::{
+    adapter::{
+        spiget::{self, ManifestSpigetPlugin, ResourceId, SpigetPlugin},
+        PluginApiType, PluginVersion,
+    },
+    cli::Subcommand,
+    jar::{self, PluginDescriptor},
+    manifest::{suggest_plugin_name, Manifest, ManifestResult, PluginDownloadSpec},
+    output::DataDisplay,
+    session::IoSession,
+    version::normalize_version_name,
+};
+
+/// The 'init' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Init {
+    /// A plugins directory (like the `plugins/` directory of a server) to create the manifest from.
+    /// Every jar in the directory is matched with a plugin in the plugin sources.
+    /// Plugins are pinned to the installed version if a version with the same name was found.
+    #[arg(long, value_name = "PLUGINS_DIR")]
+    pub from: Option<PathBuf>,
+    /// The name of the manifest. Defaults to the name of the directory that the manifest is created in.
+    #[arg(long, value_name = "NAME")]
+    pub name: Option<String>,
+    /// Overwrite the manifest file if it already exists.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub force: bool,
+}
+
+/// A jar that was matched with a plugin and added to the manifest.
+#[derive(Debug, serde::Serialize)]
+pub struct InitPlugin {
+    /// The path of the jar.
+    pub file: PathBuf,
+    pub plugin_name: String,
+    pub plugin_type: PluginApiType,
+    /// The name of the plugin in its API.
+    pub title: String,
+    pub page_url: Url,
+    /// The version of the plugin in the jar.
+    pub installed_version: String,
+    /// The identifier of the version that the plugin was pinned to, or [`None`] if no version matched the installed version.
+    pub pinned_version_identifier: Option<String>,
+    /// The entry that adds the plugin to the manifest.
+    #[serde(skip)]
+    pub plugin: PluginDownloadSpec,
+}
+
+/// A jar that could not be matched with a plugin, and has to be added to the manifest manually.
+#[derive(Debug, serde::Serialize)]
+pub struct UnmatchedJar {
+    /// The path of the jar.
+    pub file: PathBuf,
+    /// The name of the plugin in the jar, if its plugin descriptor could be read.
+    pub name: Option<String>,
+    /// The version of the plugin in the jar, if its plugin descriptor could be read.
+    pub version: Option<String>,
+    /// Why the jar could not be matched.
+    pub reason: String,
+}
+
+/// The result of matching a jar with a plugin.
+enum JarMatch {
+    Matched(Box<InitPlugin>),
+    Unmatched(UnmatchedJar),
+}
+
+/// The output of the 'init' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct InitOutput {
+    pub manifest_name: String,
+    pub manifest_path: PathBuf,
+    pub plugins: Vec<InitPlugin>,
+    pub unmatched: Vec<UnmatchedJar>,
+}
+
+/// Error for when the manifest file that would be created already exists.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("The manifest file '{}' already exists.", .0.display())]
+#[diagnostic(help(
+    "Use '--force' to overwrite it, or '--manifest' to create the manifest somewhere else."
+))]
+pub struct ManifestExistsError(pub PathBuf);
+
+impl DataDisplay for InitOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        for plugin in &self.plugins {
+            let pin = match &plugin.pinned_version_identifier {
+                Some(ident) => format!("pinned to version ID {}", ident.bright_green()),
+                None => "not pinned, no version matched".to_string(),
+            };
+
+            writeln!(
+                w,
+                "Added '{0}' as {1} plugin '{2}' ({3}), installed version '{4}' {5}",
+                plugin.file.display(),
+                plugin.plugin_type,
+                plugin.plugin_name.bright_green(),
+                plugin.page_url,
+                plugin.installed_version,
+                pin,
+            )?;
+        }
+
+        for jar in &self.unmatched {
+            writeln!(
+                w,
+                "Could not match '{0}': {1}",
+                jar.file.display().yellow(),
+                jar.reason,
+            )?;
+        }
+
+        write!(
+            w,
+            "Created manifest '{0}' in '{1}' with {2} plugins",
+            self.manifest_name.bright_green(),
+            self.manifest_path.display().bright_green(),
+            self.plugins.len().green(),
+        )?;
+
+        if !self.unmatched.is_empty() {
+            write!(
+                w,
+                ", {} jars have to be added manually",
+                self.unmatched.len().yellow()
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// One record per jar, with the fields of the added plugin or the unmatched jar.
+    fn records(&self) -> Result<Vec<Value>, serde_json::Error> {
+        let plugins = self.plugins.iter().map(serde_json::to_value);
+        let unmatched = self.unmatched.iter().map(serde_json::to_value);
+
+        plugins.chain(unmatched).collect()
+    }
+}
+
+impl Init {
+    /// The empty manifest that will be created at the given path, named after the `--name` option
+    /// or the directory that the manifest is created in.
+    #[inline]
+    pub fn new_manifest(&self, path: &Path) -> ManifestResult<Manifest> {
+        let name = match &self.name {
+            Some(name) => name.clone(),
+            None => {
+                let parent = path
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty());
+                let dir = match parent {
+                    Some(parent) => std::path::absolute(parent)?,
+                    None => std::env::current_dir()?,
+                };
+
+                dir.file_name().map_or_else(
+                    || "plugins".to_string(),
+                    |name| name.to_string_lossy().into_owned(),
+                )
+            }
+        };
+
+        Manifest::new(&name, path)
+    }
+}
+
+impl Subcommand for Init {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let mut document = manifest.edit()?;
+
+        let exists = tokio::fs::try_exists(document.path())
+            .await
+            .into_diagnostic()?;
+        if exists && !self.force {
+            return Err(ManifestExistsError(document.path().to_path_buf()).into());
+        }
+
+        let mut out = InitOutput {
+            manifest_name: manifest.meta.manifest_name.clone(),
+            manifest_path: document.path().to_path_buf(),
+            plugins: Vec::new(),
+            unmatched: Vec::new(),
+        };
+
+        if let Some(plugins_dir) = &self.from {
+            let jars = jar::plugin_jars(plugins_dir)
+                .await
+                .into_diagnostic()
+                .wrap_err_with(|| {
+                    format!(
+                        "Error reading plugins directory '{}'",
+                        plugins_dir.display()
+                    )
+                })?;
+
+            let matches =
+                futures::future::join_all(jars.into_iter().map(|jar| match_jar(session, jar)))
+                    .await;
+
+            for jar_match in matches {
+                match jar_match {
+                    JarMatch::Matched(plugin) => out.plugins.push(*plugin),
+                    JarMatch::Unmatched(jar) => out.unmatched.push(jar),
+                }
+            }
+        }
+
+        // two jars can match the same plugin name (like two versions of a plugin), only the first one is added
+        let mut plugins = Vec::with_capacity(out.plugins.len());
+        for plugin in out.plugins {
+            match plugins
+                .iter()
+                .find(|added: &&InitPlugin| added.plugin_name == plugin.plugin_name)
+            {
+                Some(added) => out.unmatched.push(UnmatchedJar {
+                    reason: format!(
+                        "it matched plugin '{}', which was already added for '{}'",
+                        plugin.plugin_name,
+                        added.file.display()
+                    ),
+                    file: plugin.file,
+                    name: Some(plugin.title),
+                    version: Some(plugin.installed_version),
+                }),
+                None => plugins.push(plugin),
+            }
+        }
+        out.plugins = plugins;
+
+        for plugin in &out.plugins {
+            document.add_plugin(&plugin.plugin_name, &plugin.plugin)?;
+        }
+
+        document
+            .save()
+            .await
+            .wrap_err_with(|| format!("Error writing manifest '{}'", document.path().display()))?;
+
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
+
+/// Match a jar with a plugin from the plugin sources, by reading its plugin descriptor.
+///
+/// Jars that can't be read or matched are returned as [`JarMatch::Unmatched`], including jars that couldn't be matched
+/// because of an error from the APIs, so that one failing request doesn't abort the whole init.
+#[inline]
+async fn match_jar(session: &IoSession, file: PathBuf) -> JarMatch {
+    let unmatched = |file, descriptor: Option<&PluginDescriptor>, reason| {
+        JarMatch::Unmatched(UnmatchedJar {
+            file,
+            name: descriptor.map(|descriptor| descriptor.name.clone()),
+            version: descriptor.map(|descriptor| descriptor.version.clone()),
+            reason,
+        })
+    };
+
+    let descriptor = match jar::plugin_descriptor(&file).await {
+        Ok(descriptor) => descriptor,
+        Err(error) => {
+            log::debug!("Could not read plugin jar '{}': {error:?}", file.display());
+            return unmatched(file, None, error.to_string());
+        }
+    };
+
+    let plugin_name = suggest_plugin_name(&descriptor.name);
+    if plugin_name.is_empty() {
+        let reason = format!("no manifest name could be made from '{}'", descriptor.name);
+        return unmatched(file, Some(&descriptor), reason);
+    }
+
+    let plugin = match find_spiget_resource(session, &descriptor).await {
+        Ok(Some(plugin)) => plugin,
+        Ok(None) => {
+            let reason = format!("no Spigot resource named '{}' was found", descriptor.name);
+            return unmatched(file, Some(&descriptor), reason);
+        }
+        Err(error) => {
+            log::debug!(
+                "Error matching '{}' with a Spigot resource: {error:?}",
+                file.display()
+            );
+            let reason = format!("it could not be matched with a Spigot resource: {error}");
+            return unmatched(file, Some(&descriptor), reason);
+        }
+    };
+
+    let resource_id = plugin.resource_id();
+    if let Err(error) = plugin.downloadable() {
+        let reason = format!(
+            "it matched Spigot resource {resource_id} ({}), but {}",
+            resource_id.plugin_page(),
+            error.label()
+        );
+        return unmatched(file, Some(&descriptor), reason);
+    }
+
+    let installed_version = normalize_version_name(&descriptor.version);
+    let version = plugin
+        .iter_versions()
+        .find(|version| normalize_version_name(&version.version.name) == installed_version);
+
+    JarMatch::Matched(Box::new(InitPlugin {
+        file,
+        plugin_name,
+        plugin_type: PluginApiType::Spiget,
+        title: plugin.resource_name().to_string(),
+        page_url: resource_id.plugin_page(),
+        installed_version: descriptor.version,
+        pinned_version_identifier: version
+            .as_ref()
+            .map(|version| version.version_identifier().into_owned()),
+        plugin: PluginDownloadSpec::Spiget(ManifestSpigetPlugin {
+            version_id: version.map(|version| version.version.id),
+            ..ManifestSpigetPlugin::new(resource_id)
+        }),
+    }))
+}
+
+/// Find the Spigot resource of the plugin with the given descriptor.
+///
+/// If the website of the plugin is a Spigot resource page, that resource is used. Otherwise a resource is found by the name of the plugin
+/// with [`spiget::find_resource`].
+///
+/// Returns [`None`] if no resource matched.
+#[inline]
+async fn find_spiget_resource(
+    session: &IoSession,
+    descriptor: &PluginDescriptor,
+) -> miette::Result<Option<SpigetPlugin>> {
+    let website_resource = descriptor
+        .website
+        .as_deref()
+        .and_then(|website| Url::parse(website).ok())
+        .and_then(|website| ResourceId::from_plugin_page(&website));
+
+    if let Some(resource_id) = website_resource {
+        match SpigetPlugin::new(session, resource_id).await {
+            Ok(plugin) => return Ok(Some(plugin)),
+            Err(error) => log::debug!(
+                "Could not get Spigot resource {resource_id} from the website of '{}': {error:?}",
+                descriptor.name
+            ),
+        }
+    }
+
+    let resource = spiget::find_resource(session, &descriptor.name, &descriptor.authors).await?;
+    let Some(resource) = resource else {
+        return Ok(None);
+    };
+
+    SpigetPlugin::new(session, resource.id).await.map(Some)
+}
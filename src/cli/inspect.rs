@@ -0,0 +1,173 @@
+//! The 'inspect' subcommand for showing the metadata in a plugin jar, like its plugin descriptor.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+use miette::{Context, IntoDiagnostic};
+use owo_colors::OwoColorize;
+
+use crate::{
+    adapter::{spiget::SpigetPlugin, PluginApiType, PluginVersion, UnsupportedApiError},
+    cli::Subcommand,
+    jar::{self, JarMetadata},
+    manifest::{Manifest, PluginDownloadSpec},
+    output::{CliOutput, DataDisplay},
+    session::IoSession,
+};
+
+use super::{complete_plugin_name, VersionSpecArgs};
+
+/// The 'inspect' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Inspect {
+    /// A jar file, or the name of a plugin in the manifest.
+    /// For plugins in the manifest, the jar of the version in the download cache is inspected.
+    #[arg(value_name = "JAR_OR_PLUGIN_NAME", add = ArgValueCompleter::new(complete_plugin_name))]
+    pub target: String,
+    #[command(flatten)]
+    pub version: VersionSpecArgs,
+}
+
+/// The output of the 'inspect' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct InspectOutput {
+    /// The path of the inspected jar, or [`None`] if a jar in the download cache was inspected.
+    pub file: Option<PathBuf>,
+    /// The name of the plugin in the manifest, if a jar in the download cache was inspected.
+    pub plugin_name: Option<String>,
+    /// The identifier of the version, if a jar in the download cache was inspected.
+    pub version_identifier: Option<String>,
+    #[serde(flatten)]
+    pub metadata: JarMetadata,
+}
+
+/// Error for when the version of a plugin that should be inspected isn't in the download cache.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("Version '{version_name}' (ID {version_identifier}) of plugin '{plugin_name}' is not in the download cache.")]
+#[diagnostic(help(
+    "Download the version with the 'download' subcommand first, or inspect a jar file instead."
+))]
+pub struct NotInDownloadCacheError {
+    pub plugin_name: String,
+    pub version_name: String,
+    pub version_identifier: String,
+}
+
+impl DataDisplay for InspectOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        let descriptor = &self.metadata.descriptor;
+
+        write!(
+            w,
+            "{0} plugin '{1}' version '{2}'",
+            descriptor.kind,
+            descriptor.name.bright_green(),
+            descriptor.version.bright_green(),
+        )?;
+
+        match (&self.file, &self.plugin_name, &self.version_identifier) {
+            (Some(file), _, _) => writeln!(w, " in '{}'", file.display())?,
+            (None, Some(plugin_name), Some(ident)) => writeln!(
+                w,
+                " (cached version ID {0} of '{1}')",
+                ident.bright_green(),
+                plugin_name.bright_green()
+            )?,
+            _ => writeln!(w)?,
+        }
+
+        let optional = |value: &Option<String>| value.clone().unwrap_or("---".into());
+        let list = |values: &[String]| match values {
+            [] => "---".to_string(),
+            values => values.join(", "),
+        };
+        let java_version = self
+            .metadata
+            .java_version
+            .map_or("---".into(), |version| version.to_string());
+
+        writeln!(w, "Main class: {}", optional(&descriptor.main))?;
+        writeln!(w, "API version: {}", optional(&descriptor.api_version))?;
+        writeln!(w, "Authors: {}", list(&descriptor.authors))?;
+        writeln!(w, "Website: {}", optional(&descriptor.website))?;
+        writeln!(w, "Depends on: {}", list(&descriptor.depend))?;
+        writeln!(w, "Soft depends on: {}", list(&descriptor.softdepend))?;
+        writeln!(w, "Loads before: {}", list(&descriptor.loadbefore))?;
+        write!(w, "Requires Java: {}", java_version.bright_green())
+    }
+}
+
+impl Inspect {
+    /// The path of the jar to inspect, or [`None`] if a plugin in the manifest should be inspected.
+    #[inline]
+    pub fn jar_path(&self) -> Option<&Path> {
+        let path = Path::new(&self.target);
+        path.is_file().then_some(path)
+    }
+
+    /// Inspect the jar file at the given path. This does not need a manifest.
+    #[inline]
+    pub fn run_jar(&self, path: &Path, cli_output: &CliOutput) -> miette::Result<()> {
+        let metadata = File::open(path)
+            .map_err(jar::JarError::from)
+            .and_then(jar::read_jar_metadata)
+            .wrap_err_with(|| format!("Error inspecting '{}'", path.display()))?;
+
+        let out = InspectOutput {
+            file: Some(path.to_path_buf()),
+            plugin_name: None,
+            version_identifier: None,
+            metadata,
+        };
+
+        cli_output.display(&out).into_diagnostic()
+    }
+}
+
+impl Subcommand for Inspect {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let plugin_name = &self.target;
+
+        let PluginDownloadSpec::Spiget(spiget) = manifest.plugin(plugin_name)? else {
+            return Err(UnsupportedApiError(PluginApiType::Hangar).into());
+        };
+
+        let plugin = SpigetPlugin::from_manifest(session, manifest, plugin_name, spiget)
+            .await
+            .wrap_err_with(|| format!("Error getting plugin '{plugin_name}'"))?;
+        let version_spec = self.version.get_or(spiget.pinned_version());
+        let Some(version) = plugin
+            .resolve_version(plugin_name, &version_spec, &manifest.server_target())
+            .await?
+        else {
+            return Err(plugin.version_not_found(manifest, plugin_name, &version_spec));
+        };
+
+        let version_identifier = version.version_identifier().into_owned();
+        let metadata = session
+            .download_cache()
+            .jar_metadata(plugin_name, &version_identifier)
+            .await
+            .wrap_err_with(|| format!("Error inspecting plugin '{plugin_name}'"))?;
+
+        let Some(metadata) = metadata else {
+            return Err(NotInDownloadCacheError {
+                plugin_name: plugin_name.clone(),
+                version_name: version.version_name().into_owned(),
+                version_identifier,
+            }
+            .into());
+        };
+
+        let out = InspectOutput {
+            file: None,
+            plugin_name: Some(plugin_name.clone()),
+            version_identifier: Some(version_identifier),
+            metadata,
+        };
+
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
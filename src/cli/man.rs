@@ -0,0 +1,35 @@
+//! The 'man' subcommand for generating man pages.
+
+use std::path::PathBuf;
+
+use clap::{Args, CommandFactory};
+use miette::{Context, IntoDiagnostic};
+
+use crate::cli::Cli;
+
+/// The 'man' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Man {
+    /// Write man pages for pluginstall and all of its subcommands to this directory, instead of writing the man page
+    /// of pluginstall to stdout.
+    #[arg(short = 'o', long, value_name = "DIR")]
+    pub out_dir: Option<PathBuf>,
+}
+
+impl Man {
+    /// Write the man page(s). This does not need a manifest.
+    #[inline]
+    pub fn run(&self) -> miette::Result<()> {
+        let command = Cli::command();
+
+        match &self.out_dir {
+            Some(out_dir) => std::fs::create_dir_all(out_dir)
+                .and_then(|()| clap_mangen::generate_to(command, out_dir))
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Error writing man pages to '{}'", out_dir.display())),
+            None => clap_mangen::Man::new(command)
+                .render(&mut std::io::stdout().lock())
+                .into_diagnostic(),
+        }
+    }
+}
@@ -9,77 +9,285 @@ pub use info::*;
 mod download;
 pub use download::*;
 
+mod cache;
+pub use cache::*;
+
+mod check;
+pub use check::*;
+
+mod init;
+pub use init::*;
+
+mod inspect;
+pub use inspect::*;
+
+mod tree;
+pub use tree::*;
+
+mod why;
+pub use why::*;
+
+mod outdated;
+pub use outdated::*;
+
+mod update;
+pub use update::*;
+
+mod add;
+pub use add::*;
+
+mod remove;
+pub use remove::*;
+
+mod pin;
+pub use pin::*;
+
+mod search;
+pub use search::*;
+
+mod completions;
+pub use completions::*;
+
+mod man;
+pub use man::*;
+
 use crate::adapter::VersionSpec;
 
 /// An error that indicates a specified plugin name could not be found in the manifest.
-#[derive(thiserror::Error, Debug, Clone)]
-#[error("Could not find a plugin with the name '{0}' in the manifest.")]
-pub struct PluginNotFoundError(pub String);
+#[derive(thiserror::Error, miette::Diagnostic, Debug, Clone)]
+#[error("Could not find a plugin with the name '{name}' in the manifest.")]
+pub struct PluginNotFoundError {
+    /// The plugin name that was not found.
+    pub name: String,
+    /// The plugin names in the manifest that are closest to the name that was not found.
+    pub suggestions: Vec<String>,
+    #[help]
+    help: String,
+}
+
+impl PluginNotFoundError {
+    /// Create an error for a plugin name that isn't in the manifest, suggesting the closest plugin names in the manifest.
+    #[inline]
+    pub fn new(name: impl Into<String>, manifest: &Manifest) -> Self {
+        let name = name.into();
+        let suggestions = closest_matches(&name, manifest.plugin.keys().map(String::as_str))
+            .into_iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>();
 
-use crate::caching::{default_cache_directory_path, CacheResult, DownloadCache};
+        let location = match &manifest.path {
+            Some(path) => format!("the manifest file '{}'", path.display()),
+            None => "the manifest file".to_string(),
+        };
+        let mut help = format!("Plugins are defined as '[plugin.<name>]' tables in {location}.");
+        if let Some(did_you_mean) = did_you_mean(&suggestions) {
+            help = format!("{did_you_mean}\n{help}");
+        }
+
+        Self {
+            name,
+            suggestions,
+            help,
+        }
+    }
+}
+
+use crate::caching::{
+    default_cache_directory_path, legacy_cache_directory_path, migrate_legacy_cache_directory,
+    CacheResult, DownloadCache,
+};
 use crate::cli;
+use crate::config::Config;
 use crate::manifest::{Manifest, ManifestResult, DEFAULT_MANIFEST_FILE_NAME};
-use crate::output::CliOutput;
-use crate::session::IoSession;
-use std::borrow::Cow;
-use std::path::PathBuf;
+use crate::output::{CliOutput, OutputFormat, OutputTemplate};
+use crate::session::{CachePolicy, CacheSettings, IoSession, NetworkSettings};
+use crate::util::{adjust_log_level, closest_matches, did_you_mean, TableStyle, LOG_LEVEL};
+use crate::version::ReleaseChannel;
+use chrono::TimeDelta;
+use clap::ColorChoice;
+use clap_complete::ArgValueCompleter;
+use log::LevelFilter;
+use miette::{Context, IntoDiagnostic};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The largest value accepted by `--cache-ttl`, the number of seconds in the largest [`TimeDelta`].
+const MAX_CACHE_TTL_SECONDS: u64 = TimeDelta::MAX.num_seconds() as _;
 
 /// The CLI command with its parameters, parsed from the arguments provided to the process.
 #[derive(clap::Parser, Clone, Debug)]
 #[command(version, about, long_about = None)]
 pub struct Cli {
     /// The path to the manifest file.
-    #[arg(
-        short,
-        long,
-        value_name = "MANIFEST_FILE",
-        default_value = DEFAULT_MANIFEST_FILE_NAME
-    )]
-    pub manifest: PathBuf,
+    ///
+    /// Defaults to the manifest in the config, or `pluginstall.manifest.toml` if the config doesn't specify one.
+    #[arg(short, long, global = true, value_name = "MANIFEST_FILE")]
+    pub manifest: Option<PathBuf>,
 
-    /// Path to the download cache. Downloaded plugins will be cached in a subdirectory
-    /// in the download cache with the name of the manifest used.
+    /// Path to the download cache. Downloaded plugins will be cached in this directory.
     ///
-    /// By default the download cache that will be used is `$HOME/.pluginstall_cache`.
+    /// By default the download cache is a subdirectory with the name of the manifest in the cache directory
+    /// from the config, or in the user's cache directory (like `$XDG_CACHE_HOME/pluginstall/`).
     /// If no cache directory is provided and the default cache directory doesn't exist,
     /// then the default cache directory will be created.
     #[arg(long, value_name = "CACHE_PATH")]
     pub cache: Option<PathBuf>,
 
+    /// Network control arguments
+    #[clap(flatten)]
+    pub network_ctrl: NetworkCtrlArgs,
+
+    /// Cache control arguments
+    #[clap(flatten)]
+    pub cache_ctrl: CacheCtrlArgs,
+
     /// Output control arguments
     #[clap(flatten)]
     pub output_ctrl: OutputCtrlArgs,
 
+    /// Logging control arguments
+    #[clap(flatten)]
+    pub log_ctrl: LogCtrlArgs,
+
     /// The subcommand
     #[command(subcommand)]
     pub command: Commands,
 }
 
+/// Arguments for controlling how pluginstall talks to the network.
+#[derive(clap::Args, Debug, Clone)]
+pub struct NetworkCtrlArgs {
+    /// The timeout of HTTP requests in seconds.
+    #[arg(long, global = true, value_name = "SECONDS")]
+    pub timeout: Option<u64>,
+
+    /// The maximum number of concurrent HTTP requests.
+    #[arg(long, global = true, value_name = "N")]
+    pub concurrency: Option<usize>,
+}
+
 /// Arguments for controlling CLI output.
 #[derive(clap::Args, Debug, Clone)]
 pub struct OutputCtrlArgs {
-    /// Use JSON output instead of human readable output.
+    /// Use JSON output instead of human readable output. Shorthand for `--format json`.
+    /// Errors are written to stderr as JSON too, in an envelope like `{"error": {...}}`.
     #[arg(long, action=clap::ArgAction::SetTrue)]
     pub json: bool,
 
+    /// The format to write output in. Defaults to the format in the config, or human readable output.
+    /// With any format other than 'human', errors are written to stderr as JSON.
+    #[arg(long, value_name = "FORMAT", conflicts_with = "json")]
+    pub format: Option<OutputFormat>,
+
+    /// Render each record of the output with a template instead of an output format, one record per line.
+    /// Records have the same fields as in the JSON output (e.g. `--template '{{version_name}} {{publish_date}}'`).
+    ///
+    /// Templates use the Jinja syntax.
+    #[arg(long, value_name = "TEMPLATE", conflicts_with_all = ["json", "format", "template_file"])]
+    pub template: Option<String>,
+
+    /// Like `--template`, but the template is read from a file.
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["json", "format"])]
+    pub template_file: Option<PathBuf>,
+
+    /// The style of tables in human readable output.
+    /// Defaults to the style in the config, or 'ascii' when writing to a terminal and 'plain' otherwise.
+    #[arg(long, value_name = "STYLE")]
+    pub table_style: Option<TableStyle>,
+
     /// Don't write a newline at the end of the command output.
     #[arg(long, action=clap::ArgAction::SetTrue)]
     pub no_newline: bool,
 }
 
-/// Version specification arguments. If no argument is provided, then the latest version is specified.
+/// Arguments for controlling logging and colors.
+#[derive(clap::Args, Debug, Clone)]
+pub struct LogCtrlArgs {
+    /// Log more. Can be repeated (`-vv`) to log even more.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    pub verbose: u8,
+
+    /// Log less. Can be repeated (`-qq`) to log even less.
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    pub quiet: u8,
+
+    /// When to use colors in output and logs. With 'auto', colors are only used when writing to a terminal,
+    /// and not at all if the `NO_COLOR` environment variable is set.
+    #[arg(long, global = true, value_name = "WHEN", default_value = "auto")]
+    pub color: ColorChoice,
+
+    /// Also write logs to this file, as JSON lines. Logs are appended if the file exists.
+    #[arg(long, global = true, value_name = "PATH")]
+    pub log_file: Option<PathBuf>,
+}
+
+/// Arguments for controlling how the HTTP cache and download cache are used.
+#[derive(clap::Args, Debug, Clone)]
+pub struct CacheCtrlArgs {
+    /// Run without network access. API data and plugin files will only be read from the download cache,
+    /// so they must have been cached by an earlier (online) invocation.
+    #[arg(
+        long,
+        global = true,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["refresh", "no_cache", "cache_ttl"]
+    )]
+    pub offline: bool,
+
+    /// Revalidate all cached API data with the API, and download plugin files again instead of using cached files.
+    /// Fetched data will still be cached.
+    #[arg(long, global = true, action = clap::ArgAction::SetTrue)]
+    pub refresh: bool,
+
+    /// Bypass both the HTTP cache and the download cache. Nothing will be read from or written to the caches.
+    #[arg(
+        long,
+        global = true,
+        action = clap::ArgAction::SetTrue,
+        conflicts_with_all = ["refresh", "cache_ttl"]
+    )]
+    pub no_cache: bool,
+
+    /// Override the amount of time (in seconds) that API responses are cached for.
+    /// Cached API responses older than this will be fetched again.
+    #[arg(
+        long,
+        global = true,
+        value_name = "SECONDS",
+        value_parser = clap::value_parser!(u64).range(..=MAX_CACHE_TTL_SECONDS)
+    )]
+    pub cache_ttl: Option<u64>,
+
+    /// Download the jars of the most recent versions of plugins that aren't in the download cache,
+    /// to check if they're compatible with the server in the manifest.
+    /// Without this, only cached jars and the Minecraft versions that plugins were tested with are checked.
+    #[arg(long, global = true, action = clap::ArgAction::SetTrue, conflicts_with = "offline")]
+    pub check_jars: bool,
+}
+
+/// Version specification arguments. If no argument is provided, then the version that the plugin is pinned to in the manifest
+/// (or the latest version) is specified.
 #[derive(clap::Args, Debug, Clone)]
 #[group(required = false, multiple = false)]
 pub struct VersionSpecArgs {
     /// The name of a version to search for.
     /// If multiple versions have the same name, the latest version with that name will be chosen.
     ///
-    /// If neither the version name, or version identifier are specified, then the latest version will be used.
-    #[arg(long, short = 'V', value_name = "VERSION_NAME")]
+    /// If neither the version name, or version identifier are specified, then the version that the plugin is pinned to
+    /// in the manifest will be used, or the latest version if it isn't pinned.
+    #[arg(long, short = 'V', value_name = "VERSION_NAME", add = ArgValueCompleter::new(complete_version_name))]
     pub version_name: Option<String>,
     /// The unique version identifier of a version.
-    #[arg(long, short = 'I', value_name = "VERSION_IDENTIFIER")]
+    #[arg(long, short = 'I', value_name = "VERSION_IDENTIFIER", add = ArgValueCompleter::new(complete_version_ident))]
     pub version_ident: Option<String>,
+    /// A version spec. The most recent version that matches the spec will be chosen.
+    ///
+    /// Either a semver-style requirement for the version name (like `^2.3`, `~1.9` or `>=1.2, <2`), `before:<DATE>` for the
+    /// most recent version published before a date (like `before:2024-06-01`), or `regex:<REGEX>` for a version name regex.
+    /// Anything else is a version name.
+    #[arg(long, short = 'S', value_name = "VERSION_SPEC")]
+    pub spec: Option<VersionSpec>,
 }
 
 /// Arguments for specifying a specific plugin.
@@ -88,18 +296,78 @@ pub struct VersionSpecArgs {
 pub struct PluginSpecArgs {
     /// The name of the plugin in the manifest file.
     /// Download strategy for the is specified in the manifest file under this key.
-    #[arg(value_name = "PLUGIN_NAME")]
+    #[arg(value_name = "PLUGIN_NAME", add = ArgValueCompleter::new(complete_plugin_name))]
     pub plugin_name: String,
 }
 
+/// Help for the subcommands that resolve the versions of plugins, which check if the versions are compatible with the server.
+const COMPATIBILITY_CHECK_HELP: &str = "If the manifest sets `[meta] minecraft_version`, unpinned plugins resolve to their latest version that is compatible with the server. \
+This is checked with the plugin descriptors of the jars in the download cache, and the Minecraft versions that plugins were tested with. \
+With `--check-jars`, the jars of the most recent versions of each plugin are downloaded into the download cache to check them.";
+
 #[derive(clap::Subcommand, Clone, Debug)]
 pub enum Commands {
     /// List all versions of a plugin.
     Versions(cli::Versions),
     /// Show info about a plugin.
+    #[command(after_long_help = COMPATIBILITY_CHECK_HELP)]
     Info(cli::Info),
     /// Download a plugin.
+    #[command(after_long_help = COMPATIBILITY_CHECK_HELP)]
     Download(cli::Download),
+    /// Show the metadata of a plugin jar, like the name, version and dependencies in its plugin descriptor,
+    /// and the Java version that it needs.
+    ///
+    /// Either a jar file, or the cached jar of a plugin in the manifest is inspected.
+    #[command(after_long_help = COMPATIBILITY_CHECK_HELP)]
+    Inspect(cli::Inspect),
+    /// Export or import bundles of cached data, for use on machines without network access.
+    Cache(cli::Cache),
+    /// Check that every plugin in the manifest exists in its API, has versions, can be downloaded,
+    /// and that pinned versions resolve. All problems are reported at once.
+    #[command(after_long_help = COMPATIBILITY_CHECK_HELP)]
+    Check(cli::Check),
+    /// Show the load order and dependency tree of the plugins in the manifest, from the plugin descriptors in their jars.
+    ///
+    /// Hard dependencies that are missing from the manifest are reported, and can be added with `--add-missing`.
+    Tree(cli::Tree),
+    /// Show which plugins in the manifest need a plugin, directly or through other plugins.
+    Why(cli::Why),
+    /// List the plugins in a plugins directory that are older than the versions that the manifest resolves to,
+    /// with their latest versions. Breaking updates (like `1.9` to `2.0`) are highlighted.
+    ///
+    /// Exits with an error if any plugin is outdated or missing, so it can be used to check servers in scripts.
+    /// Plugins that are installed in a newer version than the wanted version are listed, but aren't an error.
+    #[command(after_long_help = COMPATIBILITY_CHECK_HELP)]
+    Outdated(cli::Outdated),
+    /// Update the versions that plugins are pinned to in the manifest, within their version specs.
+    ///
+    /// Plugins are updated as far as their `update_policy` ('manual', 'patch', 'minor' or 'any') allows,
+    /// or after asking with `--interactive`. Plugins that aren't pinned to a version name or identifier are skipped.
+    #[command(after_long_help = COMPATIBILITY_CHECK_HELP)]
+    Update(cli::Update),
+    /// Search for plugins by name, to find the IDs that plugins are added to the manifest with.
+    Search(cli::Search),
+    /// Create a new manifest, optionally from the jars in an existing plugins directory.
+    ///
+    /// With `--from`, every jar is matched with a plugin in the plugin sources by its `plugin.yml` or `paper-plugin.yml`.
+    /// Jars that could not be matched are reported, so that they can be added manually.
+    Init(cli::Init),
+    /// Add a plugin to the manifest. The plugin is looked up in its API before it's added.
+    Add(cli::Add),
+    /// Remove a plugin from the manifest.
+    Remove(cli::Remove),
+    /// Pin a plugin in the manifest to a version, or unpin it.
+    ///
+    /// Without a version, the plugin is pinned to the identifier of its latest version.
+    #[command(after_long_help = COMPATIBILITY_CHECK_HELP)]
+    Pin(cli::Pin),
+    /// Generate a shell completion script.
+    ///
+    /// For example, add `source <(pluginstall completions bash)` to your `.bashrc`.
+    Completions(cli::Completions),
+    /// Generate man pages.
+    Man(cli::Man),
 }
 
 macro_rules! run_subcommand {
@@ -117,9 +385,46 @@ impl Commands {
         run_subcommand!(self, Versions, session, manifest);
         run_subcommand!(self, Info, session, manifest);
         run_subcommand!(self, Download, session, manifest);
+        run_subcommand!(self, Inspect, session, manifest);
+        run_subcommand!(self, Cache, session, manifest);
+        run_subcommand!(self, Check, session, manifest);
+        run_subcommand!(self, Tree, session, manifest);
+        run_subcommand!(self, Why, session, manifest);
+        run_subcommand!(self, Outdated, session, manifest);
+        run_subcommand!(self, Update, session, manifest);
+        run_subcommand!(self, Search, session, manifest);
+        run_subcommand!(self, Init, session, manifest);
+        run_subcommand!(self, Add, session, manifest);
+        run_subcommand!(self, Remove, session, manifest);
+        run_subcommand!(self, Pin, session, manifest);
 
         Ok(())
     }
+
+    /// Run the subcommand if it doesn't need a manifest or an [`IoSession`] (like generating completions).
+    /// Returns [`None`] if the subcommand needs them, in which case it should be ran with [`Commands::run`].
+    #[inline]
+    pub fn run_standalone(&self, cli_output: &CliOutput) -> Option<miette::Result<()>> {
+        match self {
+            Self::Completions(cmd) => Some(cmd.run()),
+            Self::Man(cmd) => Some(cmd.run()),
+            Self::Inspect(cmd) => cmd.jar_path().map(|path| cmd.run_jar(path, cli_output)),
+            _ => None,
+        }
+    }
+
+    /// The plugin that the subcommand is for, if any.
+    #[inline]
+    pub fn plugin_spec(&self) -> Option<&PluginSpecArgs> {
+        match self {
+            Self::Versions(cmd) => Some(&cmd.plugin),
+            Self::Info(cmd) => Some(&cmd.plugin),
+            Self::Download(cmd) => Some(&cmd.plugin),
+            Self::Remove(cmd) => Some(&cmd.plugin),
+            Self::Pin(cmd) => Some(&cmd.plugin),
+            _ => None,
+        }
+    }
 }
 
 /// Trait implemented by subcommands.
@@ -127,54 +432,205 @@ pub trait Subcommand {
     async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()>;
 }
 
+/// The path to the manifest file: the given path, or the manifest from the config, or the default manifest file.
+#[inline]
+pub fn manifest_path<'a>(manifest: Option<&'a Path>, config: &'a Config) -> &'a Path {
+    manifest
+        .or(config.manifest.as_deref())
+        .unwrap_or(DEFAULT_MANIFEST_FILE_NAME.as_ref())
+}
+
 impl Cli {
     /// Parse the manifest file specified by the options passed to this CLI.
-    /// If no manifest file is specified, this will parse the default manifest file.
+    /// If no manifest file is specified, this will parse the manifest file from the config, or the default manifest file.
+    ///
+    /// For the 'init' subcommand the manifest doesn't exist yet, so a new empty manifest is returned instead.
+    #[inline]
+    pub async fn manifest(&self, config: &Config) -> ManifestResult<Manifest> {
+        let path = manifest_path(self.manifest.as_deref(), config);
+
+        match &self.command {
+            Commands::Init(init) => init.new_manifest(path),
+            _ => Manifest::parse_from_file(path).await,
+        }
+    }
+
+    /// Create a [`CliOutput`] object using the output options provided to the CLI, falling back to the config.
+    #[must_use]
+    #[inline]
+    pub fn cli_output(&self, config: &Config) -> CliOutput {
+        let format = if self.output_ctrl.json {
+            OutputFormat::Json
+        } else {
+            self.output_ctrl
+                .format
+                .or(config.format)
+                .unwrap_or_default()
+        };
+
+        let table_style = self
+            .output_ctrl
+            .table_style
+            .or(config.table_style)
+            .unwrap_or_else(|| {
+                if std::io::stdout().is_terminal() {
+                    TableStyle::Ascii
+                } else {
+                    TableStyle::Plain
+                }
+            });
+
+        CliOutput::new(format, !self.output_ctrl.no_newline)
+            .with_table_style(table_style)
+            .with_color(self.log_ctrl.color)
+    }
+
+    /// The log level, from the level in the config (or the default level) adjusted by the verbosity options provided to the CLI.
+    #[must_use]
     #[inline]
-    pub async fn manifest(&self) -> ManifestResult<Manifest> {
-        Manifest::parse_from_file(&self.manifest).await
+    pub fn log_level(&self, config: &Config) -> LevelFilter {
+        let level = config.log_level.map_or(LOG_LEVEL, Into::into);
+        let steps = i64::from(self.log_ctrl.verbose) - i64::from(self.log_ctrl.quiet);
+
+        adjust_log_level(level, steps)
     }
 
-    /// Create a [`CliOutput`] object using the output options provided to the CLI.
+    /// Compile the output template provided to the CLI (either inline or in a file), if any.
+    #[inline]
+    pub async fn output_template(&self) -> miette::Result<Option<OutputTemplate>> {
+        let template = match (&self.output_ctrl.template, &self.output_ctrl.template_file) {
+            (Some(template), _) => template.clone(),
+            (None, Some(path)) => tokio::fs::read_to_string(path)
+                .await
+                .into_diagnostic()
+                .wrap_err_with(|| format!("Error reading template file '{}'", path.display()))?,
+            (None, None) => return Ok(None),
+        };
+
+        Ok(Some(OutputTemplate::new(template)?))
+    }
+
+    /// Create [`NetworkSettings`] from the network options provided to the CLI, falling back to the config.
     #[must_use]
     #[inline]
-    pub fn cli_output(&self) -> CliOutput {
-        CliOutput::new(self.output_ctrl.json, !self.output_ctrl.no_newline)
+    pub fn network_settings(&self, config: &Config) -> NetworkSettings {
+        let args = &self.network_ctrl;
+        let network = &config.network;
+        let mut settings = NetworkSettings {
+            timeout: args.timeout.or(network.timeout).map(Duration::from_secs),
+            connect_timeout: network.connect_timeout.map(Duration::from_secs),
+            credentials: config.credentials.clone(),
+            ..NetworkSettings::default()
+        };
+
+        if let Some(concurrency) = args.concurrency.or(network.concurrency) {
+            settings.concurrency = concurrency;
+        }
+
+        if let Some(user_agent) = &network.user_agent {
+            settings.user_agent = user_agent.clone();
+        }
+
+        if let Some(mirror) = &config.mirrors.spiget {
+            let mut mirror = mirror.clone();
+            // endpoints are joined onto the base URL, which would replace the last path segment without the trailing slash
+            if !mirror.path().ends_with('/') {
+                mirror.set_path(&format!("{}/", mirror.path()));
+            }
+
+            settings.spiget_base_url = mirror;
+        }
+
+        settings
+    }
+
+    /// Create [`CacheSettings`] from the cache control options provided to the CLI.
+    #[must_use]
+    #[inline]
+    pub fn cache_settings(&self) -> CacheSettings {
+        let args = &self.cache_ctrl;
+
+        let policy = if args.offline {
+            CachePolicy::Offline
+        } else if args.no_cache {
+            CachePolicy::NoCache
+        } else if args.refresh {
+            CachePolicy::Refresh
+        } else {
+            CachePolicy::Default
+        };
+
+        CacheSettings {
+            policy,
+            // the value parser of the argument keeps this in range of a TimeDelta
+            api_ttl: args.cache_ttl.map(|secs| TimeDelta::seconds(secs as _)),
+            check_jars: args.check_jars,
+        }
     }
 
     /// Create a [`DownloadCache`] object with the options provided to the CLI and the name of the manifest used.
     ///
     /// The provided `manifest_name` should come from the deserialized manifest file.
     ///
-    /// If no special cache path is provided then the default cache in the user's home directory will be used.
+    /// If no special cache path is provided then a subdirectory of the cache directory from the config
+    /// (or the user's cache directory) will be used.
     #[must_use]
     #[inline]
-    pub async fn download_cache(&self, manifest_name: &str) -> CacheResult<DownloadCache> {
-        let path = match &self.cache {
+    pub async fn download_cache(
+        &self,
+        config: &Config,
+        manifest_name: &str,
+    ) -> CacheResult<DownloadCache> {
+        // if the user has specified a cache path, we just trust them that it exists and error later
+        if let Some(cache) = &self.cache {
+            return DownloadCache::new(cache).await;
+        }
+
+        let cache_dir = match &config.cache_dir {
+            Some(cache_dir) => cache_dir.clone(),
             None => {
-                let path = default_cache_directory_path()?.join(manifest_name);
-                Cow::Owned(path)
+                let cache_dir = default_cache_directory_path()?;
+
+                if let Some(legacy) = legacy_cache_directory_path().filter(|path| path.is_dir()) {
+                    migrate_legacy_cache_directory(&legacy, &cache_dir).await;
+                }
+
+                cache_dir
             }
-            // if the user has specified a cache path, we just trust them that it exists and error later
-            Some(cache) => Cow::Borrowed(cache),
         };
 
-        DownloadCache::new(&path).await
+        DownloadCache::new(&cache_dir.join(manifest_name)).await
     }
 }
 
 impl VersionSpecArgs {
     /// Get the version spec provided to the command.
     ///
-    /// Will return [`VersionSpec::Latest`] if neither were specified.
-    /// Will panic if both the version name and version identifier are specified.
+    /// Will return [`VersionSpec::Latest`] if none were specified.
+    /// Will panic if more than one of the version name, version identifier and version spec are specified.
     #[inline]
     pub fn get(&self) -> VersionSpec {
-        match (self.version_ident.as_ref(), self.version_name.as_ref()) {
-            (Some(version_ident), None) => VersionSpec::Identifier(version_ident.clone()),
-            (None, Some(version_name)) => VersionSpec::Name(version_name.clone()),
-            (None, None) => VersionSpec::Latest,
-            _ => panic!("You cannot specify both version identifier and version name."),
+        match (
+            self.version_ident.as_ref(),
+            self.version_name.as_ref(),
+            self.spec.as_ref(),
+        ) {
+            (Some(version_ident), None, None) => VersionSpec::Identifier(version_ident.clone()),
+            (None, Some(version_name), None) => VersionSpec::Name(version_name.clone()),
+            (None, None, Some(spec)) => spec.clone(),
+            (None, None, None) => VersionSpec::Latest(ReleaseChannel::Release),
+            _ => panic!("You cannot specify more than one of version identifier, version name and version spec."),
+        }
+    }
+
+    /// Get the version spec provided to the command, or the given version spec if none were specified
+    /// (like the version that a plugin is pinned to in the manifest).
+    #[inline]
+    pub fn get_or(&self, version_spec: VersionSpec) -> VersionSpec {
+        if self.version_ident.is_none() && self.version_name.is_none() && self.spec.is_none() {
+            return version_spec;
         }
+
+        self.get()
     }
 }
@@ -0,0 +1,351 @@
+//! The 'outdated' subcommand for finding installed plugins that are older than the versions that the manifest resolves to.
+
+use std::cmp::Ordering;
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use miette::{Context, IntoDiagnostic};
+use owo_colors::{AnsiColors, OwoColorize};
+use serde_json::Value;
+
+use crate::{
+    adapter::{spiget::SpigetPlugin, PluginVersion, VersionSpec},
+    caching::CacheError,
+    cli::Subcommand,
+    jar::{self, PluginDescriptor},
+    manifest::{suggest_plugin_name, Manifest, PluginDownloadSpec},
+    output::DataDisplay,
+    session::IoSession,
+    util::{CliTable, CliTableRow, TableStyle},
+    version::{compare_version_names, is_breaking_update, normalize_version_name},
+};
+
+/// The 'outdated' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Outdated {
+    /// The plugins directory of the server, with the installed jars of the plugins in the manifest.
+    #[arg(
+        short = 'd',
+        long,
+        value_name = "PLUGINS_DIR",
+        default_value = "plugins"
+    )]
+    pub plugins_dir: PathBuf,
+}
+
+/// How the installed version of a plugin compares to the version that the manifest resolves to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum InstalledStatus {
+    /// No jar of the plugin was found.
+    Missing,
+    /// The installed version is older than the wanted version.
+    Outdated,
+    /// The installed version is the wanted version.
+    UpToDate,
+    /// The installed version is newer than the wanted version, like a manually installed hotfix.
+    Newer,
+}
+
+impl InstalledStatus {
+    /// Whether the plugin has to be installed or updated to get the wanted version.
+    #[inline]
+    pub fn needs_update(self) -> bool {
+        matches!(self, Self::Missing | Self::Outdated)
+    }
+}
+
+/// A plugin in the manifest, with its installed version and the versions that it could be updated to.
+#[derive(Debug, serde::Serialize)]
+pub struct OutdatedPlugin {
+    pub plugin_name: String,
+    /// The path of the installed jar, or [`None`] if no jar of the plugin was found.
+    pub file: Option<PathBuf>,
+    /// The version in the plugin descriptor of the installed jar.
+    pub current: Option<String>,
+    /// The name of the version that the plugin resolves to in the manifest (its pinned version, or its latest version).
+    pub wanted: String,
+    pub wanted_identifier: String,
+    /// The name of the latest version of the plugin, ignoring the version that the plugin is pinned to.
+    pub latest: String,
+    pub latest_identifier: String,
+    pub status: InstalledStatus,
+    /// Whether updating from the installed version to the wanted version is a breaking change, like `1.9` to `2.0`.
+    pub breaking: bool,
+    /// Whether updating from the wanted version to the latest version would be a breaking change.
+    pub latest_breaking: bool,
+}
+
+/// The output of the 'outdated' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct OutdatedOutput {
+    /// The plugins that aren't installed in the wanted version, or that have a newer latest version than the wanted version.
+    /// Sorted by name.
+    pub plugins: Vec<OutdatedPlugin>,
+    /// The plugins in the manifest that were skipped, because their plugin source isn't supported yet.
+    pub unsupported: Vec<String>,
+    /// The style of the table in human-readable mode.
+    #[serde(skip)]
+    pub table_style: TableStyle,
+    /// The maximum width of the table in human-readable mode.
+    #[serde(skip)]
+    pub max_width: Option<usize>,
+}
+
+/// Error for when installed plugins are outdated, so that scripts can check for outdated plugins with the exit code.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("{0} plugins are outdated")]
+#[diagnostic(help(
+    "Download the wanted versions with the 'download' subcommand, or pin the plugins to the installed versions with the 'pin' subcommand."
+))]
+pub struct OutdatedPluginsError(pub usize);
+
+/// Error for when the plugins directory doesn't exist, like when the command isn't run in the directory of the server.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("The plugins directory '{}' does not exist", .0.display())]
+#[diagnostic(help(
+    "Run the command in the directory of the server, or pass its plugins directory with '--plugins-dir'."
+))]
+pub struct MissingPluginsDirError(pub PathBuf);
+
+impl DataDisplay for OutdatedOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        for plugin_name in &self.unsupported {
+            let skipped =
+                format!("Skipped '{plugin_name}', its plugin source is not supported yet");
+            writeln!(w, "{}", skipped.dimmed())?;
+        }
+
+        if self.plugins.is_empty() {
+            return write!(w, "{}", "All plugins are up to date".green());
+        }
+
+        let mut headers = CliTableRow::new(&[
+            "Plugin".to_string(),
+            "Current".to_string(),
+            "Wanted".to_string(),
+            "Latest".to_string(),
+        ]);
+        headers.color_all(AnsiColors::Green);
+
+        let mut table = CliTable::new(headers)
+            .with_style(self.table_style)
+            .with_max_width(self.max_width);
+
+        for plugin in &self.plugins {
+            let mut row = CliTableRow::new(&[
+                plugin.plugin_name.clone(),
+                plugin
+                    .current
+                    .clone()
+                    .unwrap_or_else(|| "(missing)".to_string()),
+                plugin.wanted.clone(),
+                plugin.latest.clone(),
+            ]);
+
+            row[0].color = AnsiColors::Green;
+            match plugin.status {
+                InstalledStatus::Missing | InstalledStatus::Outdated => {
+                    row[1].color = AnsiColors::Red;
+                }
+                InstalledStatus::Newer => row[1].color = AnsiColors::Cyan,
+                InstalledStatus::UpToDate => {}
+            }
+            if plugin.latest_breaking {
+                row[3].color = AnsiColors::Yellow;
+            }
+            // updating to the wanted version may break the server, so the whole row stands out
+            if plugin.breaking {
+                row.bg_color = AnsiColors::Red;
+            }
+
+            table.add(row);
+        }
+
+        write!(w, "{table}")
+    }
+
+    /// One record per plugin.
+    fn records(&self) -> Result<Vec<Value>, serde_json::Error> {
+        self.plugins.iter().map(serde_json::to_value).collect()
+    }
+}
+
+impl Subcommand for Outdated {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        if !self.plugins_dir.is_dir() {
+            return Err(MissingPluginsDirError(self.plugins_dir.clone()).into());
+        }
+
+        let installed = installed_plugins(&self.plugins_dir).await?;
+
+        let mut plugin_names = manifest.plugin.keys().collect::<Vec<_>>();
+        plugin_names.sort();
+
+        let plugins = futures::future::try_join_all(
+            plugin_names
+                .iter()
+                .map(|plugin_name| outdated_plugin(session, manifest, plugin_name, &installed)),
+        )
+        .await?;
+
+        let mut outdated_plugins = Vec::new();
+        let mut unsupported = Vec::new();
+        for (plugin_name, plugin) in plugin_names.into_iter().zip(plugins) {
+            match plugin {
+                Some(plugin) => outdated_plugins.push(plugin),
+                None => unsupported.push(plugin_name.clone()),
+            }
+        }
+
+        let out = OutdatedOutput {
+            plugins: outdated_plugins
+                .into_iter()
+                .filter(|plugin| {
+                    plugin.status != InstalledStatus::UpToDate
+                        || plugin.latest_identifier != plugin.wanted_identifier
+                })
+                .collect(),
+            unsupported,
+            table_style: session.cli_output().table_style(),
+            max_width: session.cli_output().terminal_width(),
+        };
+        session.cli_output().display(&out).into_diagnostic()?;
+
+        // installed versions that are newer than the wanted versions are reported, but aren't errors
+        let outdated = out
+            .plugins
+            .iter()
+            .filter(|plugin| plugin.status.needs_update())
+            .count();
+        if outdated > 0 {
+            return Err(OutdatedPluginsError(outdated).into());
+        }
+
+        Ok(())
+    }
+}
+
+/// The plugin descriptors of the jars in the plugins directory. Jars without a readable plugin descriptor are skipped.
+#[inline]
+async fn installed_plugins(plugins_dir: &Path) -> miette::Result<Vec<(PathBuf, PluginDescriptor)>> {
+    let jars = jar::plugin_jars(plugins_dir)
+        .await
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            format!(
+                "Error reading plugins directory '{}'",
+                plugins_dir.display()
+            )
+        })?;
+
+    let mut installed = Vec::with_capacity(jars.len());
+    for file in jars {
+        match jar::plugin_descriptor(&file).await {
+            Ok(descriptor) => installed.push((file, descriptor)),
+            Err(error) => log::debug!("Skipping jar '{}': {error:?}", file.display()),
+        }
+    }
+
+    Ok(installed)
+}
+
+/// Resolve the wanted and latest versions of a plugin in the manifest, and compare them with its installed jar.
+///
+/// The installed jar is the jar with a plugin descriptor name that matches the name of the plugin in the manifest,
+/// or the descriptor name of the wanted version (if its jar is in the download cache).
+/// Returns [`None`] if the plugin source isn't supported yet.
+///
+/// The version in the plugin descriptor is compared with the name of the version in the plugin source, and the two don't
+/// always agree: a jar with version "2.3.1" is reported as outdated if its version is named "2.3.1 - Hotfix" on Spigot.
+#[inline]
+async fn outdated_plugin(
+    session: &IoSession,
+    manifest: &Manifest,
+    plugin_name: &str,
+    installed: &[(PathBuf, PluginDescriptor)],
+) -> miette::Result<Option<OutdatedPlugin>> {
+    // plugins from sources that aren't supported yet are reported as skipped, instead of failing for every plugin
+    let PluginDownloadSpec::Spiget(spiget) = manifest.plugin(plugin_name)? else {
+        return Ok(None);
+    };
+
+    let plugin = SpigetPlugin::from_manifest(session, manifest, plugin_name, spiget)
+        .await
+        .wrap_err_with(|| format!("Error getting plugin '{plugin_name}'"))?;
+
+    let target = manifest.server_target();
+    let wanted_spec = spiget.pinned_version();
+    let Some(wanted) = plugin
+        .resolve_version(plugin_name, &wanted_spec, &target)
+        .await?
+    else {
+        return Err(plugin.version_not_found(manifest, plugin_name, &wanted_spec));
+    };
+
+    let latest_spec = VersionSpec::Latest(spiget.min_channel.unwrap_or_default());
+    let latest = match plugin
+        .resolve_version(plugin_name, &latest_spec, &target)
+        .await?
+    {
+        Some(latest) => latest,
+        None => wanted.clone(),
+    };
+
+    // the installed jar can still be found by the name in the manifest if the cached jar can't be read
+    let descriptor_name = match session
+        .download_cache()
+        .jar_metadata(plugin_name, &wanted.version_identifier())
+        .await
+    {
+        Ok(metadata) => metadata.map(|metadata| metadata.descriptor.name),
+        Err(CacheError::NotAJar(reason)) => {
+            log::debug!("The cached file of plugin '{plugin_name}' is not a plugin jar: {reason}");
+            None
+        }
+        Err(error) => {
+            log::warn!(
+                "Could not read the plugin descriptor of the cached jar of plugin '{plugin_name}': {error:?}"
+            );
+            None
+        }
+    };
+    let (file, current) = installed
+        .iter()
+        .find(|(_, descriptor)| {
+            suggest_plugin_name(&descriptor.name) == plugin_name
+                || descriptor_name
+                    .as_ref()
+                    .is_some_and(|name| descriptor.name.eq_ignore_ascii_case(name))
+        })
+        .map(|(file, descriptor)| (file.clone(), descriptor.version.clone()))
+        .unzip();
+
+    let wanted_name = wanted.version_name();
+    let status = match &current {
+        None => InstalledStatus::Missing,
+        Some(current) => match compare_version_names(
+            normalize_version_name(current),
+            normalize_version_name(&wanted_name),
+        ) {
+            Ordering::Less => InstalledStatus::Outdated,
+            Ordering::Equal => InstalledStatus::UpToDate,
+            Ordering::Greater => InstalledStatus::Newer,
+        },
+    };
+
+    Ok(Some(OutdatedPlugin {
+        plugin_name: plugin_name.to_string(),
+        file,
+        breaking: current
+            .as_ref()
+            .is_some_and(|current| is_breaking_update(current, &wanted_name)),
+        latest_breaking: is_breaking_update(&wanted_name, &latest.version_name()),
+        current,
+        wanted: wanted_name.into_owned(),
+        wanted_identifier: wanted.version_identifier().into_owned(),
+        latest: latest.version_name().into_owned(),
+        latest_identifier: latest.version_identifier().into_owned(),
+        status,
+    }))
+}
@@ -0,0 +1,144 @@
+//! The 'pin' subcommand for pinning a plugin in the manifest to a version.
+
+use std::path::PathBuf;
+
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+use miette::{Context, IntoDiagnostic};
+use owo_colors::OwoColorize;
+
+use crate::{
+    adapter::{
+        spiget::SpigetPlugin, PluginApiType, PluginVersion, UnsupportedApiError, VersionSpec,
+    },
+    cli::Subcommand,
+    manifest::{Manifest, PluginDownloadSpec},
+    output::DataDisplay,
+    session::IoSession,
+};
+
+use super::{complete_version_ident, complete_version_name, PluginSpecArgs};
+
+/// The 'pin' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Pin {
+    #[command(flatten)]
+    pub plugin: PluginSpecArgs,
+    #[command(flatten)]
+    pub pin: PinArgs,
+}
+
+/// Arguments for the version that a plugin is pinned to. If no argument is provided,
+/// the plugin is pinned to the identifier of its latest version.
+#[derive(Args, Debug, Clone)]
+#[group(required = false, multiple = false)]
+pub struct PinArgs {
+    /// Pin the plugin to the latest version with this name.
+    #[arg(long, value_name = "VERSION_NAME", add = ArgValueCompleter::new(complete_version_name))]
+    pub version: Option<String>,
+    /// Pin the plugin to the version with this identifier.
+    #[arg(long, value_name = "VERSION_IDENTIFIER", add = ArgValueCompleter::new(complete_version_ident))]
+    pub version_id: Option<String>,
+    /// Pin the plugin to a version spec (like `^2.3` or `before:2024-06-01`), so that it resolves to the most recent version
+    /// that matches the spec.
+    #[arg(long, value_name = "VERSION_SPEC")]
+    pub spec: Option<VersionSpec>,
+    /// Remove the pin of the plugin, so that the latest version is used again.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub unpin: bool,
+}
+
+/// The output of the 'pin' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct PinOutput {
+    pub plugin_name: String,
+    /// The name of the version that the plugin was pinned to, or [`None`] if the plugin was unpinned.
+    pub version_name: Option<String>,
+    /// The identifier of the version that the plugin was pinned to, or [`None`] if the plugin was unpinned.
+    pub version_identifier: Option<String>,
+    pub manifest_path: PathBuf,
+}
+
+impl DataDisplay for PinOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        match (&self.version_name, &self.version_identifier) {
+            (Some(name), Some(ident)) => write!(
+                w,
+                "Pinned plugin '{0}' to version '{1}' (ID {2}) in '{3}'",
+                self.plugin_name.bright_green(),
+                name.bright_green(),
+                ident.bright_green(),
+                self.manifest_path.display().bright_green(),
+            ),
+            _ => write!(
+                w,
+                "Unpinned plugin '{0}' in '{1}'",
+                self.plugin_name.bright_green(),
+                self.manifest_path.display().bright_green(),
+            ),
+        }
+    }
+}
+
+impl Subcommand for Pin {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let plugin_name = &self.plugin.plugin_name;
+        let plugin_manifest = manifest.plugin(plugin_name)?;
+
+        let mut document = manifest.edit()?;
+        let mut out = PinOutput {
+            plugin_name: plugin_name.clone(),
+            version_name: None,
+            version_identifier: None,
+            manifest_path: document.path().to_path_buf(),
+        };
+
+        if self.pin.unpin {
+            document.set_pin(plugin_name, None);
+        } else {
+            let PluginDownloadSpec::Spiget(spiget) = plugin_manifest else {
+                return Err(UnsupportedApiError(PluginApiType::Hangar).into());
+            };
+
+            let version_spec = match (&self.pin.version, &self.pin.version_id, &self.pin.spec) {
+                (Some(name), _, _) => VersionSpec::Name(name.clone()),
+                (None, Some(ident), _) => VersionSpec::Identifier(ident.clone()),
+                (None, None, Some(spec)) => spec.clone(),
+                (None, None, None) => VersionSpec::Latest(spiget.min_channel.unwrap_or_default()),
+            };
+
+            // the version is validated before the plugin is pinned to it
+            let plugin = SpigetPlugin::from_manifest(session, manifest, plugin_name, spiget)
+                .await
+                .wrap_err_with(|| format!("Error getting plugin '{plugin_name}'"))?;
+            let Some(version) = plugin
+                .resolve_version(plugin_name, &version_spec, &manifest.server_target())
+                .await?
+            else {
+                return Err(plugin.version_not_found(manifest, plugin_name, &version_spec));
+            };
+
+            // pinning by name or spec keeps what the user chose, otherwise the exact version is pinned
+            let pin = match (&self.pin.version, &self.pin.spec) {
+                (Some(name), _) => ("version", name.as_str().into()),
+                (None, Some(spec)) => ("version_spec", spec.to_string().into()),
+                (None, None) => {
+                    let version_id =
+                        i64::try_from(u64::from(version.version.id)).into_diagnostic()?;
+                    ("version_id", version_id.into())
+                }
+            };
+            document.set_pin(plugin_name, Some(pin));
+
+            out.version_name = Some(version.version_name().into_owned());
+            out.version_identifier = Some(version.version_identifier().into_owned());
+        }
+
+        document
+            .save()
+            .await
+            .wrap_err_with(|| format!("Error writing manifest '{}'", document.path().display()))?;
+
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
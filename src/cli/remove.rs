@@ -0,0 +1,64 @@
+//! The 'remove' subcommand for removing a plugin from the manifest.
+
+use std::path::PathBuf;
+
+use clap::Args;
+use miette::{Context, IntoDiagnostic};
+use owo_colors::OwoColorize;
+
+use crate::{
+    cli::{PluginNotFoundError, Subcommand},
+    manifest::Manifest,
+    output::DataDisplay,
+    session::IoSession,
+};
+
+use super::PluginSpecArgs;
+
+/// The 'remove' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Remove {
+    #[command(flatten)]
+    pub plugin: PluginSpecArgs,
+}
+
+/// The output of the 'remove' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct RemoveOutput {
+    pub plugin_name: String,
+    pub manifest_path: PathBuf,
+}
+
+impl DataDisplay for RemoveOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        write!(
+            w,
+            "Removed plugin '{0}' from '{1}'",
+            self.plugin_name.bright_green(),
+            self.manifest_path.display().bright_green(),
+        )
+    }
+}
+
+impl Subcommand for Remove {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let plugin_name = &self.plugin.plugin_name;
+
+        let mut document = manifest.edit()?;
+        if !document.remove_plugin(plugin_name) {
+            return Err(PluginNotFoundError::new(plugin_name, manifest).into());
+        }
+
+        document
+            .save()
+            .await
+            .wrap_err_with(|| format!("Error writing manifest '{}'", document.path().display()))?;
+
+        let out = RemoveOutput {
+            plugin_name: plugin_name.clone(),
+            manifest_path: document.path().to_path_buf(),
+        };
+
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
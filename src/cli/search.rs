@@ -0,0 +1,244 @@
+//! The 'search' subcommand for searching for plugins in the APIs that plugins are downloaded from.
+
+use chrono::{DateTime, Utc};
+use clap::Args;
+use miette::IntoDiagnostic;
+use owo_colors::{AnsiColors, OwoColorize};
+use rq::Url;
+use serde_json::Value;
+
+use crate::{
+    adapter::{
+        spiget::{self, ManifestSpigetPlugin, SpigetSearchResultJson},
+        PluginApiType, UnsupportedApiError,
+    },
+    cli::Subcommand,
+    manifest::{plugin_snippet, suggest_plugin_name, Manifest, ManifestError, PluginDownloadSpec},
+    output::DataDisplay,
+    session::IoSession,
+    util::{Alignment, CliTable, CliTableRow, TableStyle},
+};
+
+/// The 'search' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Search {
+    /// The text to search for in the names of plugins.
+    #[arg(value_name = "QUERY")]
+    pub query: String,
+    /// The API to search in.
+    #[arg(long, value_name = "SOURCE", default_value = "spiget")]
+    pub source: PluginApiType,
+    /// The maximum number of plugins to list. The most downloaded plugins are listed first.
+    #[arg(short = 'L', long, value_name = "LIMIT", default_value = "10")]
+    pub limit: u64,
+    /// Write a manifest entry for every plugin that was found instead of a table, ready to be pasted into the manifest.
+    #[arg(short = 's', long, action = clap::ArgAction::SetTrue)]
+    pub snippet: bool,
+    /// The strftime/strptime format string for the date that the plugins were last updated.
+    #[arg(
+        short = 'F',
+        long,
+        value_name = "TIME_FORMAT",
+        default_value = "%Y-%m-%d"
+    )]
+    pub time_format: String,
+}
+
+/// A plugin that was found by the 'search' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct SearchResult {
+    pub plugin_type: PluginApiType,
+    /// The ID of the plugin in its API, like the resource ID of a Spigot resource.
+    pub id: String,
+    pub name: String,
+    /// The short description of the plugin.
+    pub tag: String,
+    /// The ID of the author in the API.
+    pub author_id: u64,
+    /// The name of the author. Only fetched for the table of human readable output, and [`None`] if it couldn't be fetched.
+    pub author: Option<String>,
+    pub downloads: u64,
+    pub rating: f64,
+    pub rating_count: u64,
+    pub update_date: DateTime<Utc>,
+    pub tested_versions: Vec<String>,
+    /// Whether the plugin can be downloaded by pluginstall.
+    pub downloadable: bool,
+    pub page_url: Url,
+    /// The suggested name of the plugin in the manifest.
+    pub manifest_name: String,
+    /// The manifest entry for the plugin, if it was requested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub manifest_snippet: Option<String>,
+    /// The entry that adds the plugin to the manifest.
+    #[serde(skip)]
+    pub plugin: PluginDownloadSpec,
+}
+
+impl SearchResult {
+    /// Create a search result for a resource found with the Spiget API.
+    #[inline]
+    fn spiget(resource: SpigetSearchResultJson) -> Self {
+        Self {
+            plugin_type: PluginApiType::Spiget,
+            id: resource.id.to_string(),
+            manifest_name: suggest_plugin_name(&resource.name),
+            page_url: resource.id.plugin_page(),
+            name: resource.name,
+            tag: resource.tag,
+            author_id: resource.author.id,
+            author: resource.author.name,
+            downloads: resource.downloads,
+            rating: resource.rating.average,
+            rating_count: resource.rating.count,
+            update_date: resource.update_date,
+            tested_versions: resource.tested_versions,
+            downloadable: !resource.premium && !resource.external,
+            manifest_snippet: None,
+            plugin: PluginDownloadSpec::Spiget(ManifestSpigetPlugin::new(resource.id)),
+        }
+    }
+}
+
+/// The output of the 'search' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct SearchOutput {
+    #[serde(skip)]
+    pub cfg: SearchOutputCfg,
+    pub query: String,
+    pub results: Vec<SearchResult>,
+}
+
+/// Options for how search results should be formatted to the terminal.
+#[derive(Debug)]
+pub struct SearchOutputCfg {
+    /// The datetime format
+    pub strftime_format: String,
+    /// Whether manifest snippets should be written instead of a table
+    pub write_snippets: bool,
+    /// The style of the results table
+    pub table_style: TableStyle,
+    /// The maximum width of the results table
+    pub max_width: Option<usize>,
+}
+
+impl DataDisplay for SearchOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        if self.results.is_empty() {
+            return write!(w, "No plugins found for '{}'", self.query.bright_green());
+        }
+
+        if self.cfg.write_snippets {
+            let snippets = self
+                .results
+                .iter()
+                .filter_map(|result| result.manifest_snippet.as_deref())
+                .collect::<Vec<_>>();
+
+            return write!(w, "{}", snippets.join("\n").trim_end());
+        }
+
+        let mut headers = CliTableRow::new(&[
+            "ID".into(),
+            "Name".into(),
+            "Author".into(),
+            "Downloads".into(),
+            "Rating".into(),
+            "Last Update".into(),
+            "Tested Versions".into(),
+        ]);
+        headers.color_all(AnsiColors::Green);
+
+        let mut table = CliTable::new(headers)
+            .with_style(self.cfg.table_style)
+            .with_max_width(self.cfg.max_width);
+        table.align(0, Alignment::Right);
+        table.align(3, Alignment::Right);
+
+        for result in &self.results {
+            let rating = match result.rating_count {
+                0 => "---".to_string(),
+                count => format!("{:.1} ({count})", result.rating),
+            };
+
+            // listing every tested version takes up too much space, so only the range is shown
+            let tested_versions = match result.tested_versions.as_slice() {
+                [] => String::new(),
+                [version] => version.clone(),
+                [first, .., last] => format!("{first} - {last}"),
+            };
+
+            let mut row = CliTableRow::new(&[
+                result.id.clone(),
+                result.name.clone(),
+                result.author.clone().unwrap_or_default(),
+                result.downloads.to_string(),
+                rating,
+                result
+                    .update_date
+                    .format(&self.cfg.strftime_format)
+                    .to_string(),
+                tested_versions,
+            ]);
+            // plugins that can't be downloaded can't be added to the manifest either
+            row[1].color = if result.downloadable {
+                AnsiColors::Green
+            } else {
+                AnsiColors::Red
+            };
+
+            table.add(row);
+        }
+
+        write!(w, "{table}")
+    }
+
+    /// One record per plugin that was found.
+    fn records(&self) -> Result<Vec<Value>, serde_json::Error> {
+        self.results.iter().map(serde_json::to_value).collect()
+    }
+}
+
+impl Subcommand for Search {
+    async fn run(&self, session: &IoSession, _manifest: &Manifest) -> miette::Result<()> {
+        // every author name takes a request, so they are only fetched for the table
+        let author_names = session.cli_output().is_human_readable() && !self.snippet;
+
+        let mut results = match self.source {
+            PluginApiType::Spiget => {
+                let mut resources =
+                    spiget::search_resources(session, &self.query, self.limit).await?;
+                if author_names {
+                    spiget::fetch_author_names(session, &mut resources).await;
+                }
+
+                resources
+                    .into_iter()
+                    .map(SearchResult::spiget)
+                    .collect::<Vec<_>>()
+            }
+            source => return Err(UnsupportedApiError(source).into()),
+        };
+
+        if self.snippet {
+            for result in &mut results {
+                let snippet = plugin_snippet(&result.manifest_name, &result.plugin)
+                    .map_err(ManifestError::from)?;
+                result.manifest_snippet = Some(snippet);
+            }
+        }
+
+        let out = SearchOutput {
+            cfg: SearchOutputCfg {
+                strftime_format: self.time_format.clone(),
+                write_snippets: self.snippet,
+                table_style: session.cli_output().table_style(),
+                max_width: session.cli_output().terminal_width(),
+            },
+            query: self.query.clone(),
+            results,
+        };
+
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
@@ -0,0 +1,334 @@
+//! The 'tree' subcommand for showing the dependency graph and load order of the plugins in the manifest.
+
+use std::io::Write;
+
+use clap::Args;
+use miette::{Context, IntoDiagnostic};
+use owo_colors::OwoColorize;
+use rq::Url;
+
+use crate::{
+    adapter::{
+        spiget::{self, ManifestSpigetPlugin},
+        PluginApiType, UnsupportedApiError,
+    },
+    cli::Subcommand,
+    dependencies::{DependencyGraph, DependencyKind, GraphPlugin, MissingDependency},
+    manifest::{suggest_plugin_name, Manifest, PluginDownloadSpec},
+    output::DataDisplay,
+    session::IoSession,
+};
+
+/// The 'tree' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Tree {
+    /// Add the missing hard dependencies to the manifest, if a plugin with the same name can be found in the source.
+    #[arg(long, action = clap::ArgAction::SetTrue)]
+    pub add_missing: bool,
+    /// The API that missing dependencies are added from.
+    #[arg(
+        long,
+        value_name = "SOURCE",
+        default_value = "spiget",
+        requires = "add_missing"
+    )]
+    pub source: PluginApiType,
+}
+
+/// A plugin in a dependency tree.
+#[derive(Debug, serde::Serialize)]
+pub struct TreeNode {
+    /// The name of the plugin in its plugin descriptor, or in the plugin descriptor that refers to it if it's missing.
+    pub name: String,
+    /// The name of the plugin in the manifest, or [`None`] if the plugin is missing from the manifest.
+    pub plugin_name: Option<String>,
+    /// The version of the plugin, from its plugin descriptor.
+    pub version: Option<String>,
+    /// How the parent of this node relates to this plugin, or [`None`] for the roots of the tree.
+    pub kind: Option<DependencyKind>,
+    /// Whether the plugin was already shown earlier in the tree, in which case its children aren't repeated.
+    pub repeated: bool,
+    pub children: Vec<TreeNode>,
+}
+
+/// A missing dependency that was added to the manifest.
+#[derive(Debug, serde::Serialize)]
+pub struct AddedDependency {
+    /// The name of the dependency in the plugin descriptors that need it.
+    pub name: String,
+    /// The name that the dependency was added to the manifest with.
+    pub plugin_name: String,
+    pub plugin_type: PluginApiType,
+    pub page_url: Url,
+}
+
+/// The output of the 'tree' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct TreeOutput {
+    /// The names of the plugins in the manifest, in the order that the server loads them in.
+    pub load_order: Vec<String>,
+    /// The dependency trees, with the plugins that no other plugin depends on at the roots.
+    pub tree: Vec<TreeNode>,
+    /// The hard dependencies that aren't in the manifest (and weren't added to it).
+    pub missing: Vec<MissingDependency>,
+    /// The missing dependencies that were added to the manifest.
+    pub added: Vec<AddedDependency>,
+    /// The plugins in the manifest that were left out, because their plugin source isn't supported yet.
+    pub unsupported: Vec<String>,
+}
+
+/// Error for when plugins in the manifest need plugins that aren't in the manifest.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("{} plugins that are needed by plugins in the manifest are missing from the manifest", .0.len())]
+#[diagnostic(help(
+    "Add them with the 'add' subcommand, or use '--add-missing' to add them from the plugin sources."
+))]
+pub struct MissingDependenciesError(pub Vec<MissingDependency>);
+
+impl DataDisplay for TreeOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        // every line of the tree ends with a newline, which the output shouldn't end with
+        let mut out = Vec::new();
+
+        writeln!(out, "Load order:")?;
+        for (index, plugin_name) in self.load_order.iter().enumerate() {
+            writeln!(out, "{0:>4}. {1}", index + 1, plugin_name.bright_green())?;
+        }
+
+        writeln!(out, "Dependencies:")?;
+        for node in &self.tree {
+            write_tree(&mut out, node, "", false)?;
+        }
+
+        for plugin_name in &self.unsupported {
+            let skipped =
+                format!("Skipped '{plugin_name}', its plugin source is not supported yet");
+            writeln!(out, "{}", skipped.dimmed())?;
+        }
+
+        for added in &self.added {
+            writeln!(
+                out,
+                "Added missing dependency '{0}' as {1} plugin '{2}' ({3})",
+                added.name.bright_green(),
+                added.plugin_type,
+                added.plugin_name.bright_green(),
+                added.page_url.bright_green(),
+            )?;
+        }
+
+        for missing in &self.missing {
+            writeln!(
+                out,
+                "Missing dependency '{0}', needed by {1}",
+                missing.name.red(),
+                missing.required_by.join(", "),
+            )?;
+        }
+
+        write!(w, "{}", String::from_utf8_lossy(&out).trim_end())
+    }
+}
+
+impl Subcommand for Tree {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let graph = DependencyGraph::from_manifest(session, manifest).await?;
+        let load_order = graph
+            .load_order()?
+            .into_iter()
+            .map(|plugin| plugin.plugin_name.clone())
+            .collect();
+        let tree = dependency_tree(&graph);
+        let mut missing = graph.missing_dependencies();
+
+        let mut added = Vec::new();
+        if self.add_missing && !missing.is_empty() {
+            if self.source != PluginApiType::Spiget {
+                return Err(UnsupportedApiError(self.source).into());
+            }
+
+            let mut document = manifest.edit()?;
+            let mut unresolved = Vec::new();
+
+            for dependency in missing {
+                let resource = spiget::find_resource(session, &dependency.name, &[])
+                    .await
+                    .wrap_err_with(|| {
+                        format!("Error searching for dependency '{}'", dependency.name)
+                    })?
+                    .filter(|resource| !resource.premium && !resource.external);
+
+                let plugin_name = suggest_plugin_name(&dependency.name);
+                let Some(resource) =
+                    resource.filter(|_| !manifest.plugin.contains_key(&plugin_name))
+                else {
+                    unresolved.push(dependency);
+                    continue;
+                };
+
+                let plugin = PluginDownloadSpec::Spiget(ManifestSpigetPlugin::new(resource.id));
+                document.add_plugin(&plugin_name, &plugin)?;
+
+                added.push(AddedDependency {
+                    name: dependency.name,
+                    plugin_name,
+                    plugin_type: PluginApiType::Spiget,
+                    page_url: resource.id.plugin_page(),
+                });
+            }
+
+            if !added.is_empty() {
+                document.save().await.wrap_err_with(|| {
+                    format!("Error writing manifest '{}'", document.path().display())
+                })?;
+            }
+
+            missing = unresolved;
+        }
+
+        let out = TreeOutput {
+            load_order,
+            tree,
+            missing,
+            added,
+            unsupported: graph.unsupported().to_vec(),
+        };
+        session.cli_output().display(&out).into_diagnostic()?;
+
+        if !out.missing.is_empty() {
+            return Err(MissingDependenciesError(out.missing).into());
+        }
+
+        Ok(())
+    }
+}
+
+/// The dependency trees of the plugins in the graph. The plugins that no other plugin (soft) depends on are the roots,
+/// followed by any plugins that could only be reached through a cycle.
+#[inline]
+fn dependency_tree(graph: &DependencyGraph) -> Vec<TreeNode> {
+    let mut shown = Vec::new();
+    let mut roots = Vec::new();
+
+    let is_root = |plugin: &GraphPlugin| {
+        !graph
+            .dependents(&plugin.descriptor.name)
+            .any(|(kind, dependent)| {
+                kind != DependencyKind::LoadBefore && dependent.plugin_name != plugin.plugin_name
+            })
+    };
+
+    for plugin in graph.plugins().iter().filter(|plugin| is_root(plugin)) {
+        roots.push(dependency_node(graph, plugin, None, &mut shown));
+    }
+    for plugin in graph.plugins() {
+        if !shown.contains(&plugin.plugin_name.as_str()) {
+            roots.push(dependency_node(graph, plugin, None, &mut shown));
+        }
+    }
+
+    roots
+}
+
+/// The tree of a plugin and its (soft) dependencies. Missing hard dependencies are included as leaves.
+#[inline]
+fn dependency_node<'a>(
+    graph: &'a DependencyGraph,
+    plugin: &'a GraphPlugin,
+    kind: Option<DependencyKind>,
+    shown: &mut Vec<&'a str>,
+) -> TreeNode {
+    let repeated = shown.contains(&plugin.plugin_name.as_str());
+    let mut node = TreeNode {
+        name: plugin.descriptor.name.clone(),
+        plugin_name: Some(plugin.plugin_name.clone()),
+        version: Some(plugin.descriptor.version.clone()),
+        kind,
+        repeated,
+        children: vec![],
+    };
+    if repeated {
+        return node;
+    }
+    shown.push(&plugin.plugin_name);
+
+    for name in &plugin.descriptor.depend {
+        if graph.plugin(name).is_none() {
+            node.children.push(TreeNode {
+                name: name.clone(),
+                plugin_name: None,
+                version: None,
+                kind: Some(DependencyKind::Depend),
+                repeated: false,
+                children: vec![],
+            });
+        }
+    }
+    for (kind, dependency) in graph.dependencies(plugin) {
+        if kind != DependencyKind::LoadBefore && dependency.plugin_name != plugin.plugin_name {
+            node.children
+                .push(dependency_node(graph, dependency, Some(kind), shown));
+        }
+    }
+
+    node
+}
+
+/// Write a node of a tree and its children, with lines that connect the children to their parent.
+/// In a reversed tree the children are the plugins that relate to their parent, instead of the other way around.
+#[inline]
+pub(super) fn write_tree(
+    w: &mut impl std::io::Write,
+    node: &TreeNode,
+    prefix: &str,
+    reversed: bool,
+) -> Result<(), std::io::Error> {
+    write_node(w, node, reversed)?;
+
+    for (index, child) in node.children.iter().enumerate() {
+        let last = index + 1 == node.children.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+
+        write!(w, "{prefix}{branch}")?;
+        write_tree(w, child, &format!("{prefix}{indent}"), reversed)?;
+    }
+
+    Ok(())
+}
+
+/// Write a single node of a tree on its own line.
+#[inline]
+fn write_node(
+    w: &mut impl std::io::Write,
+    node: &TreeNode,
+    reversed: bool,
+) -> Result<(), std::io::Error> {
+    if let Some(kind) = node.kind.filter(|_| !reversed) {
+        write!(w, "{kind} ")?;
+    }
+
+    match (&node.plugin_name, &node.version) {
+        (Some(plugin_name), Some(version)) => write!(
+            w,
+            "'{0}' ({1} {2})",
+            plugin_name.bright_green(),
+            node.name,
+            version
+        )?,
+        (Some(plugin_name), None) => write!(w, "'{}'", plugin_name.bright_green())?,
+        (None, _) => write!(w, "'{0}' {1}", node.name.red(), "(missing)".red())?,
+    }
+
+    if let Some(kind) = node.kind.filter(|_| reversed) {
+        write!(w, " {kind} it")?;
+    }
+    if node.repeated {
+        write!(w, " (*)")?;
+    }
+
+    writeln!(w)
+}
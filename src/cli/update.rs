@@ -0,0 +1,381 @@
+//! The 'update' subcommand for updating the versions that plugins are pinned to in the manifest.
+
+use std::path::PathBuf;
+
+use chrono::{DateTime, Utc};
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+use miette::{Context, IntoDiagnostic};
+use owo_colors::OwoColorize;
+use serde_json::Value;
+
+use crate::{
+    adapter::{
+        spiget::{IncompatibleVersionsError, SpigetPlugin, SpigetResourceVersion},
+        PluginVersion, VersionSpec,
+    },
+    cli::Subcommand,
+    manifest::{Manifest, PluginDownloadSpec},
+    output::DataDisplay,
+    session::IoSession,
+    version::{compare_version_names, UpdatePolicy},
+};
+
+use super::complete_plugin_name;
+
+/// The 'update' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Update {
+    /// The names of the plugins to update. By default every plugin in the manifest is updated.
+    #[arg(value_name = "PLUGIN_NAME", add = ArgValueCompleter::new(complete_plugin_name))]
+    pub plugins: Vec<String>,
+    /// Ask before updating each plugin, instead of following the update policies of the plugins.
+    /// Plugins are offered the latest version within their version spec.
+    #[arg(short, long, action = clap::ArgAction::SetTrue)]
+    pub interactive: bool,
+}
+
+/// A version in the output of the 'update' subcommand.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct UpdateVersion {
+    pub version_name: String,
+    pub version_identifier: String,
+    pub publish_date: Option<DateTime<Utc>>,
+}
+
+impl UpdateVersion {
+    #[inline]
+    fn new(version: &impl PluginVersion) -> Self {
+        Self {
+            version_name: version.version_name().into_owned(),
+            version_identifier: version.version_identifier().into_owned(),
+            publish_date: version.publish_date(),
+        }
+    }
+}
+
+impl std::fmt::Display for UpdateVersion {
+    #[inline]
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "'{0}' (ID {1}",
+            self.version_name, self.version_identifier
+        )?;
+        if let Some(publish_date) = self.publish_date {
+            write!(f, ", {}", publish_date.format("%Y-%m-%d"))?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// What happened to a plugin when updating.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdateStatus {
+    /// The plugin was pinned to a newer version.
+    Updated,
+    /// The plugin is already pinned to the latest version within its version spec.
+    UpToDate,
+    /// The plugin isn't pinned to a version, so it already resolves to its latest (matching) version.
+    NotPinned,
+    /// There is a newer version, but the update policy of the plugin doesn't allow updating to it.
+    HeldBack,
+    /// There is a newer version, but updating to it was declined.
+    Declined,
+    /// The plugin is from a plugin source that isn't supported yet, so it was skipped.
+    Unsupported,
+}
+
+/// The update of a plugin in the manifest.
+#[derive(Debug, serde::Serialize)]
+pub struct PluginUpdate {
+    pub plugin_name: String,
+    pub policy: UpdatePolicy,
+    pub status: UpdateStatus,
+    /// The version that the plugin was pinned to, or [`None`] if it isn't pinned.
+    pub from: Option<UpdateVersion>,
+    /// The version that the plugin was updated to, or the newer version that was held back or declined.
+    pub to: Option<UpdateVersion>,
+    /// The pin key and value that update the plugin to the new version.
+    #[serde(skip)]
+    pin: Option<(&'static str, toml_edit::Value)>,
+}
+
+/// The output of the 'update' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct UpdateOutput {
+    pub plugins: Vec<PluginUpdate>,
+    pub manifest_path: PathBuf,
+}
+
+impl DataDisplay for UpdateOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        for plugin in &self.plugins {
+            let name = plugin.plugin_name.bright_green();
+            let (Some(from), Some(to)) = (&plugin.from, &plugin.to) else {
+                match (plugin.status, &plugin.from) {
+                    (UpdateStatus::NotPinned, _) => writeln!(
+                        w,
+                        "{}",
+                        format!(
+                            "Skipped '{}', it isn't pinned to a version",
+                            plugin.plugin_name
+                        )
+                        .dimmed()
+                    )?,
+                    (UpdateStatus::Unsupported, _) => writeln!(
+                        w,
+                        "{}",
+                        format!(
+                            "Skipped '{}', its plugin source is not supported yet",
+                            plugin.plugin_name
+                        )
+                        .dimmed()
+                    )?,
+                    (_, Some(from)) => writeln!(w, "'{name}' is up to date at {from}")?,
+                    (_, None) => writeln!(w, "'{name}' is up to date")?,
+                }
+                continue;
+            };
+
+            match plugin.status {
+                UpdateStatus::Updated => writeln!(
+                    w,
+                    "Updated '{name}': {from} {} {}",
+                    "->".green(),
+                    to.green()
+                )?,
+                UpdateStatus::HeldBack => writeln!(
+                    w,
+                    "Held back '{name}' at {from} by update policy '{0}', newer version {1}",
+                    plugin.policy,
+                    to.yellow()
+                )?,
+                _ => writeln!(w, "Kept '{name}' at {from}, newer version {}", to.yellow())?,
+            }
+        }
+
+        let updated = self
+            .plugins
+            .iter()
+            .filter(|plugin| plugin.status == UpdateStatus::Updated)
+            .count();
+        match updated {
+            0 => write!(w, "No plugins were updated"),
+            updated => write!(
+                w,
+                "Updated {0} plugins in '{1}'",
+                updated.green(),
+                self.manifest_path.display().bright_green()
+            ),
+        }
+    }
+
+    /// One record per plugin.
+    fn records(&self) -> Result<Vec<Value>, serde_json::Error> {
+        self.plugins.iter().map(serde_json::to_value).collect()
+    }
+}
+
+impl Subcommand for Update {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let mut plugin_names = if self.plugins.is_empty() {
+            manifest.plugin.keys().cloned().collect::<Vec<_>>()
+        } else {
+            self.plugins.clone()
+        };
+        plugin_names.sort();
+        plugin_names.dedup();
+
+        // the versions of every plugin are resolved before anything is asked or written
+        let mut plugins = futures::future::try_join_all(
+            plugin_names
+                .iter()
+                .map(|plugin_name| plan_update(session, manifest, plugin_name, self.interactive)),
+        )
+        .await?;
+
+        if self.interactive {
+            for plugin in &mut plugins {
+                let (UpdateStatus::Updated, Some(from), Some(to)) =
+                    (plugin.status, &plugin.from, &plugin.to)
+                else {
+                    continue;
+                };
+
+                let question = format!(
+                    "Update '{0}' from {from} to {to}?",
+                    plugin.plugin_name.bright_green()
+                );
+                if !session.cli_output().confirm(&question).into_diagnostic()? {
+                    plugin.status = UpdateStatus::Declined;
+                    plugin.pin = None;
+                }
+            }
+        }
+
+        let mut document = manifest.edit()?;
+        let mut updated = false;
+        for plugin in &plugins {
+            if let Some((key, value)) = &plugin.pin {
+                document.update_pin(&plugin.plugin_name, key, value.clone());
+                updated = true;
+            }
+        }
+
+        if updated {
+            document.save().await.wrap_err_with(|| {
+                format!("Error writing manifest '{}'", document.path().display())
+            })?;
+        }
+
+        let out = UpdateOutput {
+            plugins,
+            manifest_path: document.path().to_path_buf(),
+        };
+        session.cli_output().display(&out).into_diagnostic()
+    }
+}
+
+/// Find the version that a plugin in the manifest would be updated to.
+///
+/// The new version is the latest version within the plugin's version spec (or release channel) that is newer than the
+/// current version by version precedence. Unless the update is interactive, it must also be allowed by the plugin's update policy. Only plugins that are pinned to a version name
+/// or identifier are updated, by pinning them to the name or identifier of the new version.
+#[inline]
+async fn plan_update(
+    session: &IoSession,
+    manifest: &Manifest,
+    plugin_name: &str,
+    interactive: bool,
+) -> miette::Result<PluginUpdate> {
+    let mut update = PluginUpdate {
+        plugin_name: plugin_name.to_string(),
+        policy: UpdatePolicy::default(),
+        status: UpdateStatus::NotPinned,
+        from: None,
+        to: None,
+        pin: None,
+    };
+
+    let PluginDownloadSpec::Spiget(spiget) = manifest.plugin(plugin_name)? else {
+        update.status = UpdateStatus::Unsupported;
+        return Ok(update);
+    };
+    update.policy = spiget.update_policy.unwrap_or_default();
+
+    let pin_key = match spiget.pin_key() {
+        Some(pin_key @ ("version" | "version_id")) => pin_key,
+        _ => return Ok(update),
+    };
+
+    let plugin = SpigetPlugin::from_manifest(session, manifest, plugin_name, spiget)
+        .await
+        .wrap_err_with(|| format!("Error getting plugin '{plugin_name}'"))?;
+
+    let pinned_spec = spiget.pinned_version();
+    let Some(current) = plugin.version_from_spec(&pinned_spec)? else {
+        return Err(plugin.version_not_found(manifest, plugin_name, &pinned_spec));
+    };
+    update.from = Some(UpdateVersion::new(&current));
+
+    let target = manifest.server_target();
+    let spec = spiget
+        .version_spec
+        .clone()
+        .unwrap_or(VersionSpec::Latest(spiget.min_channel.unwrap_or_default()));
+
+    // the versions are ordered by upload date by default, so the latest version isn't necessarily newer than the current one
+    let current_name = current.version_name();
+    let candidates = plugin.candidate_versions(&spec, |version| {
+        is_newer(&current_name, &version.version_name())
+    });
+    let latest = plugin
+        .resolve_candidates(plugin_name, &spec, &target, &candidates)
+        .await
+        .or_else(incompatible_as_none)?;
+    let Some(latest) = latest else {
+        update.status = UpdateStatus::UpToDate;
+        return Ok(update);
+    };
+
+    let allowed =
+        if interactive || allows_update(update.policy, &current_name, &latest.version_name()) {
+            Some(latest.clone())
+        } else {
+            let allowed = candidates.iter().filter(|version| {
+                allows_update(update.policy, &current_name, &version.version_name())
+            });
+            plugin
+                .resolve_candidates(plugin_name, &spec, &target, allowed)
+                .await
+                .or_else(incompatible_as_none)?
+        };
+
+    match allowed {
+        Some(allowed) => {
+            let value = match pin_key {
+                "version_id" => i64::try_from(u64::from(allowed.version.id))
+                    .into_diagnostic()?
+                    .into(),
+                _ => allowed.version.name.as_str().into(),
+            };
+
+            update.status = UpdateStatus::Updated;
+            update.to = Some(UpdateVersion::new(&allowed));
+            update.pin = Some((pin_key, value));
+        }
+        None => {
+            update.status = UpdateStatus::HeldBack;
+            update.to = Some(UpdateVersion::new(&latest));
+        }
+    }
+
+    Ok(update)
+}
+
+/// Newer versions that are incompatible with the server can't be updated to, but that shouldn't stop other plugins from updating.
+#[inline]
+fn incompatible_as_none(error: miette::Report) -> miette::Result<Option<SpigetResourceVersion>> {
+    match error.downcast_ref::<IncompatibleVersionsError>() {
+        Some(error) => {
+            log::info!("{error}");
+            Ok(None)
+        }
+        None => Err(error),
+    }
+}
+
+/// Whether a version name is newer than the current version name, by version precedence.
+#[inline]
+fn is_newer(current: &str, version: &str) -> bool {
+    compare_version_names(version, current).is_gt()
+}
+
+/// Whether a plugin may be updated from the current version to another version without asking:
+/// the version must be newer than the current version, and allowed by the update policy.
+#[inline]
+fn allows_update(policy: UpdatePolicy, current: &str, version: &str) -> bool {
+    is_newer(current, version) && policy.allows(current, version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_allows_update() {
+        assert!(allows_update(UpdatePolicy::Any, "2.0", "2.1"));
+        assert!(allows_update(UpdatePolicy::Any, "1.9", "2.0"));
+        // a hotfix for an older version that was uploaded later isn't an update
+        assert!(!allows_update(UpdatePolicy::Any, "2.0", "1.9.5"));
+        assert!(!allows_update(UpdatePolicy::Any, "2.0", "2.0"));
+
+        assert!(allows_update(UpdatePolicy::Minor, "1.2", "1.3"));
+        assert!(!allows_update(UpdatePolicy::Minor, "1.2", "2.0"));
+        assert!(allows_update(UpdatePolicy::Patch, "1.2.0", "1.2.1"));
+        assert!(!allows_update(UpdatePolicy::Patch, "1.2.1", "1.2.1"));
+        assert!(!allows_update(UpdatePolicy::Manual, "1.2", "1.3"));
+    }
+}
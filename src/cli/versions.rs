@@ -1,19 +1,22 @@
 //! The 'versions' subcommand for listing versions of a plugin.
 
 use clap::Args;
-use miette::IntoDiagnostic;
+use miette::{Context, IntoDiagnostic};
 use owo_colors::AnsiColors;
+use serde_json::Value;
 
 use crate::{
     adapter::{
         spiget::{SpigetPlugin, SpigetResourceDetails},
-        PluginDetails, PluginVersion,
+        PluginApiType, PluginDetails, PluginVersion,
     },
     cli::Subcommand,
+    compat::Compatibility,
     manifest::{Manifest, PluginDownloadSpec},
-    output::DataDisplay,
-    session::IoSession,
-    util::{CliTable, CliTableRow},
+    output::{version_record, DataDisplay},
+    session::{DownloadSpec, IoSession},
+    util::{Alignment, CliTable, CliTableRow, TableStyle},
+    version::{ReleaseChannel, VersionOrder},
 };
 
 use super::PluginSpecArgs;
@@ -41,6 +44,19 @@ pub struct Versions {
         default_value = "%Y-%m-%d"
     )]
     pub time_format: String,
+    /// Check if versions are compatible with the server in the manifest by downloading the jars that aren't in the download cache,
+    /// like the global `--check-jars`.
+    /// Without this, only the versions in the download cache are checked.
+    #[arg(short = 'c', long, action = clap::ArgAction::SetTrue)]
+    pub check_compatibility: bool,
+    /// Only list versions in this release channel or in a more stable channel.
+    /// For example, 'beta' lists betas and releases, but not alphas or snapshots.
+    #[arg(long, value_name = "CHANNEL")]
+    pub channel: Option<ReleaseChannel>,
+    /// The order to list the versions in, with the latest version first.
+    /// Defaults to the `version_order` of the plugin in the manifest.
+    #[arg(long, value_name = "ORDER")]
+    pub sort: Option<VersionOrder>,
 }
 
 /// The output of the list command. Written to stdout with [`DataDisplay`].
@@ -53,6 +69,10 @@ pub struct VersionsOutput<'a, P: PluginDetails, V: PluginVersion> {
     pub details: P,
     #[serde(serialize_with = "crate::adapter::PluginVersion::serialize_slice")]
     pub versions: &'a [V],
+    /// The compatibility of each version with the server in the manifest.
+    /// Empty if the manifest doesn't specify the server.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub compatibility: Vec<Compatibility>,
 }
 
 /// Options for how data should be formatted to the terminal.
@@ -62,35 +82,37 @@ pub struct VersionsOutputCfg {
     pub strftime_format: String,
     /// Whether download URLs for versions should be written
     pub write_download_urls: bool,
+    /// The style of the versions table
+    pub table_style: TableStyle,
+    /// The maximum width of the versions table
+    pub max_width: Option<usize>,
 }
 
 impl<'a, P: PluginDetails, V: PluginVersion> DataDisplay for VersionsOutput<'a, P, V> {
-    fn write_json(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
-        let json_string = serde_json::to_string(self).unwrap();
-        write!(w, "{json_string}")
-    }
-
     fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
-        let mut headers = if self.cfg.write_download_urls {
-            CliTableRow::new(&[
-                "Version Name".into(),
-                "Version Date".into(),
-                "Version Identifier".into(),
-                "Download URL".into(),
-            ])
-        } else {
-            CliTableRow::new(&[
-                "Version Name".into(),
-                "Version Date".into(),
-                "Version Identifier".into(),
-            ])
-        };
+        let mut header_text = vec![
+            "Version Name".to_string(),
+            "Version Date".to_string(),
+            "Version Identifier".to_string(),
+            "Channel".to_string(),
+        ];
+        if self.cfg.write_download_urls {
+            header_text.push("Download URL".into());
+        }
+        if !self.compatibility.is_empty() {
+            header_text.push("Compatible".into());
+        }
 
+        let mut headers = CliTableRow::new(&header_text);
         headers.color_all(AnsiColors::Green);
 
-        let mut table = CliTable::new(headers);
+        let mut table = CliTable::new(headers)
+            .with_style(self.cfg.table_style)
+            .with_max_width(self.cfg.max_width);
+        // version identifiers are numeric for most APIs
+        table.align(2, Alignment::Right);
 
-        for version in self.versions {
+        for (index, version) in self.versions.iter().enumerate() {
             let datetime_str = version
                 .publish_date()
                 .map(|d| d.format(&self.cfg.strftime_format).to_string());
@@ -99,6 +121,7 @@ impl<'a, P: PluginDetails, V: PluginVersion> DataDisplay for VersionsOutput<'a,
                 version.version_name().to_string(),
                 datetime_str.as_deref().unwrap_or("").to_string(),
                 version.version_identifier().to_string(),
+                version.channel().to_string(),
             ];
 
             // include download URL if requested
@@ -106,8 +129,25 @@ impl<'a, P: PluginDetails, V: PluginVersion> DataDisplay for VersionsOutput<'a,
                 row_cell_text.push(version.download_url().to_string());
             }
 
+            let compatibility = self.compatibility.get(index);
+            match compatibility {
+                Some(Compatibility::Compatible) => row_cell_text.push("yes".into()),
+                Some(Compatibility::Incompatible) => row_cell_text.push("no".into()),
+                Some(Compatibility::Unknown) => row_cell_text.push("---".into()),
+                None => (),
+            }
+
             let mut row = CliTableRow::new(&row_cell_text);
             row[0].color = AnsiColors::Green;
+            if version.channel() != ReleaseChannel::Release {
+                row[3].color = AnsiColors::Yellow;
+            }
+            // incompatible versions are flagged, since they shouldn't be installed on the server
+            if compatibility == Some(&Compatibility::Incompatible) {
+                row[0].color = AnsiColors::Red;
+                let last = row_cell_text.len() - 1;
+                row[last].color = AnsiColors::Red;
+            }
 
             table.add(row);
         }
@@ -116,6 +156,22 @@ impl<'a, P: PluginDetails, V: PluginVersion> DataDisplay for VersionsOutput<'a,
 
         Ok(())
     }
+
+    /// One record per version, with the details of the plugin so records from different plugins can be told apart.
+    fn records(&self) -> Result<Vec<Value>, serde_json::Error> {
+        self.versions
+            .iter()
+            .enumerate()
+            .map(|(index, version)| {
+                let mut record = version_record(&self.details, version)?;
+                if let Some(compatibility) = self.compatibility.get(index) {
+                    record.insert("compatibility".into(), serde_json::to_value(compatibility)?);
+                }
+
+                Ok(Value::Object(record))
+            })
+            .collect()
+    }
 }
 
 impl Subcommand for Versions {
@@ -126,24 +182,76 @@ impl Subcommand for Versions {
 
         match plugin_manifest {
             PluginDownloadSpec::Spiget(spiget_plugin_manifest) => {
-                let spiget_plugin =
-                    SpigetPlugin::new(&session, spiget_plugin_manifest.resource_id).await?;
+                let spiget_plugin = SpigetPlugin::from_manifest(
+                    session,
+                    manifest,
+                    &self.plugin.plugin_name,
+                    spiget_plugin_manifest,
+                )
+                .await
+                .wrap_err_with(|| format!("Error getting plugin '{}'", self.plugin.plugin_name))?;
 
+                let order = self
+                    .sort
+                    .unwrap_or(spiget_plugin_manifest.version_order.unwrap_or_default());
                 let versions = spiget_plugin
-                    .iter_versions()
+                    .sorted_versions(order)
+                    .into_iter()
+                    .filter(|version| {
+                        self.channel
+                            .is_none_or(|channel| version.channel() >= channel)
+                    })
                     .take(self.limit as _)
                     .collect::<Vec<_>>();
 
+                let target = manifest.server_target();
+                let mut compatibility = Vec::new();
+                if !target.is_empty() {
+                    for version in &versions {
+                        let metadata = if self.check_compatibility || session.check_jars() {
+                            let spec = DownloadSpec {
+                                plugin_name: &self.plugin.plugin_name,
+                                version,
+                                api_type: PluginApiType::Spiget,
+                            };
+                            session
+                                .plugin_metadata(spec)
+                                .await
+                                .map(Some)
+                                .wrap_err_with(|| {
+                                    format!("Error checking version '{}'", version.version.name)
+                                })?
+                        } else {
+                            session
+                                .download_cache()
+                                .jar_metadata(
+                                    &self.plugin.plugin_name,
+                                    &version.version_identifier(),
+                                )
+                                .await?
+                        };
+
+                        compatibility.push(
+                            target.compatibility(
+                                metadata.as_ref().map(|metadata| &metadata.descriptor),
+                            ),
+                        );
+                    }
+                }
+
                 let output = VersionsOutput {
                     cfg: VersionsOutputCfg {
                         strftime_format: self.time_format.clone(),
                         write_download_urls: self.download_url,
+                        table_style: session.cli_output().table_style(),
+                        max_width: session.cli_output().terminal_width(),
                     },
                     details: SpigetResourceDetails::new(
                         spiget_plugin.resource_id(),
                         &self.plugin.plugin_name,
                     ),
                     versions: &*versions,
+                    compatibility,
                 };
 
                 session.cli_output().display(&output).into_diagnostic()?;
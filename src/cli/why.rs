@@ -0,0 +1,124 @@
+//! The 'why' subcommand for showing why a plugin is needed, by the plugins that depend on it.
+
+use clap::Args;
+use clap_complete::ArgValueCompleter;
+use miette::IntoDiagnostic;
+use owo_colors::OwoColorize;
+
+use crate::{
+    cli::Subcommand,
+    dependencies::{DependencyGraph, DependencyKind},
+    manifest::Manifest,
+    output::DataDisplay,
+    session::IoSession,
+};
+
+use super::{complete_plugin_name, write_tree, PluginNotFoundError, TreeNode};
+
+/// The 'why' subcommand.
+#[derive(Args, Debug, Clone)]
+pub struct Why {
+    /// The name of a plugin in the manifest, or the name of a plugin in the plugin descriptors (like a missing dependency).
+    #[arg(value_name = "PLUGIN_NAME", add = ArgValueCompleter::new(complete_plugin_name))]
+    pub name: String,
+}
+
+/// The output of the 'why' subcommand.
+#[derive(Debug, serde::Serialize)]
+pub struct WhyOutput {
+    /// The plugin, with the plugins that (soft) depend on it or load before it as its children.
+    pub tree: TreeNode,
+}
+
+impl DataDisplay for WhyOutput {
+    fn write_hr(&self, w: &mut impl std::io::Write) -> Result<(), std::io::Error> {
+        if self.tree.children.is_empty() {
+            return write!(
+                w,
+                "No plugin in the manifest needs '{}'",
+                self.tree.name.bright_green()
+            );
+        }
+
+        // every line of the tree ends with a newline, which the output shouldn't end with
+        let mut out = Vec::new();
+        write_tree(&mut out, &self.tree, "", true)?;
+
+        write!(w, "{}", String::from_utf8_lossy(&out).trim_end())
+    }
+}
+
+impl Subcommand for Why {
+    async fn run(&self, session: &IoSession, manifest: &Manifest) -> miette::Result<()> {
+        let graph = DependencyGraph::from_manifest(session, manifest).await?;
+
+        let tree = match graph.plugin(&self.name) {
+            Some(plugin) => TreeNode {
+                name: plugin.descriptor.name.clone(),
+                plugin_name: Some(plugin.plugin_name.clone()),
+                version: Some(plugin.descriptor.version.clone()),
+                kind: None,
+                repeated: false,
+                children: dependents_nodes(&graph, &plugin.descriptor.name, &mut vec![]),
+            },
+            None => {
+                let children = dependents_nodes(&graph, &self.name, &mut vec![]);
+                if children.is_empty() {
+                    return Err(PluginNotFoundError::new(&self.name, manifest).into());
+                }
+
+                TreeNode {
+                    name: self.name.clone(),
+                    plugin_name: None,
+                    version: None,
+                    kind: None,
+                    repeated: false,
+                    children,
+                }
+            }
+        };
+
+        session
+            .cli_output()
+            .display(&WhyOutput { tree })
+            .into_diagnostic()
+    }
+}
+
+/// The trees of the plugins that depend on, soft depend on, or load before the plugin with the given name,
+/// with the plugins that depend on those plugins as their children.
+#[inline]
+fn dependents_nodes<'a>(
+    graph: &'a DependencyGraph,
+    name: &'a str,
+    shown: &mut Vec<&'a str>,
+) -> Vec<TreeNode> {
+    let mut dependents = graph.dependents(name).collect::<Vec<_>>();
+    dependents.sort_by_key(|&(kind, plugin)| (&plugin.plugin_name, kind));
+    dependents.dedup_by_key(|(_, plugin)| &plugin.plugin_name);
+
+    let mut nodes = Vec::new();
+    for (kind, plugin) in dependents {
+        let repeated = shown.contains(&plugin.plugin_name.as_str());
+        let mut node = TreeNode {
+            name: plugin.descriptor.name.clone(),
+            plugin_name: Some(plugin.plugin_name.clone()),
+            version: Some(plugin.descriptor.version.clone()),
+            kind: Some(kind),
+            repeated,
+            children: vec![],
+        };
+
+        if !repeated {
+            shown.push(&plugin.plugin_name);
+            // loading before a plugin doesn't make a plugin needed by the plugins that need it
+            if kind != DependencyKind::LoadBefore {
+                node.children = dependents_nodes(graph, &plugin.descriptor.name, shown);
+            }
+        }
+
+        nodes.push(node);
+    }
+
+    nodes
+}
@@ -0,0 +1,293 @@
+//! Compatibility of plugin versions with the Minecraft server that the manifest is for.
+
+use std::{cmp::Ordering, fmt, str::FromStr};
+
+use crate::jar::{DescriptorKind, PluginDescriptor};
+
+/// A Minecraft version like `1.21.4`. Missing parts are treated as zero, so `1.21` and `1.21.0` are equal.
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct MinecraftVersion(Vec<u32>);
+
+/// The error returned by [`<MinecraftVersion as FromStr>::from_str`].
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("'{0}' is not a Minecraft version like '1.21.4'")]
+pub struct MinecraftVersionParseError(pub String);
+
+impl MinecraftVersion {
+    /// Whether this version is in the same major line (like `1.21`) as the other version.
+    #[inline]
+    pub fn same_line(&self, other: &Self) -> bool {
+        self.part(0) == other.part(0) && self.part(1) == other.part(1)
+    }
+
+    #[inline]
+    fn part(&self, index: usize) -> u32 {
+        self.0.get(index).copied().unwrap_or(0)
+    }
+}
+
+impl FromStr for MinecraftVersion {
+    type Err = MinecraftVersionParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts = s
+            .trim()
+            .split('.')
+            .map(u32::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| MinecraftVersionParseError(s.to_string()))?;
+
+        if parts.len() < 2 {
+            return Err(MinecraftVersionParseError(s.to_string()));
+        }
+
+        Ok(Self(parts))
+    }
+}
+
+impl TryFrom<String> for MinecraftVersion {
+    type Error = MinecraftVersionParseError;
+
+    #[inline]
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value)
+    }
+}
+
+impl From<MinecraftVersion> for String {
+    #[inline]
+    fn from(value: MinecraftVersion) -> Self {
+        value.to_string()
+    }
+}
+
+impl fmt::Display for MinecraftVersion {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let parts = self.0.iter().map(ToString::to_string).collect::<Vec<_>>();
+        write!(f, "{}", parts.join("."))
+    }
+}
+
+impl PartialEq for MinecraftVersion {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for MinecraftVersion {}
+
+impl PartialOrd for MinecraftVersion {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MinecraftVersion {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.0.len().max(other.0.len());
+        (0..len)
+            .map(|index| self.part(index).cmp(&other.part(index)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// The server software that the manifest is for.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize, dm::Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum ServerPlatform {
+    #[display("Paper")]
+    Paper,
+    #[display("Spigot")]
+    Spigot,
+    #[display("Velocity")]
+    Velocity,
+    #[display("BungeeCord")]
+    Bungeecord,
+}
+
+impl ServerPlatform {
+    /// Whether the platform can load plugins with the given kind of plugin descriptor.
+    #[inline]
+    pub fn supports(self, kind: DescriptorKind) -> bool {
+        match self {
+            Self::Paper => matches!(kind, DescriptorKind::Paper | DescriptorKind::Bukkit),
+            Self::Spigot => kind == DescriptorKind::Bukkit,
+            Self::Velocity => kind == DescriptorKind::Velocity,
+            Self::Bungeecord => kind == DescriptorKind::Bungee,
+        }
+    }
+}
+
+/// Why a plugin version is incompatible with the server.
+#[derive(thiserror::Error, miette::Diagnostic, Debug, Clone)]
+pub enum Incompatibility {
+    /// The plugin is for another platform, like a Velocity plugin on a Paper server.
+    #[error("{kind} plugins can't be loaded by {platform} servers")]
+    Platform {
+        kind: DescriptorKind,
+        platform: ServerPlatform,
+    },
+    /// The plugin is made for a newer Minecraft version than the server.
+    #[error("the plugin needs Minecraft {api_version} or newer, but the server runs Minecraft {minecraft_version}")]
+    ApiVersion {
+        api_version: MinecraftVersion,
+        minecraft_version: MinecraftVersion,
+    },
+}
+
+/// Whether a plugin version is compatible with the server, as far as it's known.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Compatibility {
+    Compatible,
+    Incompatible,
+    /// The compatibility isn't known, like when the jar of the version hasn't been downloaded.
+    Unknown,
+}
+
+/// The server that the plugins in a manifest are for, from the `[meta]` table of the manifest.
+#[derive(Clone, Debug, Default)]
+pub struct ServerTarget {
+    pub minecraft_version: Option<MinecraftVersion>,
+    pub platform: Option<ServerPlatform>,
+}
+
+impl ServerTarget {
+    /// Whether the manifest doesn't specify anything about the server, so every version is considered compatible.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.minecraft_version.is_none() && self.platform.is_none()
+    }
+
+    /// Check that the plugin with the given descriptor can be loaded by the server.
+    /// Jars with descriptors for several platforms can be loaded if the server supports any of them.
+    ///
+    /// The `api-version` of Bukkit and Paper plugins is the oldest Minecraft version that the plugin works with,
+    /// so plugins with a newer API version than the server are incompatible. API versions that can't be parsed are ignored.
+    #[inline]
+    pub fn check(&self, descriptor: &PluginDescriptor) -> Result<(), Incompatibility> {
+        if let Some(platform) = self.platform {
+            if !descriptor.kinds().any(|kind| platform.supports(kind)) {
+                return Err(Incompatibility::Platform {
+                    kind: descriptor.kind,
+                    platform,
+                });
+            }
+        }
+
+        let api_version = descriptor
+            .api_version
+            .as_deref()
+            .and_then(|api_version| MinecraftVersion::from_str(api_version).ok());
+
+        if let (Some(minecraft_version), Some(api_version)) = (&self.minecraft_version, api_version)
+        {
+            if api_version > *minecraft_version {
+                return Err(Incompatibility::ApiVersion {
+                    api_version,
+                    minecraft_version: minecraft_version.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The compatibility of the plugin with the given descriptor, or [`Compatibility::Unknown`] if there is no descriptor.
+    #[inline]
+    pub fn compatibility(&self, descriptor: Option<&PluginDescriptor>) -> Compatibility {
+        match descriptor.map(|descriptor| self.check(descriptor)) {
+            Some(Ok(())) => Compatibility::Compatible,
+            Some(Err(_)) => Compatibility::Incompatible,
+            None => Compatibility::Unknown,
+        }
+    }
+
+    /// Whether the Minecraft version of the server is in one of the given tested versions (like the tested versions of a Spigot resource).
+    /// Tested versions are usually major lines like `1.21`, so only the major line of the server version is compared.
+    ///
+    /// Returns [`None`] if the Minecraft version or the tested versions aren't known.
+    #[inline]
+    pub fn tested_with(&self, tested_versions: &[String]) -> Option<bool> {
+        let minecraft_version = self.minecraft_version.as_ref()?;
+        let tested_versions = tested_versions
+            .iter()
+            .filter_map(|version| MinecraftVersion::from_str(version).ok())
+            .collect::<Vec<_>>();
+
+        if tested_versions.is_empty() {
+            return None;
+        }
+
+        Some(
+            tested_versions
+                .iter()
+                .any(|version| version.same_line(minecraft_version)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descriptor(kind: DescriptorKind, api_version: Option<&str>) -> PluginDescriptor {
+        PluginDescriptor {
+            kind,
+            other_kinds: vec![],
+            name: "Test".to_string(),
+            version: "1.0".to_string(),
+            main: None,
+            api_version: api_version.map(ToString::to_string),
+            authors: vec![],
+            website: None,
+            depend: vec![],
+            softdepend: vec![],
+            loadbefore: vec![],
+        }
+    }
+
+    #[test]
+    fn test_server_target_check() {
+        let target = ServerTarget {
+            minecraft_version: Some("1.20.6".parse().unwrap()),
+            platform: Some(ServerPlatform::Paper),
+        };
+
+        assert!(target
+            .check(&descriptor(DescriptorKind::Bukkit, Some("1.20")))
+            .is_ok());
+        assert!(target
+            .check(&descriptor(DescriptorKind::Paper, Some("1.13")))
+            .is_ok());
+        assert!(target
+            .check(&descriptor(DescriptorKind::Bukkit, None))
+            .is_ok());
+        assert!(matches!(
+            target.check(&descriptor(DescriptorKind::Bukkit, Some("1.21"))),
+            Err(Incompatibility::ApiVersion { .. })
+        ));
+        assert!(matches!(
+            target.check(&descriptor(DescriptorKind::Velocity, None)),
+            Err(Incompatibility::Platform { .. })
+        ));
+
+        let mut multi_platform = descriptor(DescriptorKind::Velocity, None);
+        multi_platform.other_kinds = vec![DescriptorKind::Bungee, DescriptorKind::Bukkit];
+        assert!(target.check(&multi_platform).is_ok());
+
+        assert_eq!(
+            target.tested_with(&["1.19".to_string(), "1.20".to_string()]),
+            Some(true)
+        );
+        assert_eq!(target.tested_with(&["1.21".to_string()]), Some(false));
+        assert_eq!(target.tested_with(&[]), None);
+    }
+}
@@ -0,0 +1,281 @@
+//! Layered configuration for user and team defaults.
+//!
+//! Configuration is read from the following layers, where later layers override earlier ones:
+//! 1. The user's config file ([`CONFIG_FILE_NAME`] in the user's config directory, like `$XDG_CONFIG_HOME/pluginstall/`).
+//! 2. The project's config file ([`PROJECT_CONFIG_FILE_NAME`] in the working directory or one of its parents).
+//! 3. Environment variables (see [`Config::from_env`]).
+//!
+//! CLI flags are applied on top of these layers by the [`crate::cli::Cli`] methods.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use directories::ProjectDirs;
+use hyperx::header::{Authorization, Basic, Bearer};
+use rq::Url;
+use tokio::fs;
+
+use crate::error::ParseError;
+use crate::output::OutputFormat;
+use crate::util::{LogLevel, TableStyle};
+
+/// The name of the user config file in the user's config directory.
+pub static CONFIG_FILE_NAME: &str = "config.toml";
+
+/// The name of the project-local config file.
+pub static PROJECT_CONFIG_FILE_NAME: &str = ".pluginstall.toml";
+
+/// The prefix of environment variables that configure pluginstall.
+pub static ENV_PREFIX: &str = "PLUGINSTALL_";
+
+/// Get the project directories for pluginstall (i.e., where the config and cache directories are).
+/// Returns [`None`] if no home directory could be found.
+#[inline]
+pub fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "pluginstall")
+}
+
+/// Configuration of pluginstall. All values are optional, and unset values fall back to a lower layer,
+/// and eventually to the built-in defaults.
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// The path to the manifest file.
+    pub manifest: Option<PathBuf>,
+    /// The directory where the download caches of manifests are stored.
+    /// Each manifest gets a subdirectory in this directory with the name of the manifest.
+    pub cache_dir: Option<PathBuf>,
+    /// The default output format.
+    pub format: Option<OutputFormat>,
+    /// The log level.
+    pub log_level: Option<LogLevel>,
+    /// The style of tables in human readable output.
+    pub table_style: Option<TableStyle>,
+    /// Network settings.
+    #[serde(default)]
+    pub network: NetworkConfig,
+    /// Mirrors to use instead of the official APIs.
+    #[serde(default)]
+    pub mirrors: MirrorConfig,
+    /// Credentials for APIs and mirrors, keyed by host name.
+    #[serde(default)]
+    pub credentials: HashMap<String, Credentials>,
+}
+
+/// Network settings in the config.
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    /// The maximum number of concurrent HTTP requests.
+    pub concurrency: Option<usize>,
+    /// The timeout of HTTP requests in seconds.
+    pub timeout: Option<u64>,
+    /// The timeout for connecting to a server in seconds.
+    pub connect_timeout: Option<u64>,
+    /// The user agent sent to APIs.
+    pub user_agent: Option<String>,
+}
+
+/// API mirrors in the config. Mirrors must serve the same API as the official API they replace.
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub struct MirrorConfig {
+    /// The base URL of a Spiget API mirror (instead of `https://api.spiget.org/v2/`).
+    pub spiget: Option<Url>,
+}
+
+/// Credentials sent to a host in the `Authorization` header.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum Credentials {
+    /// A bearer token.
+    Token { token: String },
+    /// A username and password for basic authentication.
+    Basic { username: String, password: String },
+}
+
+impl Credentials {
+    /// The value of the `Authorization` header for these credentials.
+    #[inline]
+    pub fn authorization(&self) -> String {
+        match self {
+            Self::Token { token } => Authorization(Bearer {
+                token: token.clone(),
+            })
+            .to_string(),
+            Self::Basic { username, password } => Authorization(Basic {
+                username: username.clone(),
+                password: Some(password.clone()),
+            })
+            .to_string(),
+        }
+    }
+}
+
+/// Error with loading the config.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+pub enum ConfigError {
+    #[error("Error reading config file '{}'", .0.display())]
+    Io(PathBuf, #[source] std::io::Error),
+    #[error("Error parsing config file '{}'", .0.display())]
+    Parse(
+        PathBuf,
+        #[source]
+        #[diagnostic_source]
+        ParseError,
+    ),
+    #[error("Invalid value '{value}' for environment variable '{var}': {reason}")]
+    Env {
+        var: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl Config {
+    /// Load the config from all layers (the user's config file, the project's config file, and environment variables).
+    /// Config files that don't exist are skipped.
+    #[inline]
+    pub async fn load() -> Result<Self, ConfigError> {
+        let mut config = Self::default();
+
+        if let Some(dirs) = project_dirs() {
+            let path = dirs.config_dir().join(CONFIG_FILE_NAME);
+            if let Some(user_config) = Self::from_file(&path).await? {
+                config = config.layer(user_config);
+            }
+        }
+
+        if let Some(path) = find_project_config() {
+            if let Some(project_config) = Self::from_file(&path).await? {
+                // relative paths in the project config are relative to the project, not the working directory
+                let project_dir = path.parent().unwrap_or(Path::new("."));
+                config = config.layer(project_config.relative_to(project_dir));
+            }
+        }
+
+        Ok(config.layer(Self::from_env()?))
+    }
+
+    /// Parse a config file. Returns [`None`] if the file doesn't exist.
+    #[inline]
+    pub async fn from_file(path: &Path) -> Result<Option<Self>, ConfigError> {
+        let contents = match fs::read_to_string(path).await {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(ConfigError::Io(path.to_path_buf(), err)),
+        };
+
+        log::debug!("Using config file '{}'", path.display());
+
+        toml::from_str::<Self>(&contents)
+            .map(Some)
+            .map_err(|err| ConfigError::Parse(path.to_path_buf(), ParseError::toml(err, contents)))
+    }
+
+    /// Read the config from environment variables. The following variables are supported:
+    /// - `PLUGINSTALL_MANIFEST`
+    /// - `PLUGINSTALL_CACHE_DIR`
+    /// - `PLUGINSTALL_FORMAT`
+    /// - `PLUGINSTALL_LOG_LEVEL`
+    /// - `PLUGINSTALL_TABLE_STYLE`
+    /// - `PLUGINSTALL_CONCURRENCY`
+    /// - `PLUGINSTALL_TIMEOUT`
+    /// - `PLUGINSTALL_CONNECT_TIMEOUT`
+    /// - `PLUGINSTALL_USER_AGENT`
+    /// - `PLUGINSTALL_SPIGET_MIRROR`
+    #[inline]
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Self {
+            manifest: env_var("MANIFEST")?,
+            cache_dir: env_var("CACHE_DIR")?,
+            format: env_var("FORMAT")?,
+            log_level: env_var("LOG_LEVEL")?,
+            table_style: env_var("TABLE_STYLE")?,
+            network: NetworkConfig {
+                concurrency: env_var("CONCURRENCY")?,
+                timeout: env_var("TIMEOUT")?,
+                connect_timeout: env_var("CONNECT_TIMEOUT")?,
+                user_agent: env_var("USER_AGENT")?,
+            },
+            mirrors: MirrorConfig {
+                spiget: env_var("SPIGET_MIRROR")?,
+            },
+            credentials: HashMap::new(),
+        })
+    }
+
+    /// Layer another config on top of this one. Values set in `other` override the values in this config.
+    #[inline]
+    #[must_use]
+    pub fn layer(self, other: Self) -> Self {
+        let mut credentials = self.credentials;
+        credentials.extend(other.credentials);
+
+        Self {
+            manifest: other.manifest.or(self.manifest),
+            cache_dir: other.cache_dir.or(self.cache_dir),
+            format: other.format.or(self.format),
+            log_level: other.log_level.or(self.log_level),
+            table_style: other.table_style.or(self.table_style),
+            network: NetworkConfig {
+                concurrency: other.network.concurrency.or(self.network.concurrency),
+                timeout: other.network.timeout.or(self.network.timeout),
+                connect_timeout: other
+                    .network
+                    .connect_timeout
+                    .or(self.network.connect_timeout),
+                user_agent: other.network.user_agent.or(self.network.user_agent),
+            },
+            mirrors: MirrorConfig {
+                spiget: other.mirrors.spiget.or(self.mirrors.spiget),
+            },
+            credentials,
+        }
+    }
+
+    /// Make the relative paths in this config relative to the given directory.
+    #[inline]
+    #[must_use]
+    fn relative_to(mut self, dir: &Path) -> Self {
+        self.manifest = self.manifest.map(|path| dir.join(path));
+        self.cache_dir = self.cache_dir.map(|path| dir.join(path));
+        self
+    }
+}
+
+/// Find the project config file in the working directory or the closest parent directory.
+#[inline]
+fn find_project_config() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+
+    cwd.ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Read and parse a config environment variable. The name is given without the [`ENV_PREFIX`].
+/// Returns [`None`] if the variable isn't set.
+#[inline]
+fn env_var<T>(name: &str) -> Result<Option<T>, ConfigError>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let var = format!("{ENV_PREFIX}{name}");
+    let Ok(value) = std::env::var(&var) else {
+        return Ok(None);
+    };
+
+    value
+        .parse()
+        .map(Some)
+        .map_err(|err: T::Err| ConfigError::Env {
+            reason: err.to_string(),
+            var,
+            value,
+        })
+}
@@ -0,0 +1,407 @@
+//! The dependency graph of the plugins in a manifest, built from the plugin descriptors in their jars.
+
+use miette::Context;
+
+use crate::{
+    adapter::{spiget::SpigetPlugin, PluginApiType, UnsupportedApiError},
+    jar::PluginDescriptor,
+    manifest::{Manifest, PluginDownloadSpec},
+    session::{DownloadSpec, IoSession},
+};
+
+/// How a plugin relates to another plugin in its plugin descriptor.
+#[derive(serde::Serialize, Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, dm::Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum DependencyKind {
+    /// The plugin needs the other plugin to run (`depend`).
+    #[display("depends on")]
+    Depend,
+    /// The plugin can use the other plugin, but doesn't need it (`softdepend`).
+    #[display("soft depends on")]
+    SoftDepend,
+    /// The plugin should be loaded before the other plugin (`loadbefore`).
+    #[display("loads before")]
+    LoadBefore,
+}
+
+/// A plugin in the manifest, with the plugin descriptor of the version that it resolves to.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct GraphPlugin {
+    /// The name of the plugin in the manifest.
+    pub plugin_name: String,
+    pub descriptor: PluginDescriptor,
+}
+
+/// A plugin that is needed by plugins in the manifest, but isn't in the manifest itself.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct MissingDependency {
+    /// The name of the plugin, as it's written in the plugin descriptors that need it.
+    pub name: String,
+    /// The names (in the manifest) of the plugins that need this plugin.
+    pub required_by: Vec<String>,
+}
+
+/// Error for plugins that can't be loaded because they (indirectly) depend on each other.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("The plugins {} depend on each other, so they can't be loaded.", .0.join(", "))]
+#[diagnostic(help("Plugins can only be loaded after all of the plugins in their 'depend' list."))]
+pub struct DependencyCycleError(pub Vec<String>);
+
+/// The dependency graph of plugins. Dependencies are matched with plugins by the names in their plugin descriptors, ignoring case.
+#[derive(Clone, Debug)]
+pub struct DependencyGraph {
+    plugins: Vec<GraphPlugin>,
+    /// The names (in the manifest) of the plugins that were left out, because their plugin source isn't supported yet.
+    unsupported: Vec<String>,
+}
+
+impl DependencyGraph {
+    /// Create a dependency graph of the given plugins. The plugins are sorted by their names in the manifest.
+    #[inline]
+    pub fn new(plugins: impl IntoIterator<Item = GraphPlugin>) -> Self {
+        let mut plugins = plugins.into_iter().collect::<Vec<_>>();
+        plugins.sort_by(|a, b| a.plugin_name.cmp(&b.plugin_name));
+
+        Self {
+            plugins,
+            unsupported: Vec::new(),
+        }
+    }
+
+    /// Create the dependency graph of the plugins in the manifest, from the jars of the versions that they resolve to.
+    ///
+    /// Jars that aren't in the download cache are downloaded into it. Plugins from sources that aren't supported yet
+    /// are left out of the graph (see [`DependencyGraph::unsupported`]), instead of failing for every plugin.
+    #[inline]
+    pub async fn from_manifest(session: &IoSession, manifest: &Manifest) -> miette::Result<Self> {
+        let mut plugin_names = manifest.plugin.keys().collect::<Vec<_>>();
+        plugin_names.sort();
+
+        let plugins = futures::future::try_join_all(
+            plugin_names
+                .iter()
+                .map(|plugin_name| graph_plugin(session, manifest, plugin_name)),
+        )
+        .await?;
+
+        let mut unsupported = Vec::new();
+        let mut graph_plugins = Vec::with_capacity(plugins.len());
+        for (plugin_name, plugin) in plugin_names.into_iter().zip(plugins) {
+            match plugin {
+                Some(plugin) => graph_plugins.push(plugin),
+                None => unsupported.push(plugin_name.clone()),
+            }
+        }
+
+        Ok(Self {
+            unsupported,
+            ..Self::new(graph_plugins)
+        })
+    }
+
+    /// The names (in the manifest) of the plugins that were left out of the graph, because their plugin source
+    /// isn't supported yet. Sorted by name.
+    #[inline]
+    pub fn unsupported(&self) -> &[String] {
+        &self.unsupported
+    }
+
+    /// The plugins in the graph, sorted by their names in the manifest.
+    #[inline]
+    pub fn plugins(&self) -> &[GraphPlugin] {
+        &self.plugins
+    }
+
+    /// Find a plugin by its name in the manifest or its name in its plugin descriptor, ignoring case.
+    #[inline]
+    pub fn plugin(&self, name: &str) -> Option<&GraphPlugin> {
+        self.index(name).map(|index| &self.plugins[index])
+    }
+
+    /// The plugins that the given plugin depends on, soft depends on, or loads before, that are in the graph.
+    #[inline]
+    pub fn dependencies<'a>(
+        &'a self,
+        plugin: &'a GraphPlugin,
+    ) -> impl Iterator<Item = (DependencyKind, &'a GraphPlugin)> + 'a {
+        relations(&plugin.descriptor)
+            .filter_map(|(kind, name)| self.plugin(name).map(|dependency| (kind, dependency)))
+    }
+
+    /// The plugins that depend on, soft depend on, or load before the plugin with the given descriptor name.
+    /// The name doesn't have to be a plugin in the graph, so this also works for missing dependencies.
+    #[inline]
+    pub fn dependents<'a>(
+        &'a self,
+        name: &'a str,
+    ) -> impl Iterator<Item = (DependencyKind, &'a GraphPlugin)> + 'a {
+        self.plugins.iter().flat_map(move |plugin| {
+            relations(&plugin.descriptor)
+                .filter(|(_, dependency)| dependency.eq_ignore_ascii_case(name))
+                .map(move |(kind, _)| (kind, plugin))
+        })
+    }
+
+    /// The hard dependencies of the plugins that aren't in the graph, sorted by name.
+    #[inline]
+    pub fn missing_dependencies(&self) -> Vec<MissingDependency> {
+        let mut missing = Vec::<MissingDependency>::new();
+
+        for plugin in &self.plugins {
+            for name in &plugin.descriptor.depend {
+                // a plugin that was left out of the graph may be the dependency, but its descriptor name isn't known
+                let unsupported = self
+                    .unsupported
+                    .iter()
+                    .any(|other| other.eq_ignore_ascii_case(name));
+                if unsupported || self.index(name).is_some() {
+                    continue;
+                }
+
+                match missing
+                    .iter_mut()
+                    .find(|missing| missing.name.eq_ignore_ascii_case(name))
+                {
+                    Some(missing) => missing.required_by.push(plugin.plugin_name.clone()),
+                    None => missing.push(MissingDependency {
+                        name: name.clone(),
+                        required_by: vec![plugin.plugin_name.clone()],
+                    }),
+                }
+            }
+        }
+
+        missing.sort_by_key(|missing| missing.name.to_lowercase());
+        missing
+    }
+
+    /// The order that the server loads the plugins in: every plugin is loaded after the plugins that it (soft) depends on,
+    /// and before the plugins in its `loadbefore` list. Plugins that could be loaded at the same point are ordered by name.
+    ///
+    /// Like the server, soft dependencies are ignored to break cycles. Cycles of hard dependencies can't be broken,
+    /// and return a [`DependencyCycleError`] with the plugins in (or after) the cycle.
+    #[inline]
+    pub fn load_order(&self) -> Result<Vec<&GraphPlugin>, DependencyCycleError> {
+        // the plugins that must be loaded before each plugin, and whether that's because of a hard dependency
+        let mut before = vec![Vec::<(usize, bool)>::new(); self.plugins.len()];
+        for (index, plugin) in self.plugins.iter().enumerate() {
+            let loadbefore = |name: &str| {
+                let mut loadbefore = plugin.descriptor.loadbefore.iter();
+                loadbefore.any(|other| other.eq_ignore_ascii_case(name))
+            };
+
+            for (kind, name) in relations(&plugin.descriptor) {
+                let Some(other) = self.index(name).filter(|&other| other != index) else {
+                    continue;
+                };
+
+                match kind {
+                    // a (Paper) dependency that is loaded after the plugin only has to be installed
+                    DependencyKind::Depend | DependencyKind::SoftDepend if loadbefore(name) => {}
+                    DependencyKind::Depend => before[index].push((other, true)),
+                    DependencyKind::SoftDepend => before[index].push((other, false)),
+                    DependencyKind::LoadBefore => before[other].push((index, false)),
+                }
+            }
+        }
+
+        let mut loaded = vec![false; self.plugins.len()];
+        let mut order = Vec::with_capacity(self.plugins.len());
+
+        while order.len() < self.plugins.len() {
+            let pending = (0..self.plugins.len())
+                .filter(|&index| !loaded[index])
+                .collect::<Vec<_>>();
+
+            let ready = |soft: bool| {
+                pending.iter().copied().find(|&index| {
+                    before[index]
+                        .iter()
+                        .all(|&(other, hard)| loaded[other] || (soft && !hard))
+                })
+            };
+
+            // the plugins are sorted by name, so the first ready plugin is the first by name
+            let Some(next) = ready(false).or_else(|| ready(true)) else {
+                return Err(DependencyCycleError(
+                    pending
+                        .into_iter()
+                        .map(|index| self.plugins[index].plugin_name.clone())
+                        .collect(),
+                ));
+            };
+
+            loaded[next] = true;
+            order.push(&self.plugins[next]);
+        }
+
+        Ok(order)
+    }
+
+    /// The index of the plugin with the given name in the manifest or in its plugin descriptor, ignoring case.
+    #[inline]
+    fn index(&self, name: &str) -> Option<usize> {
+        self.plugins.iter().position(|plugin| {
+            plugin.descriptor.name.eq_ignore_ascii_case(name)
+                || plugin.plugin_name.eq_ignore_ascii_case(name)
+        })
+    }
+}
+
+/// The names of the plugins that a plugin descriptor refers to, with how it relates to them.
+#[inline]
+fn relations(descriptor: &PluginDescriptor) -> impl Iterator<Item = (DependencyKind, &str)> {
+    let depend = descriptor
+        .depend
+        .iter()
+        .map(|name| (DependencyKind::Depend, name.as_str()));
+    let softdepend = descriptor
+        .softdepend
+        .iter()
+        .map(|name| (DependencyKind::SoftDepend, name.as_str()));
+    let loadbefore = descriptor
+        .loadbefore
+        .iter()
+        .map(|name| (DependencyKind::LoadBefore, name.as_str()));
+
+    depend.chain(softdepend).chain(loadbefore)
+}
+
+/// Get a plugin in the manifest with the plugin descriptor of the version that it resolves to.
+/// Returns [`None`] (with a warning) if the plugin source isn't supported yet.
+#[inline]
+async fn graph_plugin(
+    session: &IoSession,
+    manifest: &Manifest,
+    plugin_name: &str,
+) -> miette::Result<Option<GraphPlugin>> {
+    let PluginDownloadSpec::Spiget(spiget) = manifest.plugin(plugin_name)? else {
+        log::warn!(
+            "Skipping plugin '{plugin_name}': {}",
+            UnsupportedApiError(PluginApiType::Hangar)
+        );
+        return Ok(None);
+    };
+
+    let plugin = SpigetPlugin::from_manifest(session, manifest, plugin_name, spiget)
+        .await
+        .wrap_err_with(|| format!("Error getting plugin '{plugin_name}'"))?;
+    plugin.check_downloadable(manifest, plugin_name)?;
+
+    let version_spec = spiget.pinned_version();
+    let Some(version) = plugin
+        .resolve_version(plugin_name, &version_spec, &manifest.server_target())
+        .await?
+    else {
+        return Err(plugin.version_not_found(manifest, plugin_name, &version_spec));
+    };
+
+    let metadata = session
+        .plugin_metadata(DownloadSpec {
+            plugin_name,
+            version: &version,
+            api_type: PluginApiType::Spiget,
+        })
+        .await
+        .wrap_err_with(|| format!("Error reading the plugin descriptor of '{plugin_name}'"))?;
+
+    Ok(Some(GraphPlugin {
+        plugin_name: plugin_name.to_string(),
+        descriptor: metadata.descriptor,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jar::DescriptorKind;
+
+    fn plugin(
+        name: &str,
+        depend: &[&str],
+        softdepend: &[&str],
+        loadbefore: &[&str],
+    ) -> GraphPlugin {
+        let names = |names: &[&str]| names.iter().map(ToString::to_string).collect();
+
+        GraphPlugin {
+            plugin_name: name.to_lowercase(),
+            descriptor: PluginDescriptor {
+                kind: DescriptorKind::Bukkit,
+                other_kinds: vec![],
+                name: name.to_string(),
+                version: "1.0".to_string(),
+                main: None,
+                api_version: None,
+                authors: vec![],
+                website: None,
+                depend: names(depend),
+                softdepend: names(softdepend),
+                loadbefore: names(loadbefore),
+            },
+        }
+    }
+
+    #[test]
+    fn test_load_order() {
+        let graph = DependencyGraph::new([
+            plugin("Shop", &["Vault"], &["WorldGuard"], &[]),
+            plugin("Vault", &[], &[], &[]),
+            plugin("WorldGuard", &["WorldEdit", "ProtocolLib"], &[], &[]),
+            plugin("WorldEdit", &[], &[], &[]),
+            plugin("Essentials", &["Vault"], &[], &[]),
+            plugin("Chat", &[], &[], &["Essentials"]),
+            // soft dependency cycle, broken like on the server
+            plugin("A", &[], &["B"], &[]),
+            plugin("B", &[], &["A"], &[]),
+        ]);
+
+        let order = graph
+            .load_order()
+            .unwrap()
+            .into_iter()
+            .map(|plugin| plugin.plugin_name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            order,
+            [
+                "chat",
+                "vault",
+                "essentials",
+                "worldedit",
+                "worldguard",
+                "shop",
+                "a",
+                "b"
+            ]
+        );
+
+        let missing = graph.missing_dependencies();
+        assert_eq!(missing.len(), 1);
+        assert_eq!(missing[0].name, "ProtocolLib");
+        assert_eq!(missing[0].required_by, ["worldguard"]);
+
+        // a plugin from a source that isn't supported may be the dependency
+        let skipped = DependencyGraph {
+            unsupported: vec!["protocollib".to_string()],
+            ..graph.clone()
+        };
+        assert!(skipped.missing_dependencies().is_empty());
+
+        // a required dependency that is loaded after the plugin
+        let after = DependencyGraph::new([
+            plugin("Addon", &["Core"], &[], &["Core"]),
+            plugin("Core", &[], &[], &[]),
+        ]);
+        let order = after.load_order().unwrap();
+        assert_eq!(order[0].plugin_name, "addon");
+        assert!(after.missing_dependencies().is_empty());
+
+        let cycle = DependencyGraph::new([
+            plugin("A", &["B"], &[], &[]),
+            plugin("B", &["A"], &[], &[]),
+            plugin("C", &[], &[], &[]),
+        ]);
+        assert_eq!(cycle.load_order().unwrap_err().0, ["a", "b"]);
+    }
+}
@@ -1,10 +1,14 @@
 use std::error::Error;
 
 use derive_new::new;
-use miette::{SourceOffset, SourceSpan};
+use miette::{Diagnostic, SourceOffset, SourceSpan};
 use rq::StatusCode;
 
+use crate::adapter::spiget::IncompatibleVersionsError;
 use crate::adapter::VersionSpec;
+use crate::caching::CacheError;
+use crate::cli::{CheckError, PluginNotFoundError};
+use crate::config::ConfigError;
 
 macro_rules! simple_error {
     ($name:ident, $message:literal) => {
@@ -17,7 +21,7 @@ macro_rules! simple_error {
 /// Error parsing data (like TOML or JSON).
 #[derive(thiserror::Error, miette::Diagnostic, Debug)]
 #[error("Error parsing provided data")]
-#[diagnostic(severity(Error))]
+#[diagnostic(code(pluginstall::parse), severity(Error))]
 pub struct ParseError {
     #[source]
     cause: ParseErrorCause,
@@ -35,7 +39,7 @@ impl ParseError {
 
         Self {
             location: SourceOffset::from_location(&input, error.line(), error.column()).into(),
-            cause: ParseErrorCause::JsonError(error),
+            cause: ParseErrorCause::Json(error),
             input,
         }
     }
@@ -50,7 +54,21 @@ impl ParseError {
 
         Self {
             location: SourceSpan::new(SourceOffset::from(span.start), length),
-            cause: ParseErrorCause::TomlError(error),
+            cause: ParseErrorCause::Toml(Box::new(error)),
+            input,
+        }
+    }
+
+    /// Create a YAML parse error for the given YAML input.
+    #[inline]
+    pub fn yaml(error: serde_yaml::Error, input: impl Into<String>) -> Self {
+        let input: String = input.into();
+
+        let offset = error.location().map_or(0, |location| location.index());
+
+        Self {
+            location: SourceOffset::from(offset).into(),
+            cause: ParseErrorCause::Yaml(error),
             input,
         }
     }
@@ -61,45 +79,345 @@ impl ParseError {
 pub enum ParseErrorCause {
     /// Error parsing JSON
     #[error("JSON error: {0}")]
-    JsonError(serde_json::Error),
-    /// Error parsing TOML
+    Json(serde_json::Error),
+    /// Error parsing TOML (boxed, because TOML errors are much larger than the other causes)
     #[error("TOML error: {0}")]
-    TomlError(toml::de::Error),
+    Toml(Box<toml::de::Error>),
+    /// Error parsing YAML
+    #[error("YAML error: {0}")]
+    Yaml(serde_yaml::Error),
 }
 
 /// Error with finding a plugin or version specified in the CLI invocation.
 #[derive(thiserror::Error, miette::Diagnostic, Debug)]
 pub enum NotFoundError {
-    #[error("Plugin was not found in the manifest.")]
-    PluginInManifest,
     #[error("Could not find plugin in API.")]
     PluginInApi,
     #[error("Could not find this version of the plugin.")]
     Version,
 }
 
+/// Error for a version of a plugin that could not be found, with suggestions for similar versions.
+/// Caused by a [`NotFoundError::Version`], so that the kind of the error is preserved.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("Could not find version '{version_spec}' for plugin '{plugin_name}'")]
+pub struct VersionNotFoundError {
+    pub plugin_name: String,
+    pub version_spec: VersionSpec,
+    #[help]
+    help: Option<String>,
+    #[source]
+    source: NotFoundError,
+}
+
+/// Error for when data that is needed in offline mode could not be found in the local caches.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[diagnostic(code(pluginstall::not_cached))]
+pub enum NotCachedError {
+    /// An API response was not present in the HTTP cache.
+    #[error("No cached response for '{0}', and the network cannot be used in offline mode.")]
+    #[diagnostic(help(
+        "Run this command without '--offline' on a machine with network access to populate the cache."
+    ))]
+    Response(rq::Url),
+    /// A plugin file was not present in the download cache.
+    #[error("Version '{version_name}' (ID {version_identifier}) of plugin '{plugin_name}' is not in the download cache, and cannot be downloaded in offline mode.")]
+    #[diagnostic(help(
+        "Download this version without '--offline' on a machine with network access to populate the cache."
+    ))]
+    PluginFile {
+        plugin_name: String,
+        version_name: String,
+        version_identifier: String,
+    },
+}
+
 /// An error for when a response has an unexpected status code.
 #[derive(thiserror::Error, miette::Diagnostic, Debug)]
 #[error("Unexpected response status: {0}")]
 pub struct UnexpectedHttpStatus(pub StatusCode);
 
+/// An error for when a request could not be sent, or no response was received (like when the network is down).
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("Request to '{url}' failed")]
+pub struct RequestError {
+    pub url: rq::Url,
+    #[source]
+    pub source: reqwest_middleware::Error,
+}
+
+/// The kind of an error, so that scripts can tell errors apart. Every kind has its own exit code.
+#[derive(serde::Serialize, Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum ErrorKind {
+    /// Any error that doesn't have a more specific kind.
+    #[default]
+    Other,
+    /// The plugin was not found in the manifest.
+    PluginNotInManifest,
+    /// The plugin was not found in the API (it may have been removed).
+    PluginNotInApi,
+    /// The version of the plugin was not found.
+    VersionNotFound,
+    /// An API responded with an unexpected HTTP status.
+    HttpStatus,
+    /// A request could not be sent or no response was received.
+    Network,
+    /// Data needed in offline mode is not in the local caches.
+    NotCached,
+    /// Some data (like the manifest, the config or the cache index) could not be parsed.
+    Parse,
+    /// The config is invalid.
+    Config,
+    /// Error with the download cache.
+    Cache,
+    /// Checking the manifest found problems with its entries.
+    ManifestCheck,
+}
+
+impl ErrorKind {
+    /// The exit code of the process when it fails with an error of this kind.
+    /// These are stable, so that scripts can rely on them. Exit code 2 is used by clap for invalid arguments.
+    #[inline]
+    pub fn exit_code(self) -> u8 {
+        match self {
+            Self::Other => 1,
+            Self::PluginNotInManifest => 3,
+            Self::PluginNotInApi => 4,
+            Self::VersionNotFound => 5,
+            Self::HttpStatus => 6,
+            Self::Network => 7,
+            Self::NotCached => 8,
+            Self::Parse => 9,
+            Self::Config => 10,
+            Self::Cache => 11,
+            Self::ManifestCheck => 12,
+        }
+    }
+}
+
+/// The location of a parse error in the parsed input. Lines and columns start at 1.
+#[derive(serde::Serialize, Copy, Clone, Debug)]
+pub struct ErrorSpan {
+    /// The byte offset of the span in the input.
+    pub offset: usize,
+    /// The length of the span in bytes.
+    pub length: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl ErrorSpan {
+    /// Locate the span with the given offset and length in the input.
+    #[inline]
+    fn locate(input: &str, offset: usize, length: usize) -> Self {
+        let offset = offset.min(input.len());
+        let before = &input[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+
+        Self {
+            offset,
+            length,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+
+    /// Locate the first label of a diagnostic in its source code.
+    #[inline]
+    fn from_label(diagnostic: &dyn Diagnostic) -> Option<Self> {
+        let label = diagnostic.labels()?.next()?;
+        let before = SourceSpan::new(0.into(), label.offset());
+        let contents = diagnostic.source_code()?.read_span(&before, 0, 0).ok()?;
+        let input = std::str::from_utf8(contents.data()).ok()?;
+
+        Some(Self::locate(input, label.offset(), label.len()))
+    }
+}
+
+impl ParseError {
+    /// Get the location of the error in the input.
+    #[inline]
+    pub fn span(&self) -> ErrorSpan {
+        ErrorSpan::locate(&self.input, self.location.offset(), self.location.len())
+    }
+}
+
+/// A machine readable report of an error, written when JSON output is enabled.
+#[derive(serde::Serialize, Debug)]
+pub struct ErrorReport {
+    pub kind: ErrorKind,
+    /// The message of the outermost error.
+    pub message: String,
+    /// The messages of the errors that caused this error, from the outermost to the innermost.
+    pub causes: Vec<String>,
+    pub exit_code: u8,
+    /// The HTTP status, if the error is an unexpected HTTP status.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_status: Option<u16>,
+    /// The location of the error, if the error is a parse error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub span: Option<ErrorSpan>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub help: Option<String>,
+    /// Reports of the related errors, like every problem found when checking the manifest.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub related: Vec<ErrorReport>,
+}
+
+impl ErrorReport {
+    /// Create a report of the given error. The kind of the error is the kind of the innermost error in the chain that has a specific kind,
+    /// since inner errors are more specific (like a parse error in the config).
+    #[inline]
+    pub fn new(report: &miette::Report) -> Self {
+        Self::from_diagnostic(report.as_ref())
+    }
+
+    /// Create a report of the given diagnostic, like [`ErrorReport::new`].
+    #[inline]
+    pub fn from_diagnostic(diagnostic: &(dyn Diagnostic + 'static)) -> Self {
+        let chain = || {
+            let error: &(dyn Error + 'static) = diagnostic;
+            std::iter::successors(Some(error), |&error| error.source())
+        };
+        let diagnostic_chain =
+            std::iter::successors(Some(diagnostic as &dyn Diagnostic), |&diagnostic| {
+                diagnostic.diagnostic_source()
+            });
+
+        // errors with codes are leaves that are often wrapped transparently, so they are more specific than any error in the chain
+        let (kind, http_status, span) = diagnostic_chain
+            .filter_map(classify_code)
+            .last()
+            .or_else(|| chain().filter_map(classify_error).last())
+            .unwrap_or_default();
+
+        let mut causes: Vec<String> = Vec::new();
+        for cause in chain().skip(1).map(ToString::to_string) {
+            // transparent errors repeat the message of the error they wrap
+            if causes.last() != Some(&cause) {
+                causes.push(cause);
+            }
+        }
+
+        Self {
+            kind,
+            message: diagnostic.to_string(),
+            causes,
+            exit_code: kind.exit_code(),
+            http_status,
+            span,
+            help: diagnostic.help().map(|help| help.to_string()),
+            related: chain()
+                .find_map(|error| error.downcast_ref::<CheckError>())
+                .map(|error| {
+                    let problems = error.problems.iter();
+                    problems
+                        .map(|problem| Self::from_diagnostic(problem))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Get the kind of an error (and the HTTP status or span, if relevant) from its code. Returns [`None`] if the code doesn't belong to an error with a specific kind.
+///
+/// Transparent errors (and errors with context) hide the error they wrap from the chain of sources,
+/// but forward its code, so errors that are wrapped transparently are classified by their code.
+#[inline]
+fn classify_code(
+    diagnostic: &dyn Diagnostic,
+) -> Option<(ErrorKind, Option<u16>, Option<ErrorSpan>)> {
+    match diagnostic.code()?.to_string().as_str() {
+        "pluginstall::not_cached" => Some((ErrorKind::NotCached, None, None)),
+        "pluginstall::parse" => Some((ErrorKind::Parse, None, ErrorSpan::from_label(diagnostic))),
+        _ => None,
+    }
+}
+
+/// Get the kind of an error (and the HTTP status or span, if relevant). Returns [`None`] if the error doesn't have a specific kind.
+#[inline]
+fn classify_error(
+    error: &(dyn Error + 'static),
+) -> Option<(ErrorKind, Option<u16>, Option<ErrorSpan>)> {
+    let kind = |kind| Some((kind, None, None));
+
+    if let Some(error) = error.downcast_ref::<NotFoundError>() {
+        return kind(match error {
+            NotFoundError::PluginInApi => ErrorKind::PluginNotInApi,
+            NotFoundError::Version => ErrorKind::VersionNotFound,
+        });
+    }
+
+    if error.is::<IncompatibleVersionsError>() {
+        return kind(ErrorKind::VersionNotFound);
+    }
+
+    if let Some(UnexpectedHttpStatus(status)) = error.downcast_ref() {
+        return Some((ErrorKind::HttpStatus, Some(status.as_u16()), None));
+    }
+
+    if let Some(error) = error.downcast_ref::<ParseError>() {
+        return Some((ErrorKind::Parse, None, Some(error.span())));
+    }
+
+    if error.is::<ConfigError>() {
+        return kind(ErrorKind::Config);
+    }
+
+    if error.is::<CacheError>() {
+        return kind(ErrorKind::Cache);
+    }
+
+    if error.is::<CheckError>() {
+        return kind(ErrorKind::ManifestCheck);
+    }
+
+    if error.is::<PluginNotFoundError>() {
+        return kind(ErrorKind::PluginNotInManifest);
+    }
+
+    if error.is::<NotCachedError>() {
+        return kind(ErrorKind::NotCached);
+    }
+
+    if error.is::<RequestError>()
+        || error.is::<reqwest_middleware::Error>()
+        || error.is::<rq::Error>()
+    {
+        return kind(ErrorKind::Network);
+    }
+
+    None
+}
+
 /// Helpers for easily creating diagnostics.
 pub mod diagnostics {
+    use std::fmt::Display;
     use std::path::Path;
 
-    use miette::{diagnostic, MietteDiagnostic};
+    use miette::{diagnostic, MietteDiagnostic, Report};
     use rq::header::{CACHE_CONTROL, CONTENT_DISPOSITION};
 
+    use super::{NotFoundError, VersionNotFoundError};
     use crate::adapter::VersionSpec;
+    use crate::util::did_you_mean;
 
-    /// A "version not found" diagnostic.
+    /// A "version not found" error (a [`VersionNotFoundError`]).
+    /// The given suggestions (like similar version names) are listed in the help of the error.
     #[inline]
     pub fn version_not_found(
         manifest_name: impl Into<String>,
         version_spec: &VersionSpec,
-    ) -> MietteDiagnostic {
-        let manifest_name: String = manifest_name.into();
-        diagnostic!("Could not find version '{version_spec}' for plugin '{manifest_name}'")
+        suggestions: &[impl Display],
+    ) -> Report {
+        Report::new(VersionNotFoundError {
+            plugin_name: manifest_name.into(),
+            version_spec: version_spec.clone(),
+            help: did_you_mean(suggestions),
+            source: NotFoundError::Version,
+        })
     }
 
     /// An "invalid download directory" diagnostic. Usually emitted when trying to download into a directory that doesn't exist.
@@ -129,3 +447,43 @@ pub mod diagnostics {
         diagnostic!("Error parsing the '{CACHE_CONTROL}' header in response.")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use miette::Context;
+
+    use super::*;
+    use crate::manifest::ManifestError;
+    use crate::session::DownloadError;
+
+    #[test]
+    fn test_classify_not_cached_download() {
+        let error = DownloadError::NotCached(NotCachedError::PluginFile {
+            plugin_name: "gsit".to_string(),
+            version_name: "1.0.0".to_string(),
+            version_identifier: "1".to_string(),
+        });
+        let report = Err::<(), _>(error)
+            .wrap_err("Error downloading plugin 'gsit'")
+            .unwrap_err();
+
+        let report = ErrorReport::new(&report);
+        assert_eq!(report.kind, ErrorKind::NotCached);
+        assert_eq!(report.exit_code, ErrorKind::NotCached.exit_code());
+    }
+
+    #[test]
+    fn test_classify_transparent_parse_error() {
+        let input = "[meta]\nmanifest_name = \"test\"\nbad = ";
+        let error = toml::from_str::<toml::Table>(input).unwrap_err();
+        let error = ManifestError::Parse(ParseError::toml(error, input));
+        let report = Err::<(), _>(error)
+            .wrap_err("Error loading the manifest")
+            .unwrap_err();
+
+        let report = ErrorReport::new(&report);
+        assert_eq!(report.kind, ErrorKind::Parse);
+        let span = report.span.expect("parse errors have a span");
+        assert_eq!(span.line, 3);
+    }
+}
@@ -0,0 +1,618 @@
+//! Reading plugin jars, like the plugin descriptor (`plugin.yml`) that describes the plugin in a jar.
+
+use std::collections::HashMap;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
+
+use zip::result::ZipError;
+use zip::ZipArchive;
+
+use crate::error::ParseError;
+
+/// The magic number at the start of every Java class file.
+const CLASS_FILE_MAGIC: [u8; 4] = [0xCA, 0xFE, 0xBA, 0xBE];
+
+/// The difference between the major version of a class file and the Java version it's for. Every Java release since Java 1.2
+/// bumps the major version by one (Java 8 is 52). Java 1.0 and 1.1 both have major version 45, so they come out as Java 1.
+const CLASS_FILE_MAJOR_VERSION_OFFSET: u16 = 44;
+
+/// The kinds of plugin descriptors that are read from plugin jars, in order of preference.
+/// Jars can have more than one (like Paper plugins that also have a `plugin.yml`), in which case the first one is read,
+/// and the kinds of the others are recorded in [`PluginDescriptor::other_kinds`].
+pub static DESCRIPTOR_KINDS: [DescriptorKind; 4] = [
+    DescriptorKind::Paper,
+    DescriptorKind::Bukkit,
+    DescriptorKind::Bungee,
+    DescriptorKind::Velocity,
+];
+
+/// The kind of a plugin descriptor, which depends on the platform that the plugin is for.
+#[derive(serde::Serialize, serde::Deserialize, Copy, Clone, Debug, PartialEq, Eq, dm::Display)]
+#[serde(rename_all = "kebab-case")]
+pub enum DescriptorKind {
+    /// A `paper-plugin.yml` for Paper plugins.
+    #[display("Paper")]
+    Paper,
+    /// A `plugin.yml` for Bukkit (and Spigot) plugins.
+    #[display("Bukkit")]
+    Bukkit,
+    /// A `bungee.yml` for BungeeCord plugins.
+    #[display("BungeeCord")]
+    Bungee,
+    /// A `velocity-plugin.json` for Velocity plugins.
+    #[display("Velocity")]
+    Velocity,
+}
+
+impl DescriptorKind {
+    /// The name of the descriptor file in the jar.
+    #[inline]
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::Paper => "paper-plugin.yml",
+            Self::Bukkit => "plugin.yml",
+            Self::Bungee => "bungee.yml",
+            Self::Velocity => "velocity-plugin.json",
+        }
+    }
+}
+
+/// The details of a plugin from the plugin descriptor in its jar.
+/// The descriptors of every platform are read into this, with the dependencies in the Bukkit terms.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct PluginDescriptor {
+    /// The kind of descriptor that these details were read from.
+    pub kind: DescriptorKind,
+    /// The kinds of the other descriptors in the jar, which weren't read. A jar with descriptors for
+    /// several platforms can be loaded by any of them.
+    pub other_kinds: Vec<DescriptorKind>,
+    /// The name of the plugin, as it's known to the server.
+    pub name: String,
+    /// The version of the plugin.
+    pub version: String,
+    /// The fully qualified name of the main class of the plugin.
+    pub main: Option<String>,
+    /// The server API version that the plugin is made for (only for Bukkit and Paper plugins).
+    pub api_version: Option<String>,
+    /// The authors of the plugin.
+    pub authors: Vec<String>,
+    /// The website of the plugin, if the descriptor has one.
+    pub website: Option<String>,
+    /// The plugins that this plugin needs to run.
+    pub depend: Vec<String>,
+    /// The plugins that this plugin can use, but doesn't need.
+    pub softdepend: Vec<String>,
+    /// The plugins that should be loaded after this plugin.
+    pub loadbefore: Vec<String>,
+}
+
+/// The metadata of a plugin jar.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct JarMetadata {
+    #[serde(flatten)]
+    pub descriptor: PluginDescriptor,
+    /// The Java version needed to run the plugin, from the newest class file version in the jar.
+    /// [`None`] if the jar has no class files.
+    pub java_version: Option<u16>,
+}
+
+/// Model for a `plugin.yml` plugin descriptor.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct BukkitDescriptorYaml {
+    name: String,
+    version: String,
+    main: Option<String>,
+    api_version: Option<String>,
+    author: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    website: Option<String>,
+    #[serde(default)]
+    depend: Vec<String>,
+    #[serde(default)]
+    softdepend: Vec<String>,
+    #[serde(default)]
+    loadbefore: Vec<String>,
+}
+
+/// Model for a `paper-plugin.yml` plugin descriptor.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct PaperDescriptorYaml {
+    name: String,
+    version: String,
+    main: Option<String>,
+    api_version: Option<String>,
+    author: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    website: Option<String>,
+    #[serde(default)]
+    dependencies: PaperDependenciesYaml,
+    /// Only used by the legacy format of the dependencies.
+    #[serde(default)]
+    load_before: Vec<PaperLegacyLoadOrderYaml>,
+}
+
+/// The dependencies in a `paper-plugin.yml`. Older versions of Paper had a list of dependencies,
+/// newer versions have maps of the dependencies that are needed when the plugin is bootstrapped and when the server loads it.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(untagged)]
+enum PaperDependenciesYaml {
+    Legacy(Vec<PaperLegacyDependencyYaml>),
+    Current {
+        #[serde(default)]
+        server: HashMap<String, PaperDependencyYaml>,
+    },
+}
+
+impl Default for PaperDependenciesYaml {
+    #[inline]
+    fn default() -> Self {
+        Self::Legacy(Vec::new())
+    }
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "kebab-case")]
+struct PaperDependencyYaml {
+    #[serde(default)]
+    load: PaperLoadOrder,
+    #[serde(default = "default_true")]
+    required: bool,
+}
+
+/// When a dependency is loaded relative to the plugin that depends on it.
+#[derive(serde::Deserialize, Copy, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+enum PaperLoadOrder {
+    /// The dependency is loaded before the plugin.
+    Before,
+    /// The dependency is loaded after the plugin.
+    After,
+    #[default]
+    Omit,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+struct PaperLegacyDependencyYaml {
+    name: String,
+    #[serde(default = "default_true")]
+    required: bool,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+struct PaperLegacyLoadOrderYaml {
+    name: String,
+}
+
+/// Model for a `bungee.yml` plugin descriptor.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct BungeeDescriptorYaml {
+    name: String,
+    version: String,
+    main: Option<String>,
+    author: Option<String>,
+    #[serde(default)]
+    depends: Vec<String>,
+    #[serde(default)]
+    soft_depends: Vec<String>,
+}
+
+/// Model for a `velocity-plugin.json` plugin descriptor.
+#[derive(serde::Deserialize, Clone, Debug)]
+struct VelocityDescriptorJson {
+    id: String,
+    name: Option<String>,
+    #[serde(default)]
+    version: String,
+    main: Option<String>,
+    url: Option<String>,
+    #[serde(default)]
+    authors: Vec<String>,
+    #[serde(default)]
+    dependencies: Vec<VelocityDependencyJson>,
+}
+
+#[derive(serde::Deserialize, Clone, Debug)]
+struct VelocityDependencyJson {
+    id: String,
+    #[serde(default)]
+    optional: bool,
+}
+
+#[inline]
+fn default_true() -> bool {
+    true
+}
+
+impl From<BukkitDescriptorYaml> for PluginDescriptor {
+    #[inline]
+    fn from(yaml: BukkitDescriptorYaml) -> Self {
+        Self {
+            kind: DescriptorKind::Bukkit,
+            other_kinds: Vec::new(),
+            name: yaml.name,
+            version: yaml.version,
+            main: yaml.main,
+            api_version: yaml.api_version,
+            authors: yaml.author.into_iter().chain(yaml.authors).collect(),
+            website: yaml.website,
+            depend: yaml.depend,
+            softdepend: yaml.softdepend,
+            loadbefore: yaml.loadbefore,
+        }
+    }
+}
+
+impl From<PaperDescriptorYaml> for PluginDescriptor {
+    #[inline]
+    fn from(yaml: PaperDescriptorYaml) -> Self {
+        let mut depend = Vec::new();
+        let mut softdepend = Vec::new();
+        let mut loadbefore = yaml
+            .load_before
+            .into_iter()
+            .map(|plugin| plugin.name)
+            .collect::<Vec<_>>();
+
+        match yaml.dependencies {
+            PaperDependenciesYaml::Legacy(dependencies) => {
+                for dependency in dependencies {
+                    match dependency.required {
+                        true => depend.push(dependency.name),
+                        false => softdepend.push(dependency.name),
+                    }
+                }
+            }
+            PaperDependenciesYaml::Current { server } => {
+                let mut server = server.into_iter().collect::<Vec<_>>();
+                server.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+                for (name, dependency) in server {
+                    // a dependency that is loaded after the plugin is still needed if it's required
+                    if dependency.load == PaperLoadOrder::After {
+                        loadbefore.push(name.clone());
+                    }
+                    match dependency.required {
+                        true => depend.push(name),
+                        false => softdepend.push(name),
+                    }
+                }
+            }
+        }
+
+        Self {
+            kind: DescriptorKind::Paper,
+            other_kinds: Vec::new(),
+            name: yaml.name,
+            version: yaml.version,
+            main: yaml.main,
+            api_version: yaml.api_version,
+            authors: yaml.author.into_iter().chain(yaml.authors).collect(),
+            website: yaml.website,
+            depend,
+            softdepend,
+            loadbefore,
+        }
+    }
+}
+
+impl From<BungeeDescriptorYaml> for PluginDescriptor {
+    #[inline]
+    fn from(yaml: BungeeDescriptorYaml) -> Self {
+        Self {
+            kind: DescriptorKind::Bungee,
+            other_kinds: Vec::new(),
+            name: yaml.name,
+            version: yaml.version,
+            main: yaml.main,
+            api_version: None,
+            authors: yaml.author.into_iter().collect(),
+            website: None,
+            depend: yaml.depends,
+            softdepend: yaml.soft_depends,
+            loadbefore: Vec::new(),
+        }
+    }
+}
+
+impl From<VelocityDescriptorJson> for PluginDescriptor {
+    #[inline]
+    fn from(json: VelocityDescriptorJson) -> Self {
+        let (softdepend, depend) = json
+            .dependencies
+            .into_iter()
+            .partition::<Vec<_>, _>(|dependency| dependency.optional);
+
+        Self {
+            kind: DescriptorKind::Velocity,
+            other_kinds: Vec::new(),
+            // other plugins depend on the ID, the name is only for display
+            name: json.name.unwrap_or_else(|| json.id.clone()),
+            version: json.version,
+            main: json.main,
+            api_version: None,
+            authors: json.authors,
+            website: json.url,
+            depend: depend.into_iter().map(|dependency| dependency.id).collect(),
+            softdepend: softdepend
+                .into_iter()
+                .map(|dependency| dependency.id)
+                .collect(),
+            loadbefore: Vec::new(),
+        }
+    }
+}
+
+impl PluginDescriptor {
+    /// The kinds of every descriptor in the jar, starting with the one that was read.
+    #[inline]
+    pub fn kinds(&self) -> impl Iterator<Item = DescriptorKind> + '_ {
+        std::iter::once(self.kind).chain(self.other_kinds.iter().copied())
+    }
+
+    /// Parse a plugin descriptor of the given kind.
+    #[inline]
+    fn parse(kind: DescriptorKind, input: String) -> JarResult<Self> {
+        let yaml = |error| JarError::Parse(kind.file_name(), ParseError::yaml(error, &input));
+        let json = |error| JarError::Parse(kind.file_name(), ParseError::json(error, &input));
+
+        Ok(match kind {
+            DescriptorKind::Paper => serde_yaml::from_str::<PaperDescriptorYaml>(&input)
+                .map_err(yaml)?
+                .into(),
+            DescriptorKind::Bukkit => serde_yaml::from_str::<BukkitDescriptorYaml>(&input)
+                .map_err(yaml)?
+                .into(),
+            DescriptorKind::Bungee => serde_yaml::from_str::<BungeeDescriptorYaml>(&input)
+                .map_err(yaml)?
+                .into(),
+            DescriptorKind::Velocity => serde_json::from_str::<VelocityDescriptorJson>(&input)
+                .map_err(json)?
+                .into(),
+        })
+    }
+}
+
+/// Error reading a plugin jar.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+pub enum JarError {
+    /// IO error, usually because the jar could not be opened.
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+
+    /// The file is not a valid jar (zip) file.
+    #[error("Error reading jar: {0}")]
+    Zip(#[from] ZipError),
+
+    /// The jar has no plugin descriptor of any of the [`DESCRIPTOR_KINDS`], so it's probably not a plugin.
+    #[error("The jar has no plugin descriptor (plugin.yml, paper-plugin.yml, bungee.yml or velocity-plugin.json)")]
+    NoDescriptor,
+
+    /// The plugin descriptor is not valid YAML (or JSON), or lacks required keys.
+    #[error("Error parsing plugin descriptor '{0}'")]
+    Parse(
+        &'static str,
+        #[source]
+        #[diagnostic_source]
+        ParseError,
+    ),
+}
+
+/// Type alias for the generic result type returned when reading jars.
+pub type JarResult<T> = Result<T, JarError>;
+
+/// The jars in the given plugins directory, sorted by path. Subdirectories (like the data directories of plugins) are not searched.
+#[inline]
+pub async fn plugin_jars(plugins_dir: &Path) -> std::io::Result<Vec<PathBuf>> {
+    let mut jars = Vec::new();
+    let mut entries = tokio::fs::read_dir(plugins_dir).await?;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        let is_jar = path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("jar"));
+
+        if is_jar && entry.file_type().await?.is_file() {
+            jars.push(path);
+        }
+    }
+
+    jars.sort();
+    Ok(jars)
+}
+
+/// Read the plugin descriptor of the jar at the given path.
+#[inline]
+pub async fn plugin_descriptor(path: impl AsRef<Path>) -> JarResult<PluginDescriptor> {
+    let path = path.as_ref().to_path_buf();
+
+    // zip archives can only be read synchronously
+    tokio::task::spawn_blocking(move || read_plugin_descriptor(std::fs::File::open(path)?))
+        .await
+        .expect("reading a jar doesn't panic")
+}
+
+/// Read the metadata of the jar at the given path.
+#[inline]
+pub async fn jar_metadata(path: impl AsRef<Path>) -> JarResult<JarMetadata> {
+    let path = path.as_ref().to_path_buf();
+
+    tokio::task::spawn_blocking(move || read_jar_metadata(std::fs::File::open(path)?))
+        .await
+        .expect("reading a jar doesn't panic")
+}
+
+/// Read the plugin descriptor of a jar from the given reader.
+#[inline]
+pub fn read_plugin_descriptor(jar: impl Read + Seek) -> JarResult<PluginDescriptor> {
+    archive_plugin_descriptor(&mut ZipArchive::new(jar)?)
+}
+
+/// Read the metadata of a jar from the given reader.
+#[inline]
+pub fn read_jar_metadata(jar: impl Read + Seek) -> JarResult<JarMetadata> {
+    let mut archive = ZipArchive::new(jar)?;
+
+    Ok(JarMetadata {
+        descriptor: archive_plugin_descriptor(&mut archive)?,
+        java_version: archive_java_version(&mut archive)?,
+    })
+}
+
+/// Read the plugin descriptor in a jar archive.
+#[inline]
+fn archive_plugin_descriptor<R: Read + Seek>(
+    archive: &mut ZipArchive<R>,
+) -> JarResult<PluginDescriptor> {
+    for kind in DESCRIPTOR_KINDS {
+        let mut file = match archive.by_name(kind.file_name()) {
+            Ok(file) => file,
+            Err(ZipError::FileNotFound) => continue,
+            Err(error) => return Err(error.into()),
+        };
+
+        // the size in the zip header isn't trusted for preallocating, since it can be anything
+        let mut input = String::new();
+        file.read_to_string(&mut input)?;
+
+        drop(file);
+
+        let mut descriptor = PluginDescriptor::parse(kind, input)?;
+        descriptor.other_kinds = DESCRIPTOR_KINDS
+            .into_iter()
+            .filter(|other| *other != kind && archive.index_for_name(other.file_name()).is_some())
+            .collect();
+
+        return Ok(descriptor);
+    }
+
+    Err(JarError::NoDescriptor)
+}
+
+/// The Java version needed to load every class in a jar archive, from the newest class file version.
+///
+/// Classes for newer Java versions in multi-release jars (in `META-INF/versions/`) are only loaded on those Java versions, so they're skipped.
+#[inline]
+fn archive_java_version<R: Read + Seek>(archive: &mut ZipArchive<R>) -> JarResult<Option<u16>> {
+    let mut newest = None;
+
+    for index in 0..archive.len() {
+        let mut file = archive.by_index(index)?;
+        if !file.name().ends_with(".class") || file.name().starts_with("META-INF/") {
+            continue;
+        }
+
+        // the magic number is followed by the minor and major version
+        let mut header = [0; 8];
+        if file.read_exact(&mut header).is_err() || header[..4] != CLASS_FILE_MAGIC {
+            continue;
+        }
+
+        let major_version = u16::from_be_bytes([header[6], header[7]]);
+        newest = newest.max(Some(major_version));
+    }
+
+    Ok(newest.map(|major_version| major_version.saturating_sub(CLASS_FILE_MAJOR_VERSION_OFFSET)))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::write::SimpleFileOptions;
+    use zip::ZipWriter;
+
+    use super::*;
+
+    /// Create a jar with the given files in memory.
+    fn jar(files: &[(&str, &[u8])]) -> Cursor<Vec<u8>> {
+        let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+        for (name, contents) in files {
+            writer
+                .start_file(*name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents).unwrap();
+        }
+
+        let mut jar = writer.finish().unwrap();
+        jar.set_position(0);
+        jar
+    }
+
+    #[test]
+    fn test_read_plugin_descriptor() {
+        let descriptor = read_plugin_descriptor(jar(&[(
+            "plugin.yml",
+            b"name: GSit\nversion: 1.10\nmain: dev.geco.gsit.GSitMain\napi-version: 1.20\nauthor: Gecolay\nauthors: [Other]\nwebsite: https://example.com\ndepend: [ProtocolLib]\nsoftdepend: [PlaceholderAPI]\n",
+        )]))
+        .unwrap();
+
+        assert_eq!(descriptor.kind, DescriptorKind::Bukkit);
+        assert!(descriptor.other_kinds.is_empty());
+        assert_eq!(descriptor.name, "GSit");
+        assert_eq!(descriptor.version, "1.10");
+        assert_eq!(descriptor.main.as_deref(), Some("dev.geco.gsit.GSitMain"));
+        assert_eq!(descriptor.api_version.as_deref(), Some("1.20"));
+        assert_eq!(descriptor.authors, ["Gecolay", "Other"]);
+        assert_eq!(descriptor.website.as_deref(), Some("https://example.com"));
+        assert_eq!(descriptor.depend, ["ProtocolLib"]);
+        assert_eq!(descriptor.softdepend, ["PlaceholderAPI"]);
+
+        let descriptor = read_plugin_descriptor(jar(&[
+            ("plugin.yml", b"name: Old\nversion: '1'\n"),
+            (
+                "paper-plugin.yml",
+                b"name: New\nversion: '2'\ndependencies:\n  server:\n    Vault:\n      load: BEFORE\n    LuckPerms:\n      required: false\n    Chunky:\n      load: AFTER\n    Essentials:\n      load: AFTER\n      required: false\n",
+            ),
+        ]))
+        .unwrap();
+        assert_eq!(descriptor.kind, DescriptorKind::Paper);
+        assert_eq!(descriptor.other_kinds, [DescriptorKind::Bukkit]);
+        assert_eq!(descriptor.name, "New");
+        assert_eq!(descriptor.depend, ["Chunky", "Vault"]);
+        assert_eq!(descriptor.softdepend, ["Essentials", "LuckPerms"]);
+        assert_eq!(descriptor.loadbefore, ["Chunky", "Essentials"]);
+
+        let descriptor = read_plugin_descriptor(jar(&[(
+            "velocity-plugin.json",
+            br#"{"id":"proxy","name":"Proxy","version":"3.0","dependencies":[{"id":"luckperms","optional":true},{"id":"core"}]}"#,
+        )]))
+        .unwrap();
+        assert_eq!(descriptor.kind, DescriptorKind::Velocity);
+        assert_eq!(descriptor.depend, ["core"]);
+        assert_eq!(descriptor.softdepend, ["luckperms"]);
+
+        assert!(matches!(
+            read_plugin_descriptor(jar(&[("config.yml", b"a: b")])),
+            Err(JarError::NoDescriptor)
+        ));
+        assert!(matches!(
+            read_plugin_descriptor(jar(&[("plugin.yml", b"version: 1")])),
+            Err(JarError::Parse("plugin.yml", _))
+        ));
+    }
+
+    #[test]
+    fn test_read_jar_java_version() {
+        let class = |major: u8| [0xCA, 0xFE, 0xBA, 0xBE, 0, 0, 0, major];
+
+        let metadata = read_jar_metadata(jar(&[
+            ("bungee.yml", b"name: Proxy\nversion: 1\n"),
+            ("a/Main.class", &class(52)),
+            ("a/shaded/Lib.class", &class(61)),
+            ("META-INF/versions/21/a/Main.class", &class(65)),
+        ]))
+        .unwrap();
+
+        assert_eq!(metadata.descriptor.kind, DescriptorKind::Bungee);
+        assert_eq!(metadata.java_version, Some(17));
+
+        let metadata = read_jar_metadata(jar(&[("plugin.yml", b"name: A\nversion: 1\n")])).unwrap();
+        assert_eq!(metadata.java_version, None);
+    }
+}
@@ -4,21 +4,34 @@ extern crate reqwest as rq;
 use std::process::ExitCode;
 
 use crate::cli::Cli;
-use clap::Parser;
-use miette::IntoDiagnostic;
+use crate::config::Config;
+use clap::{ColorChoice, CommandFactory, Parser};
+use clap_complete::CompleteEnv;
+use error::ErrorReport;
+use miette::{Context, IntoDiagnostic, MietteHandlerOpts};
 use session::IoSession;
+use std::path::Path;
 
 mod adapter;
+mod bundle;
 mod caching;
 mod cli;
+mod compat;
+mod config;
+mod dependencies;
 mod error;
+mod jar;
 mod manifest;
 mod output;
 mod session;
 mod util;
+mod version;
 
-fn main() -> miette::Result<()> {
-    util::setup_logger();
+fn main() -> ExitCode {
+    // when the shell asks for completions, they're written and the process exits here
+    CompleteEnv::with_factory(Cli::command)
+        .var(cli::COMPLETE_ENV_VAR)
+        .complete();
 
     // start the async runtime and block
     let runtime = tokio::runtime::Builder::new_multi_thread()
@@ -30,17 +43,75 @@ fn main() -> miette::Result<()> {
 }
 
 /// The async entrypoint of the app. The main function will block here when the app is ran.
-async fn async_main() -> miette::Result<()> {
+///
+/// Errors are written to the CLI output (as JSON if JSON output is enabled), and the process exits with the exit code of the error's kind.
+async fn async_main() -> ExitCode {
     let cli = Cli::parse();
 
-    let manifest = cli.manifest().await.into_diagnostic()?;
+    // an invalid config is reported after the logger and the output have been set up with the defaults
+    let (config, config_error) = match Config::load().await {
+        Ok(config) => (config, None),
+        Err(err) => (Config::default(), Some(err)),
+    };
+
+    let color = cli.log_ctrl.color;
+    if color != ColorChoice::Auto {
+        let _ = miette::set_hook(Box::new(move |_| {
+            Box::new(
+                MietteHandlerOpts::new()
+                    .color(color == ColorChoice::Always)
+                    .build(),
+            )
+        }));
+    }
+
+    let log_file = cli.log_ctrl.log_file.as_deref();
+    let logger = util::setup_logger(cli.log_level(&config), color, log_file)
+        .into_diagnostic()
+        .wrap_err_with(|| {
+            format!(
+                "Error opening log file '{}'",
+                log_file.unwrap_or(Path::new("")).display()
+            )
+        });
+
+    let result = match (config_error, logger) {
+        (Some(err), _) => Err(err.into()),
+        (None, Err(err)) => Err(err),
+        (None, Ok(())) => run(&cli, &config).await,
+    };
+
+    let Err(report) = result else {
+        return ExitCode::SUCCESS;
+    };
+
+    // if the error can't even be written there's nothing left to report it with
+    let _ = cli.cli_output(&config).error(&report);
+
+    ExitCode::from(ErrorReport::new(&report).exit_code)
+}
+
+/// Run the command given to the CLI.
+async fn run(cli: &Cli, config: &Config) -> miette::Result<()> {
+    let cli_output = cli
+        .cli_output(config)
+        .with_template(cli.output_template().await?);
+
+    if let Some(result) = cli.command.run_standalone(&cli_output) {
+        return result;
+    }
+
+    let manifest = cli.manifest(config).await?;
 
-    let cli_output = cli.cli_output();
     let download_cache = cli
-        .download_cache(&manifest.meta.manifest_name)
-        .await
-        .into_diagnostic()?;
-    let session = IoSession::new(cli_output, download_cache);
+        .download_cache(config, &manifest.meta.manifest_name)
+        .await?;
+    let session = IoSession::new(
+        cli_output,
+        download_cache,
+        cli.cache_settings(),
+        cli.network_settings(config),
+    );
 
     cli.command.run(&session, &manifest).await
 }
@@ -1,15 +1,22 @@
 //! Manifest file processing logic
 
 use std::collections::HashMap;
-use std::path::Path;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
-use miette::{Context, SourceOffset};
+use miette::{Context, NamedSource, SourceOffset, SourceSpan};
+use rq::Url;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
+use toml::Spanned;
+use toml_edit::{DocumentMut, Item, Table, TableLike};
 
 use crate::adapter::hangar::ManifestHangarPlugin;
 use crate::adapter::spiget::ManifestSpigetPlugin;
-use crate::error::{NotFoundError, ParseError};
+use crate::cli::PluginNotFoundError;
+use crate::compat::{MinecraftVersion, ServerPlatform, ServerTarget};
+use crate::error::ParseError;
 
 pub static DEFAULT_MANIFEST_FILE_NAME: &str = "pluginstall.manifest.toml";
 
@@ -19,19 +26,45 @@ pub static DEFAULT_MANIFEST_FILE_NAME: &str = "pluginstall.manifest.toml";
 pub struct Manifest {
     pub meta: ManifestMeta,
     // maps plugin names to their details
+    #[serde(default)]
     pub plugin: HashMap<String, PluginDownloadSpec>,
+    /// The path of the file that the manifest was parsed from, if it was parsed from a file.
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    /// The TOML source that the manifest was parsed from. Used to point errors at entries in the manifest.
+    #[serde(skip)]
+    pub source: String,
+    /// The locations of the plugin entries in the manifest source.
+    #[serde(skip)]
+    spans: ManifestSpans,
 }
 
-/// Metadata for a plugin manifest. Is currently just a human-friendly name of the manifest.
+/// The locations of the plugin entries (and the keys of the entries) in a manifest source.
+///
+/// This is deserialized separately from the [`Manifest`], since spans can't be deserialized inside of
+/// internally tagged enums like [`PluginDownloadSpec`].
+#[derive(serde::Deserialize, Clone, Debug, Default)]
+struct ManifestSpans {
+    #[serde(default)]
+    plugin: HashMap<String, Spanned<HashMap<String, Spanned<toml::Value>>>>,
+}
+
+/// Metadata for a plugin manifest: a human-friendly name, and the server that the plugins are for.
 #[derive(serde::Deserialize, Clone, Debug)]
 pub struct ManifestMeta {
     /// A human-friendly name for this manifest.
     #[serde(rename = "name")]
     pub manifest_name: String,
+    /// The Minecraft version of the server. Versions of plugins that don't support it are avoided when resolving the latest version.
+    #[serde(default)]
+    pub minecraft_version: Option<MinecraftVersion>,
+    /// The server software, like Paper or Velocity. Versions of plugins for other platforms are avoided when resolving the latest version.
+    #[serde(default)]
+    pub platform: Option<ServerPlatform>,
 }
 
 /// An enum of various different supported download methods for the plugin.
-#[derive(serde::Deserialize, Clone, Debug)]
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
 #[serde(tag = "type")]
 #[serde(rename_all = "kebab-case")]
 pub enum PluginDownloadSpec {
@@ -41,6 +74,19 @@ pub enum PluginDownloadSpec {
     Spiget(ManifestSpigetPlugin),
 }
 
+impl PluginDownloadSpec {
+    /// Check if the given URL is an API endpoint with data for this plugin.
+    /// Used to find the HTTP cache entries that belong to a plugin.
+    #[inline]
+    pub fn is_plugin_api_url(&self, url: &Url) -> bool {
+        match self {
+            // TODO: update this when the Hangar API is supported
+            Self::Hangar(_) => false,
+            Self::Spiget(spiget) => spiget.is_resource_api_url(url),
+        }
+    }
+}
+
 /// Error returned when trying to process a manifest file.
 #[derive(thiserror::Error, miette::Diagnostic, Debug)]
 pub enum ManifestError {
@@ -51,13 +97,65 @@ pub enum ManifestError {
     /// Parse error, usually because the manifest file was not in valid TOML,
     /// lacked required keys, or contained unrecognized keys.
     #[error(transparent)]
+    #[diagnostic(transparent)]
     Parse(#[from] ParseError),
+
+    /// Error parsing the manifest source for editing.
+    #[error("Error reading the manifest for editing")]
+    Edit(#[from] toml_edit::TomlError),
+
+    /// Error serializing a plugin entry when editing the manifest.
+    #[error("Error writing plugin entry to the manifest")]
+    Serialize(#[from] toml_edit::ser::Error),
+}
+
+/// Error with a plugin entry in the manifest that is only found at runtime (like a resource ID that doesn't exist in the API).
+/// Points at the entry, or one of its keys, in the manifest source.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("Problem with plugin '{plugin_name}' in the manifest")]
+pub struct ManifestEntryError {
+    pub plugin_name: String,
+    /// In an [`Arc`] to keep results with this error small.
+    #[source_code]
+    source_code: Arc<NamedSource<String>>,
+    #[label("{label}")]
+    location: SourceSpan,
+    label: String,
+    #[help]
+    help: Option<String>,
+    #[source]
+    cause: Box<dyn Error + Send + Sync>,
+}
+
+impl ManifestEntryError {
+    /// Add a help message to the error.
+    #[inline]
+    #[must_use]
+    pub fn with_help(mut self, help: impl Into<String>) -> Self {
+        self.help = Some(help.into());
+        self
+    }
 }
 
 /// Type alias for the generic result type returned by manifest operations.
 pub type ManifestResult<T> = Result<T, ManifestError>;
 
 impl Manifest {
+    /// Create a manifest without any plugins, with the given name. The manifest isn't written to the given path until it's edited.
+    #[inline]
+    pub fn new(manifest_name: &str, path: impl Into<PathBuf>) -> ManifestResult<Self> {
+        let mut meta = Table::new();
+        meta.insert("name", toml_edit::value(manifest_name));
+
+        let mut document = DocumentMut::new();
+        document.insert("meta", Item::Table(meta));
+
+        let mut manifest = Self::parse(document.to_string())?;
+        manifest.path = Some(path.into());
+
+        Ok(manifest)
+    }
+
     /// Parse a manifest object from a file path. Will return errors if the file could not be
     /// found/opened, or if the file contents were not valid manifest TOML.
     #[inline]
@@ -70,37 +168,392 @@ impl Manifest {
             .read_to_string(&mut manifest_file_contents)
             .await?;
 
-        Self::parse(manifest_file_contents)
+        let mut manifest = Self::parse(manifest_file_contents)?;
+        manifest.path = Some(path.to_path_buf());
+
+        Ok(manifest)
     }
 
     #[inline]
     pub fn parse(toml: impl AsRef<str>) -> ManifestResult<Self> {
         let toml = toml.as_ref();
-        let deser = toml::from_str::<Self>(toml).map_err(|error| ParseError::toml(error, toml))?;
+        let mut deser =
+            toml::from_str::<Self>(toml).map_err(|error| ParseError::toml(error, toml))?;
+        deser.spans = toml::from_str(toml).map_err(|error| ParseError::toml(error, toml))?;
+        deser.source = toml.to_string();
 
         Ok(deser)
     }
 
+    /// The server that the plugins in the manifest are for.
+    #[inline]
+    pub fn server_target(&self) -> ServerTarget {
+        ServerTarget {
+            minecraft_version: self.meta.minecraft_version.clone(),
+            platform: self.meta.platform,
+        }
+    }
+
     /// Get a plugin described in the manifest under the given name.
     ///
-    /// Errors with [`NotFoundError::ManifestPlugin`] if the plugin could not be found.
+    /// Errors with [`PluginNotFoundError`] (suggesting similar plugin names) if the plugin could not be found.
     #[inline]
     pub fn plugin(
         &self,
         plugin_name: impl AsRef<str>,
-    ) -> Result<&PluginDownloadSpec, NotFoundError> {
+    ) -> Result<&PluginDownloadSpec, PluginNotFoundError> {
         let plugin_name = plugin_name.as_ref();
 
         self.plugin
             .get(plugin_name)
-            .ok_or(NotFoundError::PluginInManifest)
+            .ok_or_else(|| PluginNotFoundError::new(plugin_name, self))
+    }
+
+    /// Create an error pointing at the entry of a plugin in the manifest source, labelled with `label`.
+    ///
+    /// If a `key` is given (like `resource_id`), the error points at the value of that key in the entry instead of the whole entry.
+    #[inline]
+    pub fn entry_error(
+        &self,
+        plugin_name: &str,
+        key: Option<&str>,
+        label: impl Into<String>,
+        cause: impl Into<Box<dyn Error + Send + Sync>>,
+    ) -> ManifestEntryError {
+        let span = self
+            .spans
+            .plugin
+            .get(plugin_name)
+            .map(|entry| {
+                key.and_then(|key| entry.get_ref().get(key))
+                    .map_or_else(|| entry.span(), Spanned::span)
+            })
+            .unwrap_or(0..0);
+
+        let name = match &self.path {
+            Some(path) => path.display().to_string(),
+            None => "manifest".to_string(),
+        };
+
+        ManifestEntryError {
+            plugin_name: plugin_name.to_string(),
+            source_code: Arc::new(
+                NamedSource::new(name, self.source.clone()).with_language("TOML"),
+            ),
+            location: span.into(),
+            label: label.into(),
+            help: None,
+            cause: cause.into(),
+        }
+    }
+}
+
+/// Suggest a name for a plugin in the manifest from the name (title) of the plugin in its API.
+///
+/// Plugin titles often have tags in brackets (like "[1.8-1.21]") and a tagline after a separator
+/// (like "GSit - Modern Sit Seat and more"), so only the rest of the title is used.
+/// The name is lowercase, with words separated by dashes. It's empty if nothing is left of the title.
+#[inline]
+pub fn suggest_plugin_name(title: &str) -> String {
+    let mut untagged = String::with_capacity(title.len());
+    let mut depth = 0usize;
+    for c in title.chars() {
+        match c {
+            '[' | '(' => depth += 1,
+            ']' | ')' => depth = depth.saturating_sub(1),
+            c if depth == 0 => untagged.push(c),
+            _ => {}
+        }
+    }
+
+    let tagline = [" - ", " – ", " — ", "|", ":"]
+        .iter()
+        .filter_map(|separator| untagged.find(separator))
+        .min()
+        .unwrap_or(untagged.len());
+    let title = untagged[..tagline].trim();
+
+    let mut name = String::with_capacity(title.len());
+    for c in title.chars() {
+        if c.is_alphanumeric() {
+            name.extend(c.to_lowercase());
+        } else if !name.is_empty() && !name.ends_with('-') {
+            name.push('-');
+        }
+    }
+
+    name.trim_end_matches('-').to_string()
+}
+
+/// The keys that plugins can be pinned to a version with in the manifest.
+static PIN_KEYS: [&str; 3] = ["version", "version_id", "version_spec"];
+
+/// Serialize a plugin entry to the table it's written as in the manifest.
+#[inline]
+fn plugin_entry(plugin: &PluginDownloadSpec) -> Result<Table, toml_edit::ser::Error> {
+    Ok(toml_edit::ser::to_document(plugin)?.as_table().clone())
+}
+
+/// A snippet with a `[plugin.<name>]` table for the given plugin, that can be pasted into a manifest file.
+#[inline]
+pub fn plugin_snippet(
+    plugin_name: &str,
+    plugin: &PluginDownloadSpec,
+) -> Result<String, toml_edit::ser::Error> {
+    let mut plugins = Table::new();
+    plugins.set_implicit(true);
+    plugins.insert(plugin_name, Item::Table(plugin_entry(plugin)?));
+
+    let mut document = DocumentMut::new();
+    document.insert("plugin", Item::Table(plugins));
+
+    Ok(document.to_string())
+}
+
+/// A manifest file that is being edited. Edits keep the comments and formatting of the rest of the manifest.
+pub struct ManifestDocument {
+    path: PathBuf,
+    document: DocumentMut,
+}
+
+impl Manifest {
+    /// Start editing the file that this manifest was parsed from (or the default manifest file, if it wasn't parsed from a file).
+    #[inline]
+    pub fn edit(&self) -> ManifestResult<ManifestDocument> {
+        let document = self.source.parse::<DocumentMut>()?;
+
+        Ok(ManifestDocument {
+            path: self
+                .path
+                .clone()
+                .unwrap_or_else(|| DEFAULT_MANIFEST_FILE_NAME.into()),
+            document,
+        })
+    }
+}
+
+impl ManifestDocument {
+    /// The path of the manifest file that is being edited.
+    #[inline]
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// The table of plugin entries, which is created if the manifest doesn't have any plugins yet.
+    #[inline]
+    fn plugins_mut(&mut self) -> &mut dyn TableLike {
+        let plugins = self.document.entry("plugin").or_insert_with(|| {
+            let mut table = Table::new();
+            // the plugin entries are written as `[plugin.<name>]` tables instead of a `[plugin]` table
+            table.set_implicit(true);
+            Item::Table(table)
+        });
+
+        plugins
+            .as_table_like_mut()
+            .expect("the manifest was parsed, so 'plugin' is a table")
+    }
+
+    /// Add a plugin entry with the given name. An existing entry with the same name is replaced.
+    #[inline]
+    pub fn add_plugin(
+        &mut self,
+        plugin_name: &str,
+        plugin: &PluginDownloadSpec,
+    ) -> ManifestResult<()> {
+        let entry = plugin_entry(plugin)?;
+        self.plugins_mut().insert(plugin_name, Item::Table(entry));
+
+        Ok(())
+    }
+
+    /// Remove the plugin entry with the given name. Returns `false` if the manifest has no plugin with the name.
+    #[inline]
+    pub fn remove_plugin(&mut self, plugin_name: &str) -> bool {
+        self.plugins_mut().remove(plugin_name).is_some()
+    }
+
+    /// Pin a plugin to a version by setting the given pin key (like `version` or `version_id`) to the value,
+    /// removing any other pin of the plugin. With [`None`], the plugin is unpinned.
+    ///
+    /// Does nothing if the manifest has no plugin with the given name.
+    #[inline]
+    pub fn set_pin(&mut self, plugin_name: &str, pin: Option<(&str, toml_edit::Value)>) {
+        let Some(entry) = self
+            .plugins_mut()
+            .get_mut(plugin_name)
+            .and_then(Item::as_table_like_mut)
+        else {
+            return;
+        };
+
+        for key in PIN_KEYS {
+            entry.remove(key);
+        }
+
+        if let Some((key, value)) = pin {
+            entry.insert(key, Item::Value(value));
+        }
+    }
+
+    /// Set a pin key of a plugin (like `version_id`) to the value, removing the other version pins of the plugin
+    /// but keeping its `version_spec`, which the new pin was resolved within.
+    ///
+    /// Does nothing if the manifest has no plugin with the given name.
+    #[inline]
+    pub fn update_pin(&mut self, plugin_name: &str, key: &str, value: toml_edit::Value) {
+        let Some(entry) = self
+            .plugins_mut()
+            .get_mut(plugin_name)
+            .and_then(Item::as_table_like_mut)
+        else {
+            return;
+        };
+
+        for key in PIN_KEYS.iter().filter(|&&key| key != "version_spec") {
+            entry.remove(key);
+        }
+
+        entry.insert(key, Item::Value(value));
+    }
+
+    /// Validate the edited manifest and write it to the manifest file. Returns the edited manifest.
+    #[inline]
+    pub async fn save(&self) -> ManifestResult<Manifest> {
+        let mut manifest = Manifest::parse(self.document.to_string())?;
+        manifest.path = Some(self.path.clone());
+
+        tokio::fs::write(&self.path, &manifest.source).await?;
+
+        Ok(manifest)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::*;
+    use crate::version::VersionOrder;
+
     #[test]
     fn test_parse_manifest() {
-        todo!()
+        let toml = r#"
+[meta]
+name = "test-manifest"
+minecraft_version = "1.21.4"
+platform = "paper"
+
+[plugin.gsit]
+type = "spiget"
+resource_id = 62325
+
+[plugin.chunky]
+type = "spiget"
+resource_id = 81534
+version_spec = "^1.4"
+version_order = "version"
+"#;
+        let manifest = Manifest::parse(toml).unwrap();
+        assert_eq!(manifest.meta.manifest_name, "test-manifest");
+        assert_eq!(
+            manifest.meta.minecraft_version,
+            Some("1.21.4".parse().unwrap())
+        );
+        assert_eq!(manifest.meta.platform, Some(ServerPlatform::Paper));
+
+        let PluginDownloadSpec::Spiget(gsit) = manifest.plugin("gsit").unwrap() else {
+            panic!("expected a Spiget plugin");
+        };
+        assert_eq!(u64::from(gsit.resource_id), 62325);
+        assert!(gsit.pinned_version().is_latest());
+
+        let PluginDownloadSpec::Spiget(chunky) = manifest.plugin("chunky").unwrap() else {
+            panic!("expected a Spiget plugin");
+        };
+        assert_eq!(chunky.pinned_version().to_string(), "^1.4");
+        assert_eq!(chunky.pin_key(), Some("version_spec"));
+        assert_eq!(chunky.version_order, Some(VersionOrder::Version));
+
+        let entry = &manifest.spans.plugin["gsit"];
+        assert_eq!(&toml[entry.get_ref()["resource_id"].span()], "62325");
+
+        assert!(manifest.plugin("missing").is_err());
+    }
+
+    #[test]
+    fn test_new_manifest() {
+        let manifest = Manifest::new("lobby \"1\"", "lobby.toml").unwrap();
+        assert_eq!(manifest.meta.manifest_name, "lobby \"1\"");
+        assert!(manifest.plugin.is_empty());
+
+        let document = manifest.edit().unwrap();
+        assert_eq!(document.path(), Path::new("lobby.toml"));
+    }
+
+    #[test]
+    fn test_suggest_plugin_name() {
+        assert_eq!(
+            suggest_plugin_name("GSit - Modern Sit Seat and more"),
+            "gsit"
+        );
+        assert_eq!(suggest_plugin_name("[1.8-1.21] Chunky"), "chunky");
+        assert_eq!(suggest_plugin_name("Chunky [1.8-1.21]"), "chunky");
+        assert_eq!(suggest_plugin_name("World Edit | Fast"), "world-edit");
+        assert_eq!(suggest_plugin_name("LuckPerms: permissions"), "luckperms");
+    }
+
+    #[test]
+    fn test_plugin_snippet() {
+        let plugin = PluginDownloadSpec::Spiget(ManifestSpigetPlugin::new(62325.into()));
+
+        let snippet = plugin_snippet("gsit", &plugin).unwrap();
+        assert_eq!(
+            snippet,
+            "[plugin.gsit]\ntype = \"spiget\"\nresource_id = 62325\n"
+        );
+
+        let manifest = Manifest::parse(format!("[meta]\nname = \"x\"\n{snippet}")).unwrap();
+        assert!(manifest.plugin("gsit").is_ok());
+    }
+
+    #[test]
+    fn test_edit_manifest_keeps_comments() {
+        let toml = r#"# my server
+[meta]
+name = "test-manifest"
+
+# sitting
+[plugin.gsit]
+type = "spiget"
+resource_id = 62325 # the resource
+"#;
+        let mut document = Manifest::parse(toml).unwrap().edit().unwrap();
+        document.set_pin("gsit", Some(("version", "2.0.0".into())));
+        document
+            .add_plugin(
+                "chunky",
+                &Manifest::parse(
+                    "[meta]\nname = \"x\"\n[plugin.chunky]\ntype = \"spiget\"\nresource_id = 81534",
+                )
+                .unwrap()
+                .plugin["chunky"],
+            )
+            .unwrap();
+
+        let edited = document.document.to_string();
+        assert!(edited.starts_with("# my server\n"));
+        assert!(edited.contains("# sitting\n[plugin.gsit]\n"));
+        assert!(edited.contains("resource_id = 62325 # the resource\n"));
+        assert!(edited.contains("version = \"2.0.0\""));
+        assert!(edited.contains("[plugin.chunky]\ntype = \"spiget\"\nresource_id = 81534\n"));
+
+        document.set_pin("chunky", Some(("version_spec", "^1.4".into())));
+        document.update_pin("chunky", "version_id", 512_000.into());
+        let chunky = document.document["plugin"]["chunky"].as_table().unwrap();
+        assert_eq!(chunky["version_spec"].as_str(), Some("^1.4"));
+        assert_eq!(chunky["version_id"].as_integer(), Some(512_000));
+
+        assert!(document.remove_plugin("gsit"));
+        assert!(!document.remove_plugin("gsit"));
+        assert!(!document.document.to_string().contains("gsit"));
     }
 }
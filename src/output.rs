@@ -1,18 +1,113 @@
 //! Utilities for controlling the output of the CLI app.
 
 use std::io::{Stderr, Stdout, Write};
+use std::str::FromStr;
 
-use owo_colors::OwoColorize;
+use anstream::{AutoStream, ColorChoice};
+use clap::ValueEnum;
+use indexmap::IndexMap;
+use miette::SourceSpan;
+use minijinja::Environment;
+use ref_cast::RefCast;
+use serde_json::{Map, Value};
+use terminal_size::Width;
+
+use crate::adapter::{PluginDetails, PluginDetailsWrapper, PluginVersion, PluginVersionWrapper};
+use crate::error::ErrorReport;
+use crate::util::TableStyle;
+
+/// The name that output templates are registered under in their template environment.
+static TEMPLATE_NAME: &str = "output";
+
+/// The format that the CLI writes command output in.
+#[derive(ValueEnum, serde::Deserialize, Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Human readable output.
+    #[default]
+    Human,
+    /// Compact JSON.
+    Json,
+    /// Indented JSON.
+    JsonPretty,
+    /// Newline-delimited JSON, with one record per line.
+    Ndjson,
+    /// YAML.
+    Yaml,
+    /// TOML.
+    Toml,
+    /// CSV with a header row, with one record per row. Nested fields are flattened into columns like `details.page_url`.
+    Csv,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as ValueEnum>::from_str(s, true)
+    }
+}
+
+/// A user-defined template that output is rendered with instead of an output format.
+/// Each record of the output (see [`DataDisplay::records`]) is rendered on its own line, with the fields of the record as variables.
+pub struct OutputTemplate {
+    env: Environment<'static>,
+}
+
+/// Error with an output template.
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("Invalid output template")]
+pub struct TemplateError {
+    #[source]
+    source: minijinja::Error,
+    #[source_code]
+    template: String,
+    #[label("{}", source.detail().unwrap_or("here"))]
+    span: Option<SourceSpan>,
+}
+
+impl OutputTemplate {
+    /// Compile a template, returning an error if its syntax is invalid.
+    #[inline]
+    pub fn new(template: impl Into<String>) -> Result<Self, TemplateError> {
+        let template = template.into();
+        let mut env = Environment::new();
+
+        env.add_template_owned(TEMPLATE_NAME, template.clone())
+            .map_err(|source| TemplateError {
+                span: source.range().map(Into::into),
+                source,
+                template,
+            })?;
+
+        Ok(Self { env })
+    }
+
+    /// Render a record with this template.
+    #[inline]
+    pub fn render(&self, record: &Value) -> Result<String, minijinja::Error> {
+        self.env.get_template(TEMPLATE_NAME)?.render(record)
+    }
+}
 
 /// A helper struct for controlling the output from the CLI. Data can be "written" to the output manager, and it will
 /// choose the appropriate format to output it in.
 pub struct CliOutput {
-    /// Output as JSON?
-    json: bool,
+    /// The format to write output in.
+    format: OutputFormat,
+    /// A template that overrides the output format.
+    template: Option<OutputTemplate>,
+    /// The style of tables in human readable output.
+    table_style: TableStyle,
     /// Write a newline at the end of the output?
     newline: bool,
     stdout: Stdout,
     stderr: Stderr,
+    /// Whether colors are written to `stdout`.
+    stdout_color: ColorChoice,
+    /// Whether colors are written to `stderr`.
+    stderr_color: ColorChoice,
 }
 
 /// Trait implemented by data that can be outputted/displayed from the CLI app. Implementors of this trait should be "output"
@@ -24,36 +119,224 @@ pub struct CliOutput {
 ///
 /// This is not a [`std::fmt::Debug`] or [`std::fmt::Display`] analogue, since those types are meant to be implemented by
 /// *any* data that could be converted to text. This type is specifically for entire "panels" of data produced by commands/subcommands.
-pub trait DataDisplay {
-    fn write_json(&self, w: &mut impl Write) -> Result<(), std::io::Error>;
-
+///
+/// The machine-readable formats (JSON, YAML, TOML) are written with the [`serde::Serialize`] implementation of the type.
+pub trait DataDisplay: serde::Serialize {
     fn write_hr(&self, w: &mut impl Write) -> Result<(), std::io::Error>;
+
+    /// The records of this data, for record-based formats (each record is a line in NDJSON, and a row in CSV).
+    ///
+    /// By default the whole output is a single record. Outputs that are lists (like a list of versions) should return
+    /// one record per element.
+    #[inline]
+    fn records(&self) -> Result<Vec<Value>, serde_json::Error> {
+        Ok(vec![serde_json::to_value(self)?])
+    }
+}
+
+/// Create a flat record with the serialized fields of both a plugin and one of its versions
+/// (the fields of [`PluginDetails::serialize`] followed by the fields of [`PluginVersion::serialize`]).
+#[inline]
+pub fn version_record<P: PluginDetails, V: PluginVersion>(
+    details: &P,
+    version: &V,
+) -> Result<Map<String, Value>, serde_json::Error> {
+    let mut record = Map::new();
+
+    for value in [
+        serde_json::to_value(PluginDetailsWrapper::ref_cast(details))?,
+        serde_json::to_value(PluginVersionWrapper::ref_cast(version))?,
+    ] {
+        if let Value::Object(fields) = value {
+            record.extend(fields);
+        }
+    }
+
+    Ok(record)
+}
+
+/// Serialize the data in a machine-readable format.
+#[inline]
+fn serialize_data<T: DataDisplay>(
+    format: OutputFormat,
+    data: &T,
+) -> Result<String, std::io::Error> {
+    let serialized = match format {
+        OutputFormat::Human => unreachable!("human readable output is not serialized"),
+        OutputFormat::Json => serde_json::to_string(data)?,
+        OutputFormat::JsonPretty => serde_json::to_string_pretty(data)?,
+        OutputFormat::Ndjson => data
+            .records()?
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n"),
+        OutputFormat::Yaml => serde_yaml::to_string(data).map_err(std::io::Error::other)?,
+        OutputFormat::Toml => toml::to_string(data).map_err(std::io::Error::other)?,
+        OutputFormat::Csv => records_to_csv(&data.records()?)?,
+    };
+
+    Ok(serialized)
+}
+
+/// Write records as CSV. The columns are the union of the (flattened) fields of all records, in the order they first appear.
+#[inline]
+fn records_to_csv(records: &[Value]) -> Result<String, std::io::Error> {
+    let rows = records
+        .iter()
+        .map(|record| {
+            let mut row = IndexMap::new();
+            flatten_record("", record, &mut row);
+            row
+        })
+        .collect::<Vec<_>>();
+
+    let mut columns = IndexMap::new();
+    for column in rows.iter().flat_map(IndexMap::keys) {
+        columns.entry(column.as_str()).or_insert(());
+    }
+
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(columns.keys())?;
+
+    for row in &rows {
+        let fields = columns
+            .keys()
+            .map(|column| row.get(*column).map_or("", String::as_str));
+        writer.write_record(fields)?;
+    }
+
+    let csv = writer.into_inner().map_err(|err| err.into_error())?;
+    Ok(String::from_utf8(csv).expect("CSV is written from UTF-8 strings"))
+}
+
+/// Flatten a record into columns, joining the keys of nested objects with dots. Arrays are written as JSON.
+#[inline]
+fn flatten_record(prefix: &str, value: &Value, row: &mut IndexMap<String, String>) {
+    let cell = match value {
+        Value::Object(fields) => {
+            for (key, value) in fields {
+                let column = match prefix {
+                    "" => key.clone(),
+                    prefix => format!("{prefix}.{key}"),
+                };
+
+                flatten_record(&column, value, row);
+            }
+
+            return;
+        }
+        Value::Null => String::new(),
+        Value::String(string) => string.clone(),
+        Value::Array(_) => value.to_string(),
+        Value::Bool(_) | Value::Number(_) => value.to_string(),
+    };
+
+    let column = match prefix {
+        "" => "value".to_string(),
+        prefix => prefix.to_string(),
+    };
+
+    row.insert(column, cell);
 }
 
 impl CliOutput {
     /// Create a new output manager.
     ///
-    /// If the [`json`] parameter is true then this output manager will write data in JSON format instead of a human-readable format.
+    /// Data will be written in the given [`format`].
     /// If the [`newline`] parameter is true then a newline will be written after every output.
     #[inline]
-    pub fn new(json: bool, newline: bool) -> Self {
+    pub fn new(format: OutputFormat, newline: bool) -> Self {
         Self {
-            json,
+            format,
+            template: None,
+            table_style: TableStyle::default(),
             newline,
             stdout: std::io::stdout(),
             stderr: std::io::stderr(),
+            stdout_color: AutoStream::choice(&std::io::stdout()),
+            stderr_color: AutoStream::choice(&std::io::stderr()),
         }
     }
 
+    /// Render output with the given template instead of the output format. Errors are still written in the output format.
+    #[inline]
+    #[must_use]
+    pub fn with_template(mut self, template: Option<OutputTemplate>) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// Write colors according to the given choice. With [`clap::ColorChoice::Auto`], colors are only written to terminals,
+    /// and not at all if the `NO_COLOR` environment variable is set.
+    #[inline]
+    #[must_use]
+    pub fn with_color(mut self, color: clap::ColorChoice) -> Self {
+        let color = match color {
+            clap::ColorChoice::Auto => None,
+            clap::ColorChoice::Always => Some(ColorChoice::Always),
+            clap::ColorChoice::Never => Some(ColorChoice::Never),
+        };
+
+        if let Some(color) = color {
+            self.stdout_color = color;
+            self.stderr_color = color;
+        }
+
+        self
+    }
+
+    /// Write tables in human readable output in the given style.
+    #[inline]
+    #[must_use]
+    pub fn with_table_style(mut self, table_style: TableStyle) -> Self {
+        self.table_style = table_style;
+        self
+    }
+
+    /// The style that tables should be written in.
+    #[inline]
+    pub fn table_style(&self) -> TableStyle {
+        self.table_style
+    }
+
+    /// The width of the terminal that output is written to.
+    /// Returns [`None`] if `stdout` isn't a terminal, in which case output shouldn't be fit to any width.
+    #[inline]
+    pub fn terminal_width(&self) -> Option<usize> {
+        terminal_size::terminal_size_of(&self.stdout).map(|(Width(width), _)| width as usize)
+    }
+
+    /// Whether data is written as human readable output (see [`DataDisplay::write_hr`]), rather than in a machine readable format or with a template.
+    #[inline]
+    pub fn is_human_readable(&self) -> bool {
+        self.template.is_none() && self.format == OutputFormat::Human
+    }
+
     /// Write the data display type to `stdout`. This method locks `stdout`.
     #[inline]
     pub fn display<T: DataDisplay>(&self, data: &T) -> Result<(), std::io::Error> {
-        let mut lock = self.stdout.lock();
+        // colors are stripped from the output if they're disabled
+        let mut lock = AutoStream::new(self.stdout.lock(), self.stdout_color);
+
+        if let Some(template) = &self.template {
+            let lines = data
+                .records()?
+                .iter()
+                .map(|record| template.render(record))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(std::io::Error::other)?;
 
-        if self.json {
-            data.write_json(&mut lock)?;
+            write!(lock, "{}", lines.join("\n"))?;
         } else {
-            data.write_hr(&mut lock)?;
+            match self.format {
+                OutputFormat::Human => data.write_hr(&mut lock)?,
+                format => {
+                    let serialized = serialize_data(format, data)?;
+                    // some formats end with a newline, but the trailing newline is controlled by the CLI options
+                    write!(lock, "{}", serialized.trim_end_matches('\n'))?;
+                }
+            }
         }
 
         // write a newline at the end
@@ -67,13 +350,45 @@ impl CliOutput {
         Ok(())
     }
 
+    /// Ask a yes/no question on `stderr` and read the answer from `stdin`. Anything other than "y" or "yes" is a no,
+    /// including the end of `stdin`.
+    #[inline]
+    pub fn confirm(&self, question: &str) -> Result<bool, std::io::Error> {
+        {
+            let mut lock = AutoStream::new(self.stderr.lock(), self.stderr_color);
+            write!(lock, "{question} [y/N] ")?;
+            lock.flush()?;
+        }
+
+        let mut answer = String::new();
+        std::io::stdin().read_line(&mut answer)?;
+
+        Ok(matches!(
+            answer.trim().to_ascii_lowercase().as_str(),
+            "y" | "yes"
+        ))
+    }
+
+    /// Write an error to `stderr`. This method locks `stderr`.
+    ///
+    /// With any machine-readable output format, the error is written as an [`ErrorReport`] in a JSON envelope like `{"error": {...}}`,
+    /// otherwise it's written as a human-readable diagnostic.
     #[inline]
-    pub fn error<E: std::error::Error>(&self, error: E) -> Result<(), std::io::Error> {
-        let error_string = format!("{}", error);
+    pub fn error(&self, error: &miette::Report) -> Result<(), std::io::Error> {
+        let mut lock = AutoStream::new(self.stderr.lock(), self.stderr_color);
 
-        let mut lock = self.stderr.lock();
+        match self.format {
+            OutputFormat::Human => writeln!(lock, "Error: {error:?}")?,
+            OutputFormat::JsonPretty => {
+                let envelope = serde_json::json!({ "error": ErrorReport::new(error) });
+                writeln!(lock, "{envelope:#}")?;
+            }
+            _ => {
+                let envelope = serde_json::json!({ "error": ErrorReport::new(error) });
+                writeln!(lock, "{envelope}")?;
+            }
+        }
 
-        writeln!(&mut lock, "{}", error_string.red())?;
         lock.flush()?;
 
         Ok(())
@@ -1,25 +1,34 @@
 //! IO logic (networking, filesystem, stdout/stderr, etc.)
 
 use std::{
-    io::{self, ErrorKind},
+    collections::HashMap,
+    io::{self, Cursor, ErrorKind},
     path::{Path, PathBuf},
     sync::Arc,
+    time::Duration,
 };
 
-use chrono::TimeDelta;
-use http_cache_reqwest::{CACacheManager, Cache, CacheMode, HttpCache, HttpCacheOptions};
+use chrono::{DateTime, TimeDelta, Utc};
+use http::Extensions;
+use http_cache_reqwest::{CACacheManager, Cache, CacheMode, HttpCache, HttpCacheOptions, Parts};
 use hyperx::header::{CacheControl, CacheDirective, ContentDisposition, Header};
 use miette::{Context, IntoDiagnostic};
-use reqwest_middleware::ClientWithMiddleware;
-use rq::header::{CACHE_CONTROL, CONTENT_DISPOSITION};
-use tokio::{fs::File, io::AsyncWriteExt};
+use reqwest_middleware::{ClientWithMiddleware, Middleware, Next};
+use rq::header::{HeaderValue, AUTHORIZATION, CACHE_CONTROL, CONTENT_DISPOSITION};
+use rq::Url;
+use tokio::{fs::File, io::AsyncWriteExt, sync::Semaphore};
 
 use crate::{
-    adapter::{spiget::SpigetApiClient, PluginApiType, PluginVersion, VersionSpec},
+    adapter::{spiget, spiget::SpigetApiClient, PluginApiType, PluginVersion, VersionSpec},
     caching::{CacheError, DownloadCache},
-    error::diagnostics::{
-        invalid_cache_control, invalid_content_disposition, missing_content_disposition,
+    config::Credentials,
+    error::{
+        diagnostics::{
+            invalid_cache_control, invalid_content_disposition, missing_content_disposition,
+        },
+        NotCachedError,
     },
+    jar::{self, JarError, JarMetadata},
     ok_none,
     output::CliOutput,
     util::{content_disposition_file_name, validate_file_name},
@@ -28,6 +37,9 @@ use crate::{
 /// The user agent to be used by pluginstall when talking to APIs.
 pub static USER_AGENT: &str = "pluginstall (github PersonBelowRocks/pluginstall)";
 
+/// The default maximum number of concurrent HTTP requests.
+pub const DEFAULT_CONCURRENCY: usize = 8;
+
 /// Error emitted by [`IoSession`] operations.
 #[derive(thiserror::Error, Debug)]
 pub enum IoSessionError {
@@ -42,37 +54,265 @@ pub enum IoSessionError {
 /// The result of an [`IoSession`] operation.
 pub type IoSessionResult<T> = Result<T, IoSessionError>;
 
+/// How an [`IoSession`] should use the HTTP cache (for API responses) and the download cache (for plugin files).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum CachePolicy {
+    /// Use cached data while it's fresh, and fetch it from the network when it's stale or missing.
+    #[default]
+    Default,
+    /// Revalidate all cached API responses with the network and download plugin files again.
+    /// The fetched data is still written to the caches.
+    Refresh,
+    /// Bypass both caches. Nothing is read from or written to the caches.
+    NoCache,
+    /// Never touch the network, and only read data from the caches.
+    Offline,
+}
+
+impl CachePolicy {
+    /// The mode that the HTTP cache should use for API requests with this policy.
+    #[inline]
+    fn http_cache_mode(self) -> CacheMode {
+        match self {
+            Self::Default => CacheMode::Default,
+            Self::Refresh => CacheMode::NoCache,
+            Self::NoCache => CacheMode::NoStore,
+            Self::Offline => CacheMode::OnlyIfCached,
+        }
+    }
+}
+
+/// Settings for how an [`IoSession`] should cache data.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CacheSettings {
+    /// How the caches should be used.
+    pub policy: CachePolicy,
+    /// Overrides the `max-age` of API responses. Cached API responses older than this will be fetched again.
+    pub api_ttl: Option<TimeDelta>,
+    /// Whether the jars of versions that aren't in the download cache are downloaded to check if they're compatible with the server.
+    pub check_jars: bool,
+}
+
+/// Middleware that overrides the `max-age` of responses, so that the HTTP cache will consider them fresh for a fixed amount of time.
+/// Must be placed after the cache middleware so that it modifies responses before they are cached.
+struct OverrideMaxAge(TimeDelta);
+
+#[async_trait::async_trait]
+impl Middleware for OverrideMaxAge {
+    async fn handle(
+        &self,
+        req: rq::Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<rq::Response> {
+        let mut response = next.run(req, extensions).await?;
+
+        let cache_control = HeaderValue::from_str(&format!("max-age={}", self.0.num_seconds()))
+            .expect("the header value is always valid ASCII");
+        response.headers_mut().insert(CACHE_CONTROL, cache_control);
+
+        Ok(response)
+    }
+}
+
+/// Settings for how an [`IoSession`] should talk to the network.
+#[derive(Clone, Debug)]
+pub struct NetworkSettings {
+    /// The user agent sent with all requests.
+    pub user_agent: String,
+    /// The timeout of requests.
+    pub timeout: Option<Duration>,
+    /// The timeout for connecting to a server.
+    pub connect_timeout: Option<Duration>,
+    /// The maximum number of concurrent requests.
+    pub concurrency: usize,
+    /// The base URL of the Spiget API (or a mirror of it).
+    pub spiget_base_url: Url,
+    /// Credentials sent to hosts, keyed by host name.
+    pub credentials: HashMap<String, Credentials>,
+}
+
+impl Default for NetworkSettings {
+    #[inline]
+    fn default() -> Self {
+        Self {
+            user_agent: USER_AGENT.to_string(),
+            timeout: None,
+            connect_timeout: None,
+            concurrency: DEFAULT_CONCURRENCY,
+            spiget_base_url: Url::parse(spiget::BASE_URL).unwrap(),
+            credentials: HashMap::new(),
+        }
+    }
+}
+
+/// Middleware that limits how many requests can be in flight at the same time.
+struct LimitConcurrency(Arc<Semaphore>);
+
+#[async_trait::async_trait]
+impl Middleware for LimitConcurrency {
+    async fn handle(
+        &self,
+        req: rq::Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<rq::Response> {
+        let _permit = self
+            .0
+            .acquire()
+            .await
+            .expect("the semaphore is never closed");
+
+        next.run(req, extensions).await
+    }
+}
+
+/// Middleware that adds an `Authorization` header to requests to hosts that have credentials.
+/// Maps host names to the values of their `Authorization` header.
+struct Authorize(HashMap<String, HeaderValue>);
+
+impl Authorize {
+    /// Create the middleware from credentials keyed by host name.
+    /// Credentials that can't be sent in a header are skipped with a warning.
+    #[inline]
+    fn new(credentials: &HashMap<String, Credentials>) -> Self {
+        let headers = credentials
+            .iter()
+            .filter_map(|(host, credentials)| {
+                match HeaderValue::from_str(&credentials.authorization()) {
+                    Ok(mut value) => {
+                        value.set_sensitive(true);
+                        Some((host.clone(), value))
+                    }
+                    Err(_) => {
+                        log::warn!("Credentials for host '{host}' contain invalid characters and will not be used");
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        Self(headers)
+    }
+}
+
+#[async_trait::async_trait]
+impl Middleware for Authorize {
+    async fn handle(
+        &self,
+        mut req: rq::Request,
+        extensions: &mut Extensions,
+        next: Next<'_>,
+    ) -> reqwest_middleware::Result<rq::Response> {
+        let value = req.url().host_str().and_then(|host| self.0.get(host));
+
+        if let Some(value) = value {
+            req.headers_mut().insert(AUTHORIZATION, value.clone());
+        }
+
+        next.run(req, extensions).await
+    }
+}
+
+/// Create cache options for the HTTP cache. If a TTL is provided, cached responses older than the TTL will be removed from the
+/// cache when they're requested, so that they are fetched again.
+#[inline]
+fn http_cache_options(cacache_path: PathBuf, ttl: Option<TimeDelta>) -> HttpCacheOptions {
+    let Some(ttl) = ttl else {
+        return HttpCacheOptions::default();
+    };
+
+    HttpCacheOptions {
+        cache_bust: Some(Arc::new(move |_: &Parts, _: &Option<_>, key: &str| {
+            let Ok(Some(metadata)) = cacache::metadata_sync(&cacache_path, key) else {
+                return Vec::new();
+            };
+
+            // cacache timestamps are in unix milliseconds
+            let written = DateTime::<Utc>::from_timestamp_millis(metadata.time as _);
+            match written {
+                Some(written) if Utc::now() - written >= ttl => vec![key.to_string()],
+                _ => Vec::new(),
+            }
+        })),
+        ..HttpCacheOptions::default()
+    }
+}
+
 /// A session for IO operations. Functions as a bridge between both HTTP APIs and the local filesystem (including local filesystem caches).
 #[derive(Clone)]
 pub struct IoSession {
+    /// Client for downloading plugin files. Downloads are cached manually in the download cache,
+    /// so this client doesn't use the HTTP cache.
     client: ClientWithMiddleware,
     spiget: SpigetApiClient,
     cli_output: Arc<CliOutput>,
     cache: Arc<DownloadCache>,
+    /// How this session uses the caches.
+    cache_policy: CachePolicy,
+    /// See [`CacheSettings::check_jars`].
+    check_jars: bool,
 }
 
 impl IoSession {
-    /// Creates a new API session.
-    pub fn new(cli_output: CliOutput, download_cache: DownloadCache) -> Self {
-        let client = rq::Client::builder()
-            .user_agent(USER_AGENT)
-            .connection_verbose(true)
-            .build()
-            .unwrap();
-
-        let client = reqwest_middleware::ClientBuilder::new(client)
-            .with(Cache(HttpCache {
-                mode: CacheMode::Default,
-                manager: download_cache.cacache_manager(),
-                options: HttpCacheOptions::default(),
-            }))
+    /// Creates a new API session, using the caches and the network according to the given settings.
+    ///
+    /// If the cache policy is [`CachePolicy::Offline`], the session will never touch the network. API responses will only be read from the HTTP cache,
+    /// and plugin files will only be read from the download cache.
+    pub fn new(
+        cli_output: CliOutput,
+        download_cache: DownloadCache,
+        cache_settings: CacheSettings,
+        network_settings: NetworkSettings,
+    ) -> Self {
+        let mut client = rq::Client::builder()
+            .user_agent(&network_settings.user_agent)
+            .connection_verbose(true);
+
+        if let Some(timeout) = network_settings.timeout {
+            client = client.timeout(timeout);
+        }
+
+        if let Some(connect_timeout) = network_settings.connect_timeout {
+            client = client.connect_timeout(connect_timeout);
+        }
+
+        let client = client.build().unwrap();
+
+        // both clients share the same concurrency limit
+        let semaphore = Arc::new(Semaphore::new(network_settings.concurrency.max(1)));
+        let credentials = Arc::new(Authorize::new(&network_settings.credentials));
+
+        let cacache_manager = download_cache.cacache_manager();
+        let mut api_client =
+            reqwest_middleware::ClientBuilder::new(client.clone()).with(Cache(HttpCache {
+                mode: cache_settings.policy.http_cache_mode(),
+                options: http_cache_options(cacache_manager.path.clone(), cache_settings.api_ttl),
+                manager: cacache_manager,
+            }));
+
+        if let Some(ttl) = cache_settings.api_ttl {
+            api_client = api_client.with(OverrideMaxAge(ttl));
+        }
+
+        let api_client = api_client
+            .with_arc(credentials.clone())
+            .with(LimitConcurrency(semaphore.clone()))
+            .build();
+        let offline = cache_settings.policy == CachePolicy::Offline;
+
+        let download_client = reqwest_middleware::ClientBuilder::new(client)
+            .with_arc(credentials)
+            .with(LimitConcurrency(semaphore))
             .build();
 
         Self {
-            spiget: SpigetApiClient::new(&client),
+            spiget: SpigetApiClient::new(&api_client, network_settings.spiget_base_url, offline),
             cli_output: Arc::new(cli_output),
             cache: Arc::new(download_cache),
-            client,
+            client: download_client,
+            cache_policy: cache_settings.policy,
+            check_jars: cache_settings.check_jars,
         }
     }
 
@@ -94,48 +334,69 @@ impl IoSession {
         &self.cache
     }
 
-    /// Make a download HTTP request and cache the result.
+    /// Whether jars that aren't in the download cache should be downloaded to check if they're compatible with the server.
+    #[inline]
+    pub fn check_jars(&self) -> bool {
+        self.check_jars
+    }
+
+    /// Make a download HTTP request and cache the result (unless the cache policy is [`CachePolicy::NoCache`]).
     /// This will always fetch the file from the network,
     /// and never read from cache (although it will write to the cache).
+    ///
+    /// Returns the name of the downloaded file and its data.
     #[inline]
-    async fn make_download_request<'a, V: PluginVersion>(
+    async fn fetch_plugin<'a, V: PluginVersion>(
         &self,
         spec: DownloadSpec<'a, V>,
-        download_dir: &Path,
-    ) -> Result<DownloadReport, DownloadError> {
+    ) -> Result<(String, Vec<u8>), DownloadError> {
         let url = spec.version.download_url().clone();
-        let response = self
-            .client
-            .get(url)
-            // we manually handle the caching of file downloads
-            .with_extension(CacheMode::NoStore)
-            .send()
-            .await?;
+        let response = self.client.get(url).send().await?;
 
         let file_name = response_content_disposition_file_name(&response)?;
         let ttl = response_downloaded_file_ttl(&response)?;
 
-        let file_path = download_dir.join(&file_name);
-
         let response_data = response
             .bytes()
             .await
             .map_err(reqwest_middleware::Error::Reqwest)?;
 
-        self.cache
-            .cache_file(
-                spec.plugin_name,
-                &spec.version.version_identifier(),
-                &file_name,
-                spec.api_type,
-                ttl,
-                &response_data,
-            )
-            .await?;
+        if self.cache_policy != CachePolicy::NoCache {
+            let cached = self
+                .cache
+                .cache_file(
+                    spec.plugin_name,
+                    &spec.version.version_identifier(),
+                    &file_name,
+                    spec.api_type,
+                    ttl,
+                    &response_data,
+                )
+                .await;
+
+            match cached {
+                // the download itself is fine, we just can't cache it
+                Err(CacheError::UnsupportedIndex(error)) => {
+                    log::warn!("Not caching downloaded file: {error}")
+                }
+                result => result?,
+            }
+        }
 
+        Ok((file_name, response_data.into()))
+    }
+
+    /// Download the given version into the given directory, caching it with [`IoSession::fetch_plugin`].
+    #[inline]
+    async fn make_download_request<'a, V: PluginVersion>(
+        &self,
+        spec: DownloadSpec<'a, V>,
+        download_dir: &Path,
+    ) -> Result<DownloadReport, DownloadError> {
+        let (file_name, response_data) = self.fetch_plugin(spec).await?;
         let download_size = response_data.len();
 
-        let mut file = File::create(file_path).await?;
+        let mut file = File::create(download_dir.join(&file_name)).await?;
         file.write_all(&response_data).await?;
         file.flush().await?;
 
@@ -145,6 +406,46 @@ impl IoSession {
         })
     }
 
+    /// Get the metadata of the jar of the given version, like its plugin descriptor.
+    ///
+    /// The metadata is read from the download cache if the version is cached. Otherwise the version is downloaded into the download cache
+    /// (without writing it anywhere else). The metadata of a version never changes, so cached versions are used even if they have outlived their TTL.
+    ///
+    /// Returns [`NotCachedError::PluginFile`] in offline mode if the version was not in the download cache.
+    #[inline]
+    pub async fn plugin_metadata<'a, V: PluginVersion>(
+        &self,
+        spec: DownloadSpec<'a, V>,
+    ) -> Result<JarMetadata, DownloadError> {
+        let version_ident = spec.version.version_identifier();
+
+        if matches!(
+            self.cache_policy,
+            CachePolicy::Default | CachePolicy::Offline
+        ) {
+            let cached = self
+                .download_cache()
+                .jar_metadata(spec.plugin_name, &version_ident)
+                .await?;
+
+            match (cached, self.cache_policy) {
+                (Some(metadata), _) => return Ok(metadata),
+                (None, CachePolicy::Offline) => {
+                    return Err(NotCachedError::PluginFile {
+                        plugin_name: spec.plugin_name.to_string(),
+                        version_name: spec.version.version_name().into_owned(),
+                        version_identifier: version_ident.into_owned(),
+                    }
+                    .into())
+                }
+                (None, _) => (),
+            }
+        }
+
+        let (_, data) = self.fetch_plugin(spec).await?;
+        Ok(jar::read_jar_metadata(Cursor::new(data))?)
+    }
+
     /// Download the given version to the given path. Returns a [`DownloadReport`] upon success, describing details of this download.
     #[inline]
     pub async fn download_plugin<'a, V: PluginVersion>(
@@ -154,6 +455,18 @@ impl IoSession {
     ) -> Result<DownloadReport, DownloadError> {
         let version_ident = spec.version.version_identifier();
 
+        match self.cache_policy {
+            CachePolicy::Offline => return self.copy_cached_plugin(spec, download_dir).await,
+            CachePolicy::Refresh | CachePolicy::NoCache => {
+                if !download_dir.is_dir() {
+                    return Err(DownloadError::InvalidDirectoryPath);
+                }
+
+                return self.make_download_request(spec, download_dir).await;
+            }
+            CachePolicy::Default => (),
+        }
+
         let cached_file = self
             .download_cache()
             .get_cached_file(spec.plugin_name, &version_ident)
@@ -173,6 +486,49 @@ impl IoSession {
 
         Ok(report)
     }
+
+    /// Copy the given version from the download cache to the given path, without touching the network.
+    /// Files that have outlived their TTL will still be used, since there's no way to get a fresh copy.
+    ///
+    /// Returns [`NotCachedError::PluginFile`] if the version was not in the download cache.
+    #[inline]
+    async fn copy_cached_plugin<'a, V: PluginVersion>(
+        &self,
+        spec: DownloadSpec<'a, V>,
+        download_dir: &Path,
+    ) -> Result<DownloadReport, DownloadError> {
+        let version_ident = spec.version.version_identifier();
+
+        let cached_file = self
+            .download_cache()
+            .get_cached_file_ignore_ttl(spec.plugin_name, &version_ident)
+            .await?;
+
+        let Some(mut cached_file) = cached_file else {
+            return Err(NotCachedError::PluginFile {
+                plugin_name: spec.plugin_name.to_string(),
+                version_name: spec.version.version_name().into_owned(),
+                version_identifier: version_ident.into_owned(),
+            }
+            .into());
+        };
+
+        if !download_dir.is_dir() {
+            return Err(DownloadError::InvalidDirectoryPath);
+        }
+
+        if cached_file.meta.is_outdated() {
+            log::warn!(
+                "Using outdated cached file for version '{version_ident}' of plugin '{}' in offline mode",
+                spec.plugin_name
+            );
+        }
+
+        Ok(DownloadReport {
+            download_size: cached_file.copy_to_directory(download_dir).await?,
+            cached: true,
+        })
+    }
 }
 
 /// Error in download operation.
@@ -184,6 +540,8 @@ pub enum DownloadError {
     Io(#[from] io::Error),
     #[error("Cache error")]
     Cache(#[from] CacheError),
+    #[error("Error reading downloaded plugin file")]
+    Jar(#[from] JarError),
     #[error("HTTP error")]
     Reqwest(#[from] reqwest_middleware::Error),
     #[error(transparent)]
@@ -192,6 +550,9 @@ pub enum DownloadError {
     #[error(transparent)]
     #[diagnostic(transparent)]
     ResponseFilename(#[from] ContentDispositionFilenameError),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    NotCached(#[from] NotCachedError),
 }
 
 /// Error returned by [`response_content_disposition_file_name`] (an internal function).
@@ -1,14 +1,20 @@
+use chrono::Utc;
+use clap::ColorChoice;
 use env_logger::WriteStyle;
 use hyperx::header::{ContentDisposition, DispositionParam};
 use log::LevelFilter;
 use owo_colors::{AnsiColors, OwoColorize};
 use std::{
+    borrow::Cow,
     cmp::max,
     fmt,
-    io::Write,
+    fs::{File, OpenOptions},
+    io::{self, Write},
     path::{Path, PathBuf},
     str::FromStr,
+    sync::{Mutex, PoisonError},
 };
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 pub const LOG_LEVEL_COLORS: [AnsiColors; 5] = [
     AnsiColors::BrightRed,
@@ -18,18 +24,67 @@ pub const LOG_LEVEL_COLORS: [AnsiColors; 5] = [
     AnsiColors::Cyan,
 ];
 
+/// A log level that can be chosen by the user.
+#[derive(clap::ValueEnum, serde::Deserialize, Copy, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Off,
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for LevelFilter {
+    #[inline]
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as clap::ValueEnum>::from_str(s, true)
+    }
+}
+
 #[cfg(debug_assertions)]
 pub const LOG_LEVEL: LevelFilter = LevelFilter::Debug;
 #[cfg(not(debug_assertions))]
 pub const LOG_LEVEL: LevelFilter = LevelFilter::Info;
 
-/// Initialize the logger with the default format
+/// Initialize the logger with the default format, logging at the given level.
+/// Logs are written to stderr, with colors according to the given color choice.
+///
+/// If a log file is given, logs are also appended to it as JSON lines (with a timestamp, level, target and message).
+/// If the log file can't be opened, the logger is still initialized but will only write to stderr.
 #[cold]
-pub(crate) fn setup_logger() {
-    env_logger::builder()
+pub(crate) fn setup_logger(
+    level: LevelFilter,
+    color: ColorChoice,
+    log_file: Option<&Path>,
+) -> io::Result<()> {
+    let write_style = match color {
+        ColorChoice::Auto => WriteStyle::Auto,
+        ColorChoice::Always => WriteStyle::Always,
+        ColorChoice::Never => WriteStyle::Never,
+    };
+
+    let stderr = env_logger::builder()
         .parse_default_env()
-        .filter_level(LOG_LEVEL)
-        .write_style(WriteStyle::Auto)
+        .filter_level(level)
+        .write_style(write_style)
         .format(|formatter, record| {
             let level = record.level();
             // levels start at ordinal 1, so we need to shift them down by 1
@@ -42,7 +97,78 @@ pub(crate) fn setup_logger() {
                 message = record.args()
             )
         })
-        .init();
+        .build();
+
+    let file = log_file
+        .map(|path| OpenOptions::new().create(true).append(true).open(path))
+        .transpose();
+
+    let (file, result) = match file {
+        Ok(file) => (file, Ok(())),
+        Err(err) => (None, Err(err)),
+    };
+
+    log::set_max_level(stderr.filter());
+    log::set_boxed_logger(Box::new(CliLogger {
+        stderr,
+        file: file.map(Mutex::new),
+    }))
+    .expect("the logger is only initialized once");
+
+    result
+}
+
+/// A logger that writes human readable logs to stderr, and optionally structured logs (JSON lines) to a file.
+struct CliLogger {
+    stderr: env_logger::Logger,
+    file: Option<Mutex<File>>,
+}
+
+impl log::Log for CliLogger {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.stderr.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.stderr.matches(record) {
+            return;
+        }
+
+        self.stderr.log(record);
+
+        if let Some(file) = &self.file {
+            let line = serde_json::json!({
+                "timestamp": Utc::now().to_rfc3339(),
+                "level": record.level().as_str(),
+                "target": record.target(),
+                "message": record.args().to_string(),
+            });
+
+            let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+            // there's nowhere to report errors with logging to
+            let _ = writeln!(file, "{line}");
+        }
+    }
+
+    fn flush(&self) {
+        self.stderr.flush();
+
+        if let Some(file) = &self.file {
+            let _ = file.lock().unwrap_or_else(PoisonError::into_inner).flush();
+        }
+    }
+}
+
+/// Adjust a log level by a number of steps, where positive steps make the level more verbose (e.g., from info to debug)
+/// and negative steps make it less verbose. The level is clamped between [`LevelFilter::Off`] and [`LevelFilter::Trace`].
+#[inline]
+#[must_use]
+pub fn adjust_log_level(level: LevelFilter, steps: i64) -> LevelFilter {
+    let index = (level as i64 + steps).clamp(0, LevelFilter::max() as i64);
+
+    LevelFilter::iter()
+        .nth(index as usize)
+        .expect("the index is clamped to the valid levels")
 }
 
 /// Get the attachment file name from a 'content-disposition' header.
@@ -117,6 +243,108 @@ pub fn validate_file_name(file_name_candidate: &Path) -> bool {
     true
 }
 
+/// The style that a [`CliTable`] is written in.
+#[derive(clap::ValueEnum, serde::Deserialize, Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    /// Borders drawn with ASCII characters.
+    #[default]
+    Ascii,
+    /// Borders drawn with Unicode box-drawing characters.
+    Unicode,
+    /// No borders and no colors, with columns separated by spaces. Meant for output that isn't a terminal.
+    Plain,
+}
+
+impl FromStr for TableStyle {
+    type Err = String;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        <Self as clap::ValueEnum>::from_str(s, true)
+    }
+}
+
+impl TableStyle {
+    /// The total width of the borders and padding between the columns of a table with the given number of columns.
+    #[inline]
+    fn border_width(self, columns: usize) -> usize {
+        match self {
+            Self::Ascii | Self::Unicode => (columns * 3) + 1,
+            Self::Plain => columns.saturating_sub(1) * PLAIN_COLUMN_SEPARATOR.len(),
+        }
+    }
+
+    /// The character used for vertical borders.
+    #[inline]
+    fn vertical_border(self) -> char {
+        match self {
+            Self::Unicode => '│',
+            _ => '|',
+        }
+    }
+}
+
+/// The separator between columns in a [`TableStyle::Plain`] table.
+const PLAIN_COLUMN_SEPARATOR: &str = "  ";
+
+/// Columns are never shrunk below this width to fit a table into the terminal.
+const MIN_COLUMN_WIDTH: usize = 8;
+
+/// How the text in a column of a [`CliTable`] is aligned.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Alignment {
+    #[default]
+    Left,
+    /// Right alignment, useful for numeric columns.
+    Right,
+}
+
+/// The position of a horizontal rule in a [`CliTable`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum RulePosition {
+    Top,
+    Middle,
+    Bottom,
+}
+
+/// Get the width of the text when displayed in a terminal.
+/// Unlike [`str::len`], this accounts for multi-byte and wide (e.g., CJK) characters.
+#[inline]
+#[must_use]
+pub fn display_width(text: &str) -> usize {
+    UnicodeWidthStr::width(text)
+}
+
+/// Truncate the text so that its display width is at most `width`. Truncated text ends with an ellipsis.
+#[inline]
+#[must_use]
+pub fn truncate_to_width(text: &str, width: usize) -> Cow<'_, str> {
+    if display_width(text) <= width {
+        return Cow::Borrowed(text);
+    }
+
+    // leave room for the ellipsis
+    let mut remaining = width.saturating_sub(1);
+    let mut truncated = String::new();
+
+    for c in text.chars() {
+        let char_width = UnicodeWidthChar::width(c).unwrap_or(0);
+        if char_width > remaining {
+            break;
+        }
+
+        remaining -= char_width;
+        truncated.push(c);
+    }
+
+    if width > 0 {
+        truncated.push('…');
+    }
+
+    Cow::Owned(truncated)
+}
+
 /// A row in a [`CliTable`], holding a list of the cells in the row.
 ///
 /// May be indexed to access the contained cells.
@@ -182,50 +410,105 @@ impl CliTableRow {
         }
     }
 
-    /// Write this table row to the formatter.
-    /// Columns will be padded until they reach their width as described in the `widths` slice.
+    /// Write this table row to the formatter in the given style.
+    /// Columns will be padded until they reach their width as described in the `widths` slice,
+    /// and cells that are wider than their column will be truncated.
     ///
     /// Will not write a newline at the end.
     ///
     /// # Panics
-    /// Will panic if the length of `width` is not the same as the number of columns in this row,
-    /// or if a cell in this row is wider than the width of its column in `widths`
+    /// Will panic if the length of `widths` or `alignments` is not the same as the number of columns in this row.
     #[inline]
-    pub fn write(&self, f: &mut fmt::Formatter, widths: &[usize]) -> fmt::Result {
+    pub fn write(
+        &self,
+        f: &mut impl fmt::Write,
+        widths: &[usize],
+        alignments: &[Alignment],
+        style: TableStyle,
+    ) -> fmt::Result {
         assert_eq!(widths.len(), self.columns(), "Number of columns must match");
+        assert_eq!(
+            alignments.len(),
+            self.columns(),
+            "Number of columns must match"
+        );
 
-        for i in 0..self.columns() {
-            let cell = &self[i];
-            let target_width = widths[i];
+        if style == TableStyle::Plain {
+            return self.write_plain(f, widths, alignments);
+        }
 
-            // the number of spaces to insert on the right of the field
-            let right_padding = target_width - cell.width();
+        let border = style.vertical_border();
 
-            // leftward cell border, also the rightward cell border of the leftward cell
-            write!(f, "{}", '|'.on_color(self.bg_color).dimmed())?;
+        for (i, cell) in self.cells.iter().enumerate() {
+            let text = truncate_to_width(&cell.text, widths[i]);
+            let (left_padding, right_padding) = alignments[i].padding(widths[i], &text);
 
-            // padding against the leftward cell border
-            write!(f, "{}", ' '.on_color(self.bg_color))?;
+            // leftward cell border, also the rightward cell border of the leftward cell
+            write!(f, "{}", border.on_color(self.bg_color).dimmed())?;
 
-            // writing the text
+            // padding against the leftward cell border, and padding to fit the column width
             write!(
                 f,
                 "{}",
-                &cell.text.on_color(self.bg_color).color(cell.color)
+                " ".repeat(left_padding + 1).on_color(self.bg_color)
             )?;
 
-            // padding to fit the column width
-            write!(f, "{}", &" ".repeat(right_padding).on_color(self.bg_color))?;
+            // writing the text
+            write!(f, "{}", text.on_color(self.bg_color).color(cell.color))?;
 
-            // padding against the rightward cell border
-            write!(f, "{}", ' '.on_color(self.bg_color))?;
+            // padding to fit the column width, and padding against the rightward cell border
+            write!(
+                f,
+                "{}",
+                " ".repeat(right_padding + 1).on_color(self.bg_color)
+            )?;
         }
 
         // rightmost cell border
-        write!(f, "{}", '|'.on_color(self.bg_color).dimmed())?;
+        write!(f, "{}", border.on_color(self.bg_color).dimmed())?;
 
         Ok(())
     }
+
+    /// Write this table row without borders or colors.
+    #[inline]
+    fn write_plain(
+        &self,
+        f: &mut impl fmt::Write,
+        widths: &[usize],
+        alignments: &[Alignment],
+    ) -> fmt::Result {
+        let mut line = String::new();
+
+        for (i, cell) in self.cells.iter().enumerate() {
+            if i > 0 {
+                line.push_str(PLAIN_COLUMN_SEPARATOR);
+            }
+
+            let text = truncate_to_width(&cell.text, widths[i]);
+            let (left_padding, right_padding) = alignments[i].padding(widths[i], &text);
+
+            line.push_str(&" ".repeat(left_padding));
+            line.push_str(&text);
+            line.push_str(&" ".repeat(right_padding));
+        }
+
+        // trailing whitespace is useless in plain output
+        write!(f, "{}", line.trim_end())
+    }
+}
+
+impl Alignment {
+    /// The padding on the left and the right of the text to fit it into a column with the given width.
+    #[inline]
+    fn padding(self, width: usize, text: &str) -> (usize, usize) {
+        let padding = width.saturating_sub(display_width(text));
+
+        match self {
+            Self::Left => (0, padding),
+            Self::Right => (padding, 0),
+        }
+    }
 }
 
 /// A cell in a [`CliTable`].
@@ -257,11 +540,11 @@ impl CliTableCell {
         }
     }
 
-    /// The width of the text in this cell.
+    /// The display width of the text in this cell.
     #[inline]
     #[must_use]
     pub fn width(&self) -> usize {
-        self.text.len()
+        display_width(&self.text)
     }
 }
 
@@ -271,6 +554,11 @@ pub struct CliTable {
     /// The names of the columns in the row. Will be printed as a header or footer.
     column_names: CliTableRow,
     rows: Vec<CliTableRow>,
+    /// The alignment of each column.
+    alignments: Vec<Alignment>,
+    style: TableStyle,
+    /// The maximum width of the table. Columns will be shrunk (and their cells truncated) to fit the table into this width.
+    max_width: Option<usize>,
 }
 
 impl CliTable {
@@ -279,11 +567,40 @@ impl CliTable {
     #[inline]
     pub fn new(columns: CliTableRow) -> Self {
         Self {
+            alignments: vec![Alignment::default(); columns.columns()],
             column_names: columns,
             rows: Vec::new(),
+            style: TableStyle::default(),
+            max_width: None,
         }
     }
 
+    /// Set the style that this table is written in.
+    #[inline]
+    #[must_use]
+    pub fn with_style(mut self, style: TableStyle) -> Self {
+        self.style = style;
+        self
+    }
+
+    /// Set the maximum width of this table (usually the width of the terminal).
+    /// If [`None`], the table will be as wide as its content.
+    #[inline]
+    #[must_use]
+    pub fn with_max_width(mut self, max_width: Option<usize>) -> Self {
+        self.max_width = max_width;
+        self
+    }
+
+    /// Set the alignment of the given column.
+    ///
+    /// # Panics
+    /// Will panic if the column doesn't exist.
+    #[inline]
+    pub fn align(&mut self, column: usize, alignment: Alignment) {
+        self.alignments[column] = alignment;
+    }
+
     /// The number of rows in this table.
     #[inline]
     pub fn rows(&self) -> usize {
@@ -333,69 +650,193 @@ impl CliTable {
     #[inline]
     #[must_use]
     pub fn calculate_max_widths(&self) -> Vec<usize> {
-        let mut cols = vec![0usize; self.columns()];
-
-        for i in 0..self.columns() {
-            cols[i] = self.column_names[i].width()
-        }
+        let mut cols = self
+            .column_names
+            .cells
+            .iter()
+            .map(CliTableCell::width)
+            .collect::<Vec<_>>();
 
         for row in self.iter() {
-            for i in 0..self.columns() {
-                cols[i] = max(cols[i], row[i].width())
+            for (col, cell) in cols.iter_mut().zip(&row.cells) {
+                *col = max(*col, cell.width())
             }
         }
 
         cols
     }
 
+    /// Calculate the width of each column, shrinking the widest columns until the table fits into its maximum width.
+    /// Columns are never shrunk below [`MIN_COLUMN_WIDTH`], so the table may still be wider than the maximum width.
+    #[inline]
+    #[must_use]
+    fn calculate_column_widths(&self) -> Vec<usize> {
+        let mut widths = self.calculate_max_widths();
+
+        let Some(max_width) = self.max_width else {
+            return widths;
+        };
+
+        let border_width = self.style.border_width(self.columns());
+        while widths.iter().sum::<usize>() + border_width > max_width {
+            let Some(widest) = widths
+                .iter_mut()
+                .filter(|width| **width > MIN_COLUMN_WIDTH)
+                .max_by_key(|width| **width)
+            else {
+                break;
+            };
+
+            *widest -= 1;
+        }
+
+        widths
+    }
+
     /// Iterate over the rows in this table, in order of insertion.
     #[inline]
     pub fn iter(&self) -> impl Iterator<Item = &CliTableRow> + use<'_> {
         self.rows.iter()
     }
-}
 
-/// Calculate the width of a table's borders and their padding.
-#[inline]
-fn calculate_border_widths(columns: usize) -> usize {
-    (columns * 3) + 1
+    /// Write a horizontal rule spanning the table.
+    #[inline]
+    fn write_rule(
+        &self,
+        f: &mut fmt::Formatter<'_>,
+        widths: &[usize],
+        position: RulePosition,
+    ) -> fmt::Result {
+        let rule = match self.style {
+            TableStyle::Plain => return Ok(()),
+            TableStyle::Ascii => {
+                let total_width =
+                    widths.iter().sum::<usize>() + self.style.border_width(widths.len());
+                "-".repeat(total_width)
+            }
+            TableStyle::Unicode => {
+                let (left, junction, right) = match position {
+                    RulePosition::Top => ('┌', '┬', '┐'),
+                    RulePosition::Middle => ('├', '┼', '┤'),
+                    RulePosition::Bottom => ('└', '┴', '┘'),
+                };
+
+                let segments = widths
+                    .iter()
+                    .map(|width| "─".repeat(width + 2))
+                    .collect::<Vec<_>>();
+
+                format!("{left}{}{right}", segments.join(&junction.to_string()))
+            }
+        };
+
+        write!(f, "{}", rule.dimmed())
+    }
 }
 
 impl fmt::Display for CliTable {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        // we should handle the case where the table is completely empty
+        // a table without columns has nothing to write
         if self.columns() == 0 {
-            todo!()
+            return Ok(());
         }
 
-        // Find the maximum width of each column. Fields will be padded until they are equal to the maximum width.
-        let max_column_widths = self.calculate_max_widths();
+        // Find the width of each column. Fields will be padded (or truncated) until they are equal to the width.
+        let column_widths = self.calculate_column_widths();
 
-        // the total width the table takes up
-        let total_table_width =
-            max_column_widths.iter().sum::<usize>() + calculate_border_widths(self.columns());
+        // whether a line has been written yet, lines after the first are preceded by a newline
+        let mut first_line = true;
+        let mut new_line = |f: &mut fmt::Formatter<'_>| -> fmt::Result {
+            if !std::mem::take(&mut first_line) {
+                writeln!(f)?;
+            }
+
+            Ok(())
+        };
+
+        if self.style == TableStyle::Unicode {
+            new_line(f)?;
+            self.write_rule(f, &column_widths, RulePosition::Top)?;
+        }
 
         // write the column headers if they're not empty
         if !self.column_names.is_empty() {
-            self.column_names.write(f, &max_column_widths)?;
-            // newline after the headers
-            writeln!(f)?;
+            new_line(f)?;
+            self.column_names
+                .write(f, &column_widths, &self.alignments, self.style)?;
+
             // a horizontal separator underneath the headers
-            write!(f, "{}", "-".repeat(total_table_width).dimmed())?;
+            if self.style != TableStyle::Plain {
+                new_line(f)?;
+                self.write_rule(f, &column_widths, RulePosition::Middle)?;
+            }
         }
 
         // write the table contents with appropriate padding
         for row in self.iter() {
-            // new line for a new row
-            writeln!(f)?;
+            new_line(f)?;
+            row.write(f, &column_widths, &self.alignments, self.style)?;
+        }
 
-            row.write(f, &max_column_widths)?;
+        if self.style == TableStyle::Unicode {
+            new_line(f)?;
+            self.write_rule(f, &column_widths, RulePosition::Bottom)?;
         }
 
         Ok(())
     }
 }
 
+/// The maximum number of suggestions in a "did you mean" help message.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Find the candidates that are closest to the target by edit distance (ignoring case), closest first.
+/// Candidates that are too different from the target to be a likely typo are left out.
+#[inline]
+pub fn closest_matches<'a>(
+    target: &str,
+    candidates: impl IntoIterator<Item = &'a str>,
+) -> Vec<&'a str> {
+    let target_lowercase = target.to_lowercase();
+    let max_distance = (target.chars().count() / 3).max(2);
+
+    let mut matches = candidates
+        .into_iter()
+        .map(|candidate| {
+            let distance = strsim::levenshtein(&target_lowercase, &candidate.to_lowercase());
+            (distance, candidate)
+        })
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect::<Vec<_>>();
+
+    matches.sort_unstable();
+    matches.dedup();
+
+    matches
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// A help message like "Did you mean 'a', 'b' or 'c'?" for the given suggestions.
+/// Returns [`None`] if there are no suggestions.
+#[inline]
+pub fn did_you_mean(suggestions: &[impl fmt::Display]) -> Option<String> {
+    let quoted = suggestions
+        .iter()
+        .map(|suggestion| format!("'{suggestion}'"))
+        .collect::<Vec<_>>();
+
+    let list = match quoted.as_slice() {
+        [] => return None,
+        [only] => only.clone(),
+        [rest @ .., last] => format!("{} or {last}", rest.join(", ")),
+    };
+
+    Some(format!("Did you mean {list}?"))
+}
+
 /// Return early with an `Ok(None)` if the result of the given expression is [`None`].
 /// Otherwise return the value contained in [`Some`].
 #[macro_export]
@@ -407,3 +848,47 @@ macro_rules! ok_none {
         }
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_without_columns() {
+        let table = CliTable::new(CliTableRow::new(&[]));
+        assert_eq!(table.to_string(), "");
+    }
+
+    #[test]
+    fn test_plain_table_pads_by_display_width() {
+        let mut table = CliTable::new(CliTableRow::new(&["Name".into(), "ID".into()]))
+            .with_style(TableStyle::Plain);
+        table.align(1, Alignment::Right);
+        table.add(CliTableRow::new(&["漢字".into(), "7".into()]));
+        table.add(CliTableRow::new(&["Übersetzung".into(), "123".into()]));
+
+        assert_eq!(
+            table.to_string(),
+            "Name          ID\n漢字           7\nÜbersetzung  123"
+        );
+    }
+
+    #[test]
+    fn test_truncate_to_width() {
+        assert_eq!(truncate_to_width("short", 10), "short");
+        assert_eq!(truncate_to_width("漢字漢字", 5), "漢字…");
+    }
+
+    #[test]
+    fn test_closest_matches() {
+        let candidates = ["worldedit", "worldguard", "essentials", "WorldEdit-Extra"];
+
+        assert_eq!(closest_matches("wordledit", candidates), vec!["worldedit"]);
+        assert_eq!(closest_matches("WORLDGARD", candidates), vec!["worldguard"]);
+        assert!(closest_matches("luckperms", candidates).is_empty());
+        assert_eq!(
+            did_you_mean(&["a", "b", "c"]).as_deref(),
+            Some("Did you mean 'a', 'b' or 'c'?")
+        );
+    }
+}
@@ -0,0 +1,778 @@
+//! Lenient parsing of plugin version names, and requirements that version names can be matched against.
+//!
+//! Plugin versions rarely follow semver exactly (like `2.0.0-SNAPSHOT-b123` or `v1.9`), so the leading numbers of a
+//! version name are used as its version, and anything after them is ignored. Version names are ordered with
+//! [`compare_version_names`], which also takes qualifiers like `-beta` into account.
+
+use std::{cmp::Ordering, fmt, hash::Hash, str::FromStr};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use regex::Regex;
+
+/// The numeric part of a version name, like `2.0.0` for `v2.0.0-SNAPSHOT-b123`.
+/// Missing parts are treated as zero when comparing, so `1.2` and `1.2.0` are equal.
+#[derive(Clone, Debug)]
+pub struct LenientVersion(Vec<u64>);
+
+impl LenientVersion {
+    /// Parse the leading numbers of a version name (after an optional `v`).
+    /// Returns [`None`] if the version name doesn't start with a number.
+    #[inline]
+    pub fn parse(version_name: &str) -> Option<Self> {
+        let version_name = version_name.trim();
+        let version_name = version_name
+            .strip_prefix(['v', 'V'])
+            .unwrap_or(version_name);
+
+        let mut parts = Vec::new();
+        for part in version_name.split('.') {
+            let digits = part
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(part, |end| &part[..end]);
+            let Ok(number) = digits.parse() else {
+                break;
+            };
+
+            parts.push(number);
+            // anything after the digits (like '-SNAPSHOT') ends the version
+            if digits.len() != part.len() {
+                break;
+            }
+        }
+
+        (!parts.is_empty()).then_some(Self(parts))
+    }
+
+    #[inline]
+    fn part(&self, index: usize) -> u64 {
+        self.0.get(index).copied().unwrap_or(0)
+    }
+}
+
+impl PartialEq for LenientVersion {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for LenientVersion {}
+
+impl PartialOrd for LenientVersion {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for LenientVersion {
+    #[inline]
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.0.len().max(other.0.len());
+        (0..len)
+            .map(|index| self.part(index).cmp(&other.part(index)))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// The operator of a [`Comparator`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+enum Op {
+    /// `^1.2`: compatible versions, where the first non-zero part is the same.
+    Caret,
+    /// `~1.2`: versions with the same major and minor parts (or major part, if only that is given).
+    Tilde,
+    /// `=1.2`: versions that start with the given parts.
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+}
+
+/// A single comparison in a [`VersionReq`], like `>=1.2`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Comparator {
+    op: Op,
+    parts: Vec<u64>,
+}
+
+impl Comparator {
+    #[inline]
+    fn matches(&self, version: &LenientVersion) -> bool {
+        let bound = LenientVersion(self.parts.clone());
+
+        match self.op {
+            Op::Exact => self
+                .parts
+                .iter()
+                .enumerate()
+                .all(|(index, &part)| version.part(index) == part),
+            Op::Greater => *version > bound,
+            Op::GreaterEq => *version >= bound,
+            Op::Less => *version < bound,
+            Op::LessEq => *version <= bound,
+            Op::Caret => {
+                // the first non-zero part (or the last given part) can't change
+                let fixed = self
+                    .parts
+                    .iter()
+                    .position(|&part| part != 0)
+                    .unwrap_or(self.parts.len() - 1);
+                *version >= bound && self.same_prefix(version, fixed + 1)
+            }
+            Op::Tilde => *version >= bound && self.same_prefix(version, self.parts.len().min(2)),
+        }
+    }
+
+    /// Whether the first `len` parts of the version are the same as the parts of this comparator.
+    #[inline]
+    fn same_prefix(&self, version: &LenientVersion, len: usize) -> bool {
+        (0..len).all(|index| version.part(index) == self.parts[index])
+    }
+}
+
+/// A semver-style version requirement like `^2.3`, `~1.9` or `>=1.2, <2`, matched against the leading numbers
+/// of version names (see [`LenientVersion`]). Multiple comparisons are separated by commas, and must all match.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct VersionReq {
+    comparators: Vec<Comparator>,
+    source: String,
+}
+
+/// The error returned by [`<VersionReq as FromStr>::from_str`].
+#[derive(thiserror::Error, miette::Diagnostic, Debug, Clone)]
+#[error("'{0}' is not a version requirement")]
+#[diagnostic(help(
+    "Version requirements are an operator ('^', '~', '=', '>', '>=', '<' or '<=') followed by a version, like '^2.3'. Use commas to combine them."
+))]
+pub struct VersionReqParseError(pub String);
+
+impl VersionReq {
+    /// Check if the version name matches this requirement. Version names that don't start with a number never match.
+    #[inline]
+    pub fn matches(&self, version_name: &str) -> bool {
+        LenientVersion::parse(version_name).is_some_and(|version| {
+            self.comparators
+                .iter()
+                .all(|comparator| comparator.matches(&version))
+        })
+    }
+
+    /// Whether the string looks like a version requirement (i.e., it starts with an operator).
+    #[inline]
+    pub fn is_requirement(s: &str) -> bool {
+        s.trim_start().starts_with(['^', '~', '=', '>', '<'])
+    }
+}
+
+impl FromStr for VersionReq {
+    type Err = VersionReqParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || VersionReqParseError(s.to_string());
+
+        let comparators = s
+            .split(',')
+            .map(|comparator| {
+                let comparator = comparator.trim();
+                let (op, version) = [
+                    (">=", Op::GreaterEq),
+                    ("<=", Op::LessEq),
+                    (">", Op::Greater),
+                    ("<", Op::Less),
+                    ("=", Op::Exact),
+                    ("^", Op::Caret),
+                    ("~", Op::Tilde),
+                ]
+                .into_iter()
+                .find_map(|(prefix, op)| comparator.strip_prefix(prefix).map(|rest| (op, rest)))
+                .ok_or_else(error)?;
+
+                let parts = version
+                    .trim()
+                    .split('.')
+                    .map(u64::from_str)
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| error())?;
+
+                Ok(Comparator { op, parts })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self {
+            comparators,
+            source: s.trim().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for VersionReq {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+/// A regex that version names are matched against. The regex can match any part of a name, unless it's anchored with `^` and `$`.
+#[derive(Clone, Debug)]
+pub struct VersionPattern(Regex);
+
+impl VersionPattern {
+    /// Check if the version name matches the regex.
+    #[inline]
+    pub fn matches(&self, version_name: &str) -> bool {
+        self.0.is_match(version_name)
+    }
+}
+
+impl FromStr for VersionPattern {
+    type Err = regex::Error;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Regex::new(s).map(Self)
+    }
+}
+
+impl fmt::Display for VersionPattern {
+    #[inline]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.as_str())
+    }
+}
+
+// regexes are compared by their source, which is enough to tell version specs apart
+impl PartialEq for VersionPattern {
+    #[inline]
+    fn eq(&self, other: &Self) -> bool {
+        self.0.as_str() == other.0.as_str()
+    }
+}
+
+impl Eq for VersionPattern {}
+
+impl Hash for VersionPattern {
+    #[inline]
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.as_str().hash(state);
+    }
+}
+
+/// How stable a plugin version is. Channels are ordered from least stable ([`ReleaseChannel::Snapshot`]) to most stable
+/// ([`ReleaseChannel::Release`]), so a minimum channel can be compared against.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    dm::Display,
+    serde::Deserialize,
+    serde::Serialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum ReleaseChannel {
+    /// Development builds, like nightlies and `-SNAPSHOT` versions.
+    #[display("snapshot")]
+    Snapshot,
+    #[display("alpha")]
+    Alpha,
+    /// Betas, pre-releases and release candidates.
+    #[display("beta")]
+    Beta,
+    #[default]
+    #[display("release")]
+    Release,
+}
+
+/// The error returned by [`<ReleaseChannel as FromStr>::from_str`].
+#[derive(thiserror::Error, miette::Diagnostic, Debug)]
+#[error("'{0}' is not a release channel (release, beta, alpha or snapshot)")]
+pub struct ReleaseChannelParseError(pub String);
+
+impl ReleaseChannel {
+    /// Guess the channel of a version from words in its name, like "beta" in `2.1-beta3` or "SNAPSHOT" in `2.0.0-SNAPSHOT-b123`.
+    /// If the name has words of multiple channels, the least stable channel is used. Names without any of these words are releases.
+    #[inline]
+    pub fn from_version_name(version_name: &str) -> Self {
+        version_name
+            .to_ascii_lowercase()
+            .split(|c: char| !c.is_ascii_alphabetic())
+            .filter_map(|word| match word {
+                "snapshot" | "dev" | "nightly" | "experimental" => Some(Self::Snapshot),
+                "alpha" => Some(Self::Alpha),
+                "beta" | "pre" | "prerelease" | "rc" => Some(Self::Beta),
+                _ => None,
+            })
+            .min()
+            .unwrap_or(Self::Release)
+    }
+}
+
+impl FromStr for ReleaseChannel {
+    type Err = ReleaseChannelParseError;
+
+    #[inline]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "release" => Ok(Self::Release),
+            "beta" => Ok(Self::Beta),
+            "alpha" => Ok(Self::Alpha),
+            "snapshot" => Ok(Self::Snapshot),
+            _ => Err(ReleaseChannelParseError(s.to_string())),
+        }
+    }
+}
+
+/// Whether updating from one version name to another is a breaking change, like `1.9` to `2.0` or `0.4.1` to `0.5`:
+/// the new version is higher, but doesn't match the caret requirement (`^`) of the old version.
+///
+/// Returns `false` if either version name doesn't start with a number.
+#[inline]
+pub fn is_breaking_update(from: &str, to: &str) -> bool {
+    let (Some(from), Some(to)) = (LenientVersion::parse(from), LenientVersion::parse(to)) else {
+        return false;
+    };
+
+    let compatible = Comparator {
+        op: Op::Caret,
+        parts: from.0.clone(),
+    };
+    to > from && !compatible.matches(&to)
+}
+
+/// Which updates of a plugin the 'update' subcommand makes without asking, relative to the version that the plugin is pinned to.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    dm::Display,
+    serde::Deserialize,
+    serde::Serialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum UpdatePolicy {
+    /// Never update the plugin, unless it's updated interactively.
+    #[display("manual")]
+    Manual,
+    /// Only update to versions with the same major and minor version, like `1.4.2` to `1.4.5` (as with `~1.4.2`).
+    #[display("patch")]
+    Patch,
+    /// Only update to versions that aren't breaking updates, like `1.4.2` to `1.6` (as with `^1.4.2`).
+    #[display("minor")]
+    Minor,
+    /// Update to any version.
+    #[default]
+    #[display("any")]
+    Any,
+}
+
+impl UpdatePolicy {
+    /// Whether the policy allows updating from one version name to another.
+    /// The patch and minor policies don't allow downgrades, or updates from or to version names that don't start with a number.
+    #[inline]
+    pub fn allows(self, from: &str, to: &str) -> bool {
+        let op = match self {
+            Self::Manual => return false,
+            Self::Patch => Op::Tilde,
+            Self::Minor => Op::Caret,
+            Self::Any => return true,
+        };
+
+        let (Some(from), Some(to)) = (LenientVersion::parse(from), LenientVersion::parse(to))
+        else {
+            return false;
+        };
+
+        Comparator { op, parts: from.0 }.matches(&to)
+    }
+}
+
+/// How the versions of a plugin are ordered, which decides which version is the "latest" one.
+#[derive(
+    Copy,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    dm::Display,
+    serde::Deserialize,
+    serde::Serialize,
+    clap::ValueEnum,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum VersionOrder {
+    /// By publish date, so the most recently uploaded version is the latest.
+    #[default]
+    #[display("date")]
+    Date,
+    /// By version precedence (see [`compare_version_names`]), so the highest version is the latest,
+    /// even if a hotfix for an older version was uploaded after it.
+    #[display("version")]
+    Version,
+}
+
+/// A version name without a leading 'v', since versions are often named "v1.0" in one place and "1.0" in another.
+#[inline]
+pub fn normalize_version_name(version_name: &str) -> &str {
+    let version_name = version_name.trim();
+    version_name
+        .strip_prefix(['v', 'V'])
+        .unwrap_or(version_name)
+}
+
+/// Compare two version names by their precedence.
+///
+/// Names are compared like Maven versions: they are split into numbers and qualifiers, numbers are compared numerically,
+/// and known qualifiers (in any case) are ordered `alpha < beta < milestone < rc < snapshot < (release) < sp`.
+/// Unknown qualifiers come after all of those, in alphabetical order, and numbers come after every qualifier.
+/// Semver versions are compared the same way, so their pre-release tags are ranked like qualifiers, and their build metadata is ignored.
+///
+/// Zeros and release qualifiers at the end of a name (or before a qualifier) are ignored, so `1.0.0` and `1-final` are equal to `1`.
+/// Every name is compared through the same normalized items, which makes this a total order that can be used for sorting.
+#[inline]
+pub fn compare_version_names(a: &str, b: &str) -> Ordering {
+    let (a, b) = (version_items(a), version_items(b));
+    let release = VersionItem::release();
+
+    let len = a.len().max(b.len());
+    (0..len)
+        .map(|index| {
+            let a = a.get(index).unwrap_or(&release);
+            a.cmp(b.get(index).unwrap_or(&release))
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// A part of a version name in [`compare_version_names`].
+///
+/// Qualifiers are ordered by their rank, and unknown qualifiers by their name. Known qualifiers have no name,
+/// so that aliases like `b` and `beta` are equal. Every qualifier is lower than every number.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum VersionItem {
+    Qualifier(u8, String),
+    Number(u64),
+}
+
+/// The rank of releases among qualifiers, which is also the rank of a missing item.
+const RELEASE_QUALIFIER_RANK: u8 = 5;
+
+/// The rank of qualifiers that aren't known.
+const UNKNOWN_QUALIFIER_RANK: u8 = 7;
+
+impl VersionItem {
+    /// The item of a release, which is what missing items are compared as.
+    #[inline]
+    fn release() -> Self {
+        Self::Qualifier(RELEASE_QUALIFIER_RANK, String::new())
+    }
+
+    /// Create the item for a lowercase qualifier.
+    #[inline]
+    fn qualifier(qualifier: String) -> Self {
+        let rank = match qualifier.as_str() {
+            "alpha" | "a" => 0,
+            "beta" | "b" => 1,
+            "milestone" | "m" => 2,
+            "rc" | "cr" | "pre" => 3,
+            "snapshot" => 4,
+            "ga" | "final" | "release" => RELEASE_QUALIFIER_RANK,
+            "sp" => 6,
+            _ => return Self::Qualifier(UNKNOWN_QUALIFIER_RANK, qualifier),
+        };
+
+        Self::Qualifier(rank, String::new())
+    }
+
+    /// Whether the item can be left out without changing the version, like the zero in `1.0`.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        *self == Self::Number(0) || *self == Self::release()
+    }
+}
+
+/// Split a version name into numbers and qualifiers, at separators and where digits change to letters (or back).
+/// Build metadata (after a `+`) is left out, and so are empty items at the end of the name or before a qualifier.
+#[inline]
+fn version_items(version_name: &str) -> Vec<VersionItem> {
+    let version_name = version_name.trim().to_ascii_lowercase();
+    let version_name = version_name.strip_prefix('v').unwrap_or(&version_name);
+    let version_name = version_name
+        .split_once('+')
+        .map_or(version_name, |(version_name, _)| version_name);
+
+    let mut items = Vec::new();
+    let mut chars = version_name.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if !c.is_ascii_alphanumeric() {
+            chars.next();
+            continue;
+        }
+
+        let digits = c.is_ascii_digit();
+        let mut item = String::new();
+        while let Some(c) =
+            chars.next_if(|c| c.is_ascii_alphanumeric() && c.is_ascii_digit() == digits)
+        {
+            item.push(c);
+        }
+
+        items.push(match item.parse() {
+            Ok(number) if digits => VersionItem::Number(number),
+            _ => VersionItem::qualifier(item),
+        });
+    }
+
+    // from the end, so that `1.0.0` loses both zeros
+    let mut normalized = Vec::<VersionItem>::with_capacity(items.len());
+    for item in items.into_iter().rev() {
+        let before_qualifier = normalized
+            .last()
+            .is_none_or(|next| matches!(next, VersionItem::Qualifier(..)));
+
+        if !(item.is_empty() && before_qualifier) {
+            normalized.push(item);
+        }
+    }
+
+    normalized.reverse();
+    normalized
+}
+
+/// Parse a date cutoff like `2024-06-01` (midnight UTC) or an RFC 3339 datetime like `2024-06-01T12:00:00Z`.
+#[inline]
+pub fn parse_cutoff(s: &str) -> Option<DateTime<Utc>> {
+    let s = s.trim();
+
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Some(datetime.to_utc());
+    }
+
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|datetime| datetime.and_utc())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lenient_version() {
+        let version = LenientVersion::parse("v2.0.0-SNAPSHOT-b123").unwrap();
+        assert_eq!(version.0, [2, 0, 0]);
+        assert_eq!(LenientVersion::parse("1.9b").unwrap().0, [1, 9]);
+        assert_eq!(
+            LenientVersion::parse("5.4.2 (Build 12)").unwrap().0,
+            [5, 4, 2]
+        );
+        assert!(LenientVersion::parse("Build 12").is_none());
+
+        assert_eq!(
+            LenientVersion::parse("1.2").unwrap(),
+            LenientVersion::parse("1.2.0").unwrap()
+        );
+        assert!(LenientVersion::parse("1.10").unwrap() > LenientVersion::parse("1.9.5").unwrap());
+    }
+
+    #[test]
+    fn test_version_req() {
+        let caret = VersionReq::from_str("^2.3").unwrap();
+        assert!(caret.matches("2.3"));
+        assert!(caret.matches("2.9.1-SNAPSHOT-b123"));
+        assert!(!caret.matches("2.2.9"));
+        assert!(!caret.matches("3.0"));
+
+        let caret_zero = VersionReq::from_str("^0.4.1").unwrap();
+        assert!(caret_zero.matches("0.4.7"));
+        assert!(!caret_zero.matches("0.5.0"));
+
+        let tilde = VersionReq::from_str("~1.9").unwrap();
+        assert!(tilde.matches("1.9.4"));
+        assert!(!tilde.matches("1.10"));
+
+        let range = VersionReq::from_str(">=1.2, <2").unwrap();
+        assert!(range.matches("v1.5"));
+        assert!(!range.matches("2.0"));
+        assert_eq!(range.to_string(), ">=1.2, <2");
+
+        let exact = VersionReq::from_str("=4.1").unwrap();
+        assert!(exact.matches("4.1.2"));
+        assert!(!exact.matches("4.2"));
+
+        assert!(!caret.matches("latest build"));
+        assert!(VersionReq::from_str("^x").is_err());
+        assert!(VersionReq::from_str("2.3").is_err());
+    }
+
+    #[test]
+    fn test_is_breaking_update() {
+        assert!(is_breaking_update("1.9.2", "2.0"));
+        assert!(is_breaking_update("v0.4.1", "0.5.0-SNAPSHOT"));
+        assert!(!is_breaking_update("1.9.2", "1.10"));
+        assert!(!is_breaking_update("0.4.1", "0.4.7"));
+        // downgrades and unknown versions aren't breaking updates
+        assert!(!is_breaking_update("2.0", "1.9"));
+        assert!(!is_breaking_update("latest build", "2.0"));
+    }
+
+    #[test]
+    fn test_update_policy() {
+        assert!(UpdatePolicy::Patch.allows("1.4.2", "1.4.5"));
+        assert!(!UpdatePolicy::Patch.allows("1.4.2", "1.5"));
+        assert!(UpdatePolicy::Minor.allows("1.4.2", "1.6"));
+        assert!(!UpdatePolicy::Minor.allows("1.4.2", "2.0"));
+        assert!(!UpdatePolicy::Minor.allows("1.4.2", "1.4.1"));
+        assert!(!UpdatePolicy::Minor.allows("build 12", "build 13"));
+        assert!(UpdatePolicy::Any.allows("1.4.2", "2.0"));
+        assert!(!UpdatePolicy::Manual.allows("1.4.2", "1.4.3"));
+    }
+
+    #[test]
+    fn test_release_channel_from_version_name() {
+        let channel = ReleaseChannel::from_version_name;
+
+        assert_eq!(channel("5.4.2"), ReleaseChannel::Release);
+        assert_eq!(channel("2.1-beta3"), ReleaseChannel::Beta);
+        assert_eq!(channel("1.0.0-RC1"), ReleaseChannel::Beta);
+        assert_eq!(channel("3.0 Pre-Release 2"), ReleaseChannel::Beta);
+        assert_eq!(channel("4.0.0-alpha.1"), ReleaseChannel::Alpha);
+        assert_eq!(channel("2.0.0-SNAPSHOT-b123"), ReleaseChannel::Snapshot);
+        assert_eq!(channel("beta dev build"), ReleaseChannel::Snapshot);
+        // words are only matched as a whole
+        assert_eq!(channel("Devourer 1.2"), ReleaseChannel::Release);
+
+        assert!(ReleaseChannel::Snapshot < ReleaseChannel::Beta);
+    }
+
+    #[test]
+    fn test_normalize_version_name() {
+        assert_eq!(normalize_version_name(" v1.0 "), "1.0");
+        assert_eq!(normalize_version_name("V2"), "2");
+        assert_eq!(normalize_version_name("1.0"), "1.0");
+    }
+
+    #[test]
+    fn test_compare_version_names() {
+        let sorted = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-beta",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "v1.0.1",
+            "1.2",
+            "1.10",
+            "2.0.0-SNAPSHOT-b123",
+            "2.0",
+            "2.0.0.1",
+        ];
+
+        for (index, a) in sorted.iter().enumerate() {
+            for b in &sorted[index + 1..] {
+                assert_eq!(compare_version_names(a, b), Ordering::Less, "{a} < {b}");
+                assert_eq!(compare_version_names(b, a), Ordering::Greater, "{b} > {a}");
+            }
+        }
+
+        assert_eq!(compare_version_names("1.0", "1.0.0"), Ordering::Equal);
+        assert_eq!(compare_version_names("1.0-final", "1.0"), Ordering::Equal);
+        // build metadata doesn't affect semver precedence
+        assert_eq!(
+            compare_version_names("1.0.0+b1", "1.0.0+b2"),
+            Ordering::Equal
+        );
+        assert_eq!(compare_version_names("1.0-sp1", "1.0"), Ordering::Greater);
+        assert_eq!(
+            compare_version_names("1.0-Beta", "1.0-beta"),
+            Ordering::Equal
+        );
+        assert_eq!(
+            compare_version_names("1.0.0-RC.1", "1.0.0-beta.2"),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare_version_names("1.0-rc1", "1.0.0-beta.2"),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_compare_version_names_transitive() {
+        let names = [
+            "1",
+            "1.0",
+            "1.0.0",
+            "1-final",
+            "1.0-sp",
+            "1-sp1",
+            "1-foo",
+            "1.0-foo",
+            "1.0.1",
+            "1-beta",
+            "1.0.0-beta.1",
+            "1.0-SNAPSHOT",
+            "1-RC1",
+            "1.0.0-rc.1",
+            "v1.0.0+build",
+            "2.0.0-SNAPSHOT-b123",
+            "2.0",
+            "0.9",
+        ];
+
+        for a in names {
+            for b in names {
+                assert_eq!(
+                    compare_version_names(a, b),
+                    compare_version_names(b, a).reverse(),
+                    "{a} <> {b}"
+                );
+
+                for c in names {
+                    let (ab, bc, ac) = (
+                        compare_version_names(a, b),
+                        compare_version_names(b, c),
+                        compare_version_names(a, c),
+                    );
+                    if ab == bc || bc.is_eq() {
+                        assert_eq!(ac, ab, "{a} {ab:?} {b} {bc:?} {c}");
+                    } else if ab.is_eq() {
+                        assert_eq!(ac, bc, "{a} {ab:?} {b} {bc:?} {c}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_cutoff() {
+        assert_eq!(
+            parse_cutoff("2024-06-01").unwrap().to_rfc3339(),
+            "2024-06-01T00:00:00+00:00"
+        );
+        assert_eq!(
+            parse_cutoff("2024-06-01T12:30:00+02:00")
+                .unwrap()
+                .to_rfc3339(),
+            "2024-06-01T10:30:00+00:00"
+        );
+        assert!(parse_cutoff("June").is_none());
+    }
+}